@@ -0,0 +1,284 @@
+//! Platform-neutral speech logic behind the driver's event handlers.
+//!
+//! The driver translates UI Automation and keyboard events into calls to these
+//! functions, so what Aria says can be tested against a recording engine. Everything
+//! announced here is recorded in the [`SpeechHistory`].
+
+use aria_tts::error::TTSError;
+use aria_tts::queue::{Category, Utterance};
+use aria_tts::state::SpeechState;
+use aria_tts::tts::TTS;
+use aria_utils::clean_text::{clean_text_with, RegexCleanerPair};
+use aria_utils::normalize::Normalizer;
+
+use crate::capitals::CapitalIndicator;
+use crate::history::SpeechHistory;
+
+type Result<T> = std::result::Result<T, TTSError>;
+
+/// Build the text announced when focus lands on an element, e.g. "OK, button".
+/// Empty parts are skipped.
+pub fn focus_text(name: &str, content: &str, control_type_name: &str) -> String {
+    [name, content, control_type_name]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Normalize, clean and speak a focus announcement, replacing whatever was being
+/// said. Runs of the same emoji are read once with their count if `collapse_emoji`
+/// is set.
+pub async fn announce_focus(
+    tts: &TTS,
+    history: &SpeechHistory,
+    info_string: &str,
+    normalizer: &Normalizer,
+    clean_list: &[RegexCleanerPair],
+    collapse_emoji: bool,
+) -> Result<()> {
+    let normalized = normalizer.normalize(info_string);
+    let cleaned_info_string: String = clean_text_with(&normalized, clean_list, collapse_emoji);
+    history.record(&cleaned_info_string, Category::Focus);
+    tts.speak(Utterance::focus(cleaned_info_string)).await
+}
+
+/// Echo a key typed into an input field, setting capital letters apart. Only the
+/// latest key is kept, and a focus announcement is never cut off.
+pub async fn echo_key(
+    tts: &TTS,
+    history: &SpeechHistory,
+    key_name: &str,
+    capitals: &CapitalIndicator,
+) -> Result<()> {
+    history.record(key_name, Category::KeyEcho);
+    tts.speak(capitals.utterance(key_name, Utterance::key_echo))
+        .await
+}
+
+/// Say goodbye and close the engine. Once this begins, nothing but the shutdown
+/// message is spoken and it cannot be stopped.
+pub async fn shut_down(tts: &TTS) -> Result<()> {
+    tts.set_state(SpeechState::ShuttingDown).await?;
+    tts.speak_and_wait(Utterance::system("Aria shutting down."))
+        .await?;
+    tts.set_state(SpeechState::Stopped).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aria_tts::engine::RecordingEngine;
+    use aria_utils::config::{CapitalIndication, Language, NumberMode};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    async fn recording_tts() -> (Arc<RecordingEngine>, TTS, SpeechHistory) {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        tts.set_state(SpeechState::Running).await.unwrap();
+        (engine, tts, SpeechHistory::new(10))
+    }
+
+    /// Let the speech queue catch up. Time is paused, so this returns as soon as
+    /// every task is idle.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    #[test]
+    fn focus_text_skips_empty_parts() {
+        assert_eq!(focus_text("OK", "", "button"), "OK, button");
+        assert_eq!(focus_text("", "", "pane"), "pane");
+        assert_eq!(focus_text("", "", ""), "");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn focus_is_cleaned_and_interrupts() {
+        let (engine, tts, history) = recording_tts().await;
+        let normalizer = Normalizer::default();
+        let clean_list = RegexCleanerPair::prep_list(&[(r"\s+", " ")]).unwrap();
+
+        echo_key(&tts, &history, "A", &CapitalIndicator::default())
+            .await
+            .unwrap();
+        settle().await;
+        announce_focus(
+            &tts,
+            &history,
+            "File\n\t menu, menu item",
+            &normalizer,
+            &clean_list,
+            false,
+        )
+        .await
+        .unwrap();
+        settle().await;
+
+        assert_eq!(engine.spoken_texts(), vec!["A", "File menu, menu item"]);
+        assert_eq!(engine.stop_count(), 1);
+        let recorded: Vec<_> = history.entries().into_iter().map(|e| e.text).collect();
+        assert_eq!(recorded, vec!["A", "File menu, menu item"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn focus_is_normalized_before_cleaning() {
+        let (engine, tts, history) = recording_tts().await;
+        let normalizer = Normalizer::new(Language::En, NumberMode::Whole);
+        let clean_list = RegexCleanerPair::prep_list(&[(r"fifty", "half")]).unwrap();
+
+        announce_focus(
+            &tts,
+            &history,
+            "Volume 50%, slider",
+            &normalizer,
+            &clean_list,
+            false,
+        )
+        .await
+        .unwrap();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["Volume half percent, slider"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn emoji_runs_collapse_on_request() {
+        let (engine, tts, history) = recording_tts().await;
+        let normalizer = Normalizer::default();
+        let clean_list = RegexCleanerPair::prep_list(&[(r"\s+", " ")]).unwrap();
+
+        for collapse_emoji in [false, true] {
+            announce_focus(
+                &tts,
+                &history,
+                "👍👍👍👍",
+                &normalizer,
+                &clean_list,
+                collapse_emoji,
+            )
+            .await
+            .unwrap();
+            settle().await;
+        }
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["thumbs up thumbs up thumbs up", "thumbs up times 4"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn key_echo_waits_for_focus() {
+        let (engine, tts, history) = recording_tts().await;
+        let normalizer = Normalizer::default();
+        let clean_list = RegexCleanerPair::prep_list(&[(r"\s+", " ")]).unwrap();
+
+        announce_focus(
+            &tts,
+            &history,
+            "Search, edit",
+            &normalizer,
+            &clean_list,
+            false,
+        )
+        .await
+        .unwrap();
+        settle().await;
+        echo_key(&tts, &history, "A", &CapitalIndicator::default())
+            .await
+            .unwrap();
+        echo_key(&tts, &history, "Space", &CapitalIndicator::default())
+            .await
+            .unwrap();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["Search, edit"]);
+        assert_eq!(engine.stop_count(), 0);
+
+        engine.finish_playback();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["Search, edit", "Space"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn capitals_are_set_apart_in_key_echo() {
+        let (engine, tts, history) = recording_tts().await;
+        let capitals =
+            CapitalIndicator::new(&[CapitalIndication::Say, CapitalIndication::Pitch], 50.0);
+
+        for key in ["a", "A"] {
+            echo_key(&tts, &history, key, &capitals).await.unwrap();
+            settle().await;
+            engine.finish_playback();
+            settle().await;
+        }
+
+        assert_eq!(engine.spoken_texts(), vec!["a", "cap A"]);
+        let pitches: Vec<_> = engine
+            .utterances()
+            .iter()
+            .map(|u| u.prosody.pitch)
+            .collect();
+        assert_eq!(pitches, vec![None, Some(1.5)]);
+        let recorded: Vec<_> = history.entries().into_iter().map(|e| e.text).collect();
+        assert_eq!(recorded, vec!["a", "A"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn only_the_shutdown_message_follows_shut_down() {
+        let (engine, tts, history) = recording_tts().await;
+        let normalizer = Normalizer::default();
+        let clean_list = RegexCleanerPair::prep_list(&[(r"\s+", " ")]).unwrap();
+        announce_focus(
+            &tts,
+            &history,
+            "Search, edit",
+            &normalizer,
+            &clean_list,
+            false,
+        )
+        .await
+        .unwrap();
+        settle().await;
+        tts.speak(Utterance::notification("Download complete"))
+            .await
+            .unwrap();
+
+        let mut states = tts.subscribe_state();
+        let shutdown = tokio::spawn({
+            let tts = tts.clone();
+            async move { shut_down(&tts).await }
+        });
+        states
+            .wait_for(|state| *state == SpeechState::ShuttingDown)
+            .await
+            .unwrap();
+        announce_focus(
+            &tts,
+            &history,
+            "OK, button",
+            &normalizer,
+            &clean_list,
+            false,
+        )
+        .await
+        .unwrap();
+        echo_key(&tts, &history, "A", &CapitalIndicator::default())
+            .await
+            .unwrap();
+        tts.stop().await.unwrap();
+        settle().await;
+        engine.finish_playback();
+        shutdown.await.unwrap().unwrap();
+
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["Search, edit", "Aria shutting down."]
+        );
+        assert_eq!(tts.state(), SpeechState::Stopped);
+        assert!(engine.is_closed());
+        assert!(tts.speak("Hello").await.is_ok());
+        settle().await;
+        assert_eq!(engine.spoken_texts().len(), 2);
+    }
+}
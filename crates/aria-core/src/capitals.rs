@@ -0,0 +1,128 @@
+//! Setting capital letters apart where characters are read on their own.
+//!
+//! Key echo and spelling read one character at a time, so nothing else tells "a"
+//! from "A". Following `capital_indication` in aria.toml, a capital is preceded by
+//! "cap", said at a higher pitch, accompanied by a short beep, or any combination.
+
+use std::time::Duration;
+
+use aria_tts::queue::{Cue, Utterance};
+use aria_utils::config::{AriaConfig, CapitalIndication};
+
+use crate::sound::play_tone;
+
+const BEEP_FREQUENCY: f32 = 1_200.0;
+const BEEP_DURATION: Duration = Duration::from_millis(40);
+
+/// Whether `text` is a single capital letter, possibly with combining marks.
+pub fn is_capital(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(char::is_uppercase) && !chars.any(char::is_alphabetic)
+}
+
+/// How capital letters are set apart. The default leaves them alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CapitalIndicator {
+    say: bool,
+    /// Factor the pitch is raised by, if it is.
+    pitch: Option<f64>,
+    beep: bool,
+}
+
+impl CapitalIndicator {
+    pub fn new(indications: &[CapitalIndication], pitch_percent: f64) -> Self {
+        CapitalIndicator {
+            say: indications.contains(&CapitalIndication::Say),
+            pitch: indications
+                .contains(&CapitalIndication::Pitch)
+                .then_some(1.0 + pitch_percent / 100.0),
+            beep: indications.contains(&CapitalIndication::Beep),
+        }
+    }
+
+    pub fn from_config(config: &AriaConfig) -> Self {
+        Self::new(&config.capital_indication, config.capital_pitch_percent)
+    }
+
+    /// Build the utterance for the character `text` with `utterance`, set apart if
+    /// it is a capital. The beep, if any, plays as the utterance starts.
+    pub fn utterance(&self, text: &str, utterance: impl FnOnce(String) -> Utterance) -> Utterance {
+        self.mark(text, text.to_string(), utterance)
+    }
+
+    /// Like [`Self::utterance`], but saying `spoken` for `character`, e.g. its
+    /// phonetic word.
+    pub fn mark(
+        &self,
+        character: &str,
+        spoken: String,
+        utterance: impl FnOnce(String) -> Utterance,
+    ) -> Utterance {
+        if !is_capital(character) {
+            return utterance(spoken);
+        }
+        let spoken = if self.say {
+            format!("cap {}", spoken)
+        } else {
+            spoken
+        };
+        let mut utterance = utterance(spoken);
+        if let Some(factor) = self.pitch {
+            utterance = utterance.with_pitch_factor(factor);
+        }
+        if self.beep {
+            utterance = utterance.with_cue(Cue::new(|| play_tone(BEEP_FREQUENCY, BEEP_DURATION)));
+        }
+        utterance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_single_capitals_count() {
+        assert!(is_capital("A"));
+        assert!(is_capital("É"));
+        assert!(is_capital("E\u{301}"));
+        assert!(!is_capital("a"));
+        assert!(!is_capital("1"));
+        assert!(!is_capital("Space"));
+        assert!(!is_capital(""));
+    }
+
+    #[test]
+    fn indications_combine() {
+        let say = CapitalIndicator::new(&[CapitalIndication::Say], 30.0);
+        let utterance = say.utterance("A", Utterance::key_echo);
+        assert_eq!(
+            (utterance.text.as_str(), utterance.pitch_factor),
+            ("cap A", None)
+        );
+
+        let both = CapitalIndicator::new(&[CapitalIndication::Say, CapitalIndication::Pitch], 30.0);
+        let utterance = both.utterance("A", Utterance::key_echo);
+        assert_eq!(
+            (utterance.text.as_str(), utterance.pitch_factor),
+            ("cap A", Some(1.3))
+        );
+
+        let utterance = both.utterance("a", Utterance::key_echo);
+        assert_eq!(
+            (utterance.text.as_str(), utterance.pitch_factor),
+            ("a", None)
+        );
+
+        let utterance = CapitalIndicator::default().utterance("A", Utterance::key_echo);
+        assert_eq!(
+            (utterance.text.as_str(), utterance.pitch_factor),
+            ("A", None)
+        );
+        assert!(utterance.cue.is_none());
+
+        let beep = CapitalIndicator::new(&[CapitalIndication::Beep], 30.0);
+        assert!(beep.utterance("A", Utterance::key_echo).cue.is_some());
+        assert!(beep.utterance("a", Utterance::key_echo).cue.is_none());
+    }
+}
@@ -1,7 +1,15 @@
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+
+use aria_tts::engine::SpeechEngine;
 use aria_tts::error::TTSError;
+use aria_tts::queue::Utterance;
+use aria_tts::state::SpeechState;
 use aria_tts::tts::TTS;
-use aria_utils::clean_text::{clean_text, RegexCleanerPair};
-use aria_utils::config::get_config;
+use aria_utils::config::{get_config, get_config_path};
+use aria_utils::normalize::Normalizer;
+use aria_utils::pronunciation::load_pronunciation;
 use egui::{Pos2 as EguiPos2, Rect as EguiRect};
 use mki::{Action, Keyboard};
 use once_cell::sync::{Lazy, OnceCell as StaticOnceCell};
@@ -13,32 +21,38 @@ use uiautomation::core::UIAutomation;
 use uiautomation::events::{CustomFocusChangedEventHandler, UIFocusChangedEventHandler};
 use uiautomation::UIElement;
 
+use crate::announce::{announce_focus, echo_key, focus_text, shut_down};
+use crate::capitals::CapitalIndicator;
 use crate::error::CoreError;
+use crate::history::{
+    copy_current, repeat_last, review_next, review_previous, spell_current, SpeechHistory,
+    SpellPresses,
+};
+use crate::profiles::{ElementSource, FocusedApp, Profiles};
+use crate::settings::{save_settings, select_setting, step_setting, SettingsRing};
 use crate::sound::{play_sound, INPUT_FOCUSSED_SOUND, SHUTDOWN_SOUND, STARTUP_SOUND};
 
 // Static for Tokio Runtime Handle
 static TOKIO_RUNTIME_HANDLE: StaticOnceCell<TokioHandle> = StaticOnceCell::new();
 static RECT_SENDER: StaticOnceCell<mpsc::Sender<Option<EguiRect>>> = StaticOnceCell::new();
+static SPEECH: StaticOnceCell<TTS> = StaticOnceCell::new();
 
 // Result type alias for this module
 type Result<T> = std::result::Result<T, CoreError>;
 
 struct FocusChangedEventHandler {
     previous_element: Mutex<Option<UIElement>>,
+    tts: TTS,
+    history: Arc<SpeechHistory>,
+    normalizer: Normalizer,
+    /// Chooses the cleaning rules and speech settings for the focused application.
+    profiles: Arc<Profiles>,
+    ring: Arc<SettingsRing>,
+    collapse_emoji: bool,
     // No need to store sender here if using a static OnceCell
 }
 
 static IS_FOCUSSED_ON_INPUT: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
-static CONTENT_CLEAN_LIST: Lazy<Result<Vec<RegexCleanerPair>>> = Lazy::new(|| {
-    RegexCleanerPair::prep_list(&[
-        (r"\s+", " "),
-        (
-            r"(?P<s>[0-9a-f]{6})([0-9]+[a-f]|[a-f]+[0-9])[0-9a-f]*",
-            "hash $s",
-        ),
-    ])
-    .map_err(CoreError::Regex)
-});
 
 impl CustomFocusChangedEventHandler for FocusChangedEventHandler {
     fn handle(&self, sender: &UIElement) -> uiautomation::Result<()> {
@@ -113,8 +127,17 @@ impl CustomFocusChangedEventHandler for FocusChangedEventHandler {
             .trim()
             .to_string();
         let control_type = sender.get_control_type()?;
+        let app = FocusedApp::read(&FocusedElement(sender));
+        self.history.set_app(app.process.clone());
+        self.tts.set_app(app.process.clone());
 
         if let Some(handle) = TOKIO_RUNTIME_HANDLE.get() {
+            let tts = self.tts.clone();
+            let history = self.history.clone();
+            let normalizer = self.normalizer;
+            let profiles = self.profiles.clone();
+            let ring = self.ring.clone();
+            let collapse_emoji = self.collapse_emoji;
             handle.spawn(async move {
                 let mut is_focussed_on_input_lock = IS_FOCUSSED_ON_INPUT.lock().await;
 
@@ -128,37 +151,32 @@ impl CustomFocusChangedEventHandler for FocusChangedEventHandler {
                 }
                 drop(is_focussed_on_input_lock); // Release lock
 
-                let mut parts = Vec::new();
-                if !name.is_empty() {
-                    parts.push(name);
-                }
-                if !content.is_empty() {
-                    parts.push(content);
-                }
-                if !control_type_name.is_empty() {
-                    parts.push(control_type_name);
-                }
-
-                let info_string = parts.join(", ");
-
-                match CONTENT_CLEAN_LIST.as_ref() {
-                    Ok(clean_list) => {
-                        let cleaned_info_string: String = clean_text(&info_string, clean_list);
-                        // Errors from TTS calls in spawned tasks are logged, not mapped to CoreError here.
-                        // The type of `e` here will be TTSError.
-                        if let Err(e) = TTS::stop(false).await {
-                            log::error!("TTS stop failed on focus change: {:?}", e);
-                        }
-                        if let Err(e) = TTS::speak(&cleaned_info_string, false).await {
-                            log::error!("TTS speak failed on focus change: {:?}", e);
-                        }
-                    }
+                let announcement = match profiles.focus(&tts, &ring, &app).await {
+                    Ok(announcement) => announcement,
                     Err(e) => {
-                        log::error!("Failed to get content clean list: {:?}", e);
-                        if let Err(e_tts) = TTS::speak(&info_string, false).await {
-                            log::error!("TTS speak failed on focus change (fallback): {:?}", e_tts);
-                        }
+                        log::error!("Failed to switch profiles: {:?}", e);
+                        None
                     }
+                };
+                let profile = profiles.active();
+                let mut info_string = focus_text(&name, &content, &control_type_name);
+                if let Some(announcement) = announcement {
+                    info_string = format!("{}, {}", announcement, info_string);
+                }
+
+                // Errors from TTS calls in spawned tasks are logged, not mapped to CoreError here.
+                // The type of `e` here will be TTSError.
+                if let Err(e) = announce_focus(
+                    &tts,
+                    &history,
+                    &info_string,
+                    &normalizer,
+                    &profile.clean_list,
+                    collapse_emoji,
+                )
+                .await
+                {
+                    log::error!("TTS speak failed on focus change: {:?}", e);
                 }
             });
         } else {
@@ -170,17 +188,21 @@ impl CustomFocusChangedEventHandler for FocusChangedEventHandler {
 
 // This function is also likely called from a synchronous context (mki callback).
 // Spawn async work to tokio runtime.
-fn on_keypress(key_name: String) {
+fn on_keypress(
+    tts: TTS,
+    history: Arc<SpeechHistory>,
+    profiles: Arc<Profiles>,
+    capitals: CapitalIndicator,
+    key_name: String,
+) {
     log::info!("Key pressed: {}", key_name);
+    let key_name = typed_letter(key_name);
     if let Some(handle) = TOKIO_RUNTIME_HANDLE.get() {
         handle.spawn(async move {
             let is_focussed = IS_FOCUSSED_ON_INPUT.lock().await.clone();
-            if is_focussed {
+            if is_focussed && profiles.active().key_echo {
                 // Errors from TTS calls in spawned tasks are logged.
-                if let Err(e) = TTS::stop(false).await {
-                    log::error!("TTS stop failed on keypress: {:?}", e);
-                }
-                if let Err(e) = TTS::speak(&key_name, false).await {
+                if let Err(e) = echo_key(&tts, &history, &key_name, &capitals).await {
                     log::error!("TTS speak failed on keypress: {:?}", e);
                 }
             }
@@ -190,14 +212,235 @@ fn on_keypress(key_name: String) {
     }
 }
 
+/// Letter keys are named by their capital, e.g. `A`. Turn them into the letter
+/// that was typed, which is lowercase unless exactly one of Shift and Caps Lock is
+/// on.
+fn typed_letter(key_name: String) -> String {
+    use winapi::um::winuser::{GetAsyncKeyState, GetKeyState, VK_CAPITAL, VK_SHIFT};
+
+    if key_name.len() != 1 || !key_name.chars().all(|c| c.is_ascii_uppercase()) {
+        return key_name;
+    }
+    // SAFETY: both calls only read the keyboard state. The hook runs before the
+    // focused thread sees the key, so Shift is read from the physical keyboard.
+    let (shift, caps_lock) = unsafe {
+        (
+            GetAsyncKeyState(VK_SHIFT) as u16 & 0x8000 != 0,
+            GetKeyState(VK_CAPITAL) & 1 != 0,
+        )
+    };
+    if shift != caps_lock {
+        key_name
+    } else {
+        key_name.to_ascii_lowercase()
+    }
+}
+
+/// The focused element, read for the profile of its application.
+struct FocusedElement<'a>(&'a UIElement);
+
+impl ElementSource for FocusedElement<'_> {
+    fn process_name(&self) -> Option<String> {
+        let process_id = self.0.get_process_id().ok()?;
+        process_name(process_id as u32)
+    }
+
+    fn window_class(&self) -> Option<String> {
+        use winapi::um::winuser::{GetClassNameW, GetForegroundWindow};
+
+        let mut buffer = [0u16; 256];
+        // SAFETY: the window is checked before use and `buffer` holds as many
+        // characters as GetClassNameW is told it does.
+        let len = unsafe {
+            let window = GetForegroundWindow();
+            if window.is_null() {
+                return None;
+            }
+            GetClassNameW(window, buffer.as_mut_ptr(), buffer.len() as i32)
+        };
+        (len > 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+}
+
+/// File name of the executable running `process_id`, e.g. `notepad.exe`.
+fn process_name(process_id: u32) -> Option<String> {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winbase::QueryFullProcessImageNameW;
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    // SAFETY: the handle is checked before use and closed afterwards, and `len`
+    // holds the size of `buffer`.
+    let succeeded = unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+        if process.is_null() {
+            return None;
+        }
+        let succeeded = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut len);
+        CloseHandle(process);
+        succeeded != 0
+    };
+    if !succeeded {
+        return None;
+    }
+    let path = String::from_utf16_lossy(&buffer[..len as usize]);
+    Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Run a hotkey command on the Tokio runtime from a keyboard hook.
+fn spawn_command(name: &'static str, command: impl Future<Output = Result<()>> + Send + 'static) {
+    if let Some(handle) = TOKIO_RUNTIME_HANDLE.get() {
+        handle.spawn(async move {
+            if let Err(e) = command.await {
+                log::error!("Command `{}` failed: {:?}", name, e);
+            }
+        });
+    } else {
+        log::error!("Tokio runtime handle not available for command `{}`.", name);
+    }
+}
+
+/// Write the speech history next to aria.toml, as both text and JSON.
+fn export_history(history: &SpeechHistory) -> Result<()> {
+    let config_path = get_config_path().map_err(|e| CoreError::Config(e.to_string()))?;
+    let directory = config_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("history");
+    let stem = chrono::Local::now()
+        .format("history-%Y%m%d-%H%M%S")
+        .to_string();
+    for extension in ["txt", "json"] {
+        let path = directory.join(format!("{}.{}", stem, extension));
+        history.export(&path)?;
+        log::info!("Exported speech history to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Bind the speech history commands:
+///
+/// - Ctrl+Alt+L repeats the last utterance
+/// - Ctrl+Alt+Left and Ctrl+Alt+Right step through earlier ones
+/// - Ctrl+Alt+S spells the one under review, pressed twice quickly phonetically
+///   and three times by Unicode name
+/// - Ctrl+Alt+C copies the one under review
+/// - Ctrl+Alt+E exports the session next to aria.toml
+fn bind_history_hotkeys(tts: &TTS, history: &Arc<SpeechHistory>, capitals: CapitalIndicator) {
+    use Keyboard::*;
+
+    macro_rules! bind {
+        ($key:expr, $name:literal, $command:expr) => {{
+            let tts = tts.clone();
+            let history = history.clone();
+            mki::register_hotkey(&[LeftControl, LeftAlt, $key], move || {
+                let tts = tts.clone();
+                let history = history.clone();
+                spawn_command($name, async move { $command(&tts, &history).await });
+            });
+        }};
+    }
+
+    bind!(L, "repeat", repeat_last);
+    bind!(Left, "previous", review_previous);
+    bind!(Right, "next", review_next);
+    bind!(C, "copy", copy_current);
+
+    let presses = SpellPresses::new();
+    {
+        let tts = tts.clone();
+        let history = history.clone();
+        mki::register_hotkey(&[LeftControl, LeftAlt, S], move || {
+            let tts = tts.clone();
+            let history = history.clone();
+            let level = presses.press();
+            spawn_command("spell", async move {
+                spell_current(&tts, &history, level, &capitals).await
+            });
+        });
+    }
+
+    let tts = tts.clone();
+    let history = history.clone();
+    mki::register_hotkey(&[LeftControl, LeftAlt, E], move || {
+        let tts = tts.clone();
+        let history = history.clone();
+        spawn_command("export", async move {
+            export_history(&history)?;
+            tts.speak(Utterance::review("History exported"))
+                .await
+                .map_err(|e| CoreError::TTS(e.to_string()))
+        });
+    });
+}
+
+/// Bind the settings ring:
+///
+/// - Ctrl+Alt+PageUp and Ctrl+Alt+PageDown select the previous or next setting
+/// - Ctrl+Alt+Up and Ctrl+Alt+Down raise or lower its value, which the active
+///   profile may override
+/// - Ctrl+Alt+W saves the settings to aria.toml
+fn bind_settings_hotkeys(tts: &TTS, ring: &Arc<SettingsRing>, profiles: &Arc<Profiles>) {
+    use Keyboard::*;
+
+    macro_rules! bind {
+        ($key:expr, $name:literal, |$tts:ident, $ring:ident, $profiles:ident| $command:expr) => {{
+            let tts = tts.clone();
+            let ring = ring.clone();
+            let profiles = profiles.clone();
+            mki::register_hotkey(&[LeftControl, LeftAlt, $key], move || {
+                let $tts = tts.clone();
+                let $ring = ring.clone();
+                let $profiles = profiles.clone();
+                spawn_command($name, async move { $command.await });
+            });
+        }};
+    }
+
+    bind!(PageUp, "previous setting", |tts, ring, _profiles| {
+        select_setting(&tts, &ring, -1)
+    });
+    bind!(PageDown, "next setting", |tts, ring, _profiles| {
+        select_setting(&tts, &ring, 1)
+    });
+    bind!(Up, "increase setting", |tts, ring, profiles| {
+        step_setting(&tts, &ring, &profiles, 1)
+    });
+    bind!(Down, "decrease setting", |tts, ring, profiles| {
+        step_setting(&tts, &ring, &profiles, -1)
+    });
+    bind!(W, "save settings", |tts, ring, _profiles| {
+        save_settings(&tts, &ring)
+    });
+}
+
+/// Bind Ctrl+Alt+N to skip the rest of the sentence being read.
+fn bind_skip_hotkey(tts: &TTS) {
+    use Keyboard::*;
+
+    let tts = tts.clone();
+    mki::register_hotkey(&[LeftControl, LeftAlt, N], move || {
+        let tts = tts.clone();
+        spawn_command("skip", async move {
+            tts.skip().await.map_err(|e| CoreError::TTS(e.to_string()))
+        });
+    });
+}
+
 pub struct WindowsDriver {}
 
 impl WindowsDriver {
-    pub async fn start() -> Result<()> {
-        Self::start_with_highlight(None).await
+    pub async fn start(engine: Arc<dyn SpeechEngine>) -> Result<()> {
+        Self::start_with_highlight(engine, None).await
     }
 
+    /// Start the driver, speaking everything through `engine`.
     pub async fn start_with_highlight(
+        engine: Arc<dyn SpeechEngine>,
         highlight_sender: Option<mpsc::Sender<Option<EguiRect>>>,
     ) -> Result<()> {
         // Ensure the Tokio runtime handle is initialized and stored.
@@ -212,24 +455,34 @@ impl WindowsDriver {
 
         let config = get_config().map_err(|e| CoreError::Config(e.to_string()))?;
 
-        // Set TTS to its operational state (able to speak and be stopped by default)
-        TTS::set_can_stop(true)
-            .await
-            .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
-        TTS::set_can_speak(true)
+        let tts = TTS::new(engine);
+        match load_pronunciation().and_then(|dictionaries| dictionaries.compile()) {
+            Ok(pronunciation) => tts.set_pronunciation(pronunciation),
+            Err(e) => log::error!("Ignoring pronunciation dictionaries: {}", e),
+        }
+        tts.set_punctuation(config.punctuation);
+        tts.set_language(config.language)
             .await
             .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
+        let history = Arc::new(SpeechHistory::new(config.history_size as usize));
+        SPEECH
+            .set(tts.clone())
+            .map_err(|_| CoreError::Init("Failed to set SPEECH for driver"))?;
 
         if config.startup_shutdown_sounds {
             play_sound(STARTUP_SOUND);
             tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-            // Speak welcome message, ignoring the CAN_SPEAK flag for this specific utterance.
-            TTS::speak("Welcome to Aria.", true)
+            // Still starting, so nothing can cut off the welcome message.
+            tts.speak_and_wait(Utterance::system("Welcome to Aria."))
                 .await
                 .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
 
+        // Switch to normal operation, where speech can be stopped.
+        tts.set_state(SpeechState::Running)
+            .await
+            .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
+
         // Store the highlight sender if provided
         if let Some(sender) = highlight_sender {
             RECT_SENDER
@@ -237,24 +490,49 @@ impl WindowsDriver {
                 .map_err(|_| CoreError::Init("Failed to set RECT_SENDER for highlighter"))?;
         }
 
-        // Setup event handlers after TTS flags are set for normal operation.
+        let ring = Arc::new(SettingsRing::load(&tts, &config).await?);
+        if let Some(voice) = ring.settings().voice {
+            // Lets the pronunciation dictionary of the voice apply, and sentences in
+            // other languages return to this voice rather than the system's.
+            tts.use_voice(&voice)
+                .await
+                .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
+        }
+        tts.set_language_voices(&config.language_voices)
+            .await
+            .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
+        let profiles = Arc::new(Profiles::load(&tts, &config).await?);
+
+        // Setup event handlers once TTS is running normally.
         let automation = UIAutomation::new()?;
         let focus_changed_handler = FocusChangedEventHandler {
             previous_element: Mutex::new(None),
+            tts: tts.clone(),
+            history: history.clone(),
+            normalizer: Normalizer::from_config(&config),
+            profiles: profiles.clone(),
+            ring: ring.clone(),
+            collapse_emoji: config.collapse_emoji,
         };
         let focus_changed_event_handler = UIFocusChangedEventHandler::from(focus_changed_handler);
         automation
             .add_focus_changed_event_handler(None, &focus_changed_event_handler)
             .map_err(CoreError::UIAutomation)?;
 
+        let capitals = CapitalIndicator::from_config(&config);
+        bind_history_hotkeys(&tts, &history, capitals);
+        bind_settings_hotkeys(&tts, &ring, &profiles);
+        bind_skip_hotkey(&tts);
+
         task::spawn_blocking(move || {
-            mki::bind_any_key(Action::handle_kb(|key| {
+            mki::bind_any_key(Action::handle_kb(move |key| {
                 use Keyboard::*;
                 match key {
                     Escape => {
                         if let Some(handle) = TOKIO_RUNTIME_HANDLE.get() {
-                            handle.spawn(async {
-                                if let Err(e) = TTS::stop(true).await {
+                            let tts = tts.clone();
+                            handle.spawn(async move {
+                                if let Err(e) = tts.stop().await {
                                     log::error!("TTS stop on escape failed: {:?}", e);
                                 }
                             });
@@ -264,7 +542,13 @@ impl WindowsDriver {
                             );
                         }
                     }
-                    _ => on_keypress(format!("{:?}", key)),
+                    _ => on_keypress(
+                        tts.clone(),
+                        history.clone(),
+                        profiles.clone(),
+                        capitals,
+                        format!("{:?}", key),
+                    ),
                 }
             }));
         });
@@ -276,23 +560,14 @@ impl WindowsDriver {
 
         log::info!("Stopping Windows driver.");
 
-        // Disable TTS general speaking/stopping before final shutdown message.
-        TTS::set_can_stop(false)
-            .await
-            .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
-        TTS::set_can_speak(false)
-            .await
-            .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
-
-        // Speak shutdown message, ignoring flags.
-        TTS::speak("Aria shutting down.", true)
-            .await
-            .map_err(|e: TTSError| CoreError::TTS(e.to_string()))?;
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let tts = SPEECH
+            .get()
+            .ok_or(CoreError::Init("Driver stopped before it was started"))?;
 
-        TTS::destroy().await.map_err(|e: TTSError| {
+        // From here on only the shutdown message is spoken, then the engine is closed.
+        shut_down(tts).await.map_err(|e: TTSError| {
             CoreError::TTS(format!(
-                "Failed to destroy TTS: {:?}. This may cause a memory leak.",
+                "Failed to shut down TTS: {:?}. This may cause a memory leak.",
                 e
             ))
         })?;
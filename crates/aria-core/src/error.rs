@@ -2,6 +2,7 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum CoreError {
+    #[cfg(all(windows, feature = "windows"))]
     #[error("UI Automation error: {0}")]
     UIAutomation(#[from] uiautomation::Error),
 
@@ -23,6 +24,12 @@ pub enum CoreError {
     #[error("MKI error: {0}")]
     Mki(String),
 
+    #[error("Clipboard error: {0}")]
+    Clipboard(String),
+
+    #[error("Export error: {0}")]
+    Export(String),
+
     #[error("Regex error: {0}")]
     Regex(#[from] regex::Error),
 }
@@ -0,0 +1,449 @@
+//! What Aria has said this session, for reviewing speech that went by too fast.
+//!
+//! The announce functions record every utterance here. The driver binds the
+//! review commands below to hotkeys; they speak with [`Utterance::review`] and
+//! are not recorded themselves.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use aria_tts::queue::{Category, Policy, Priority, Utterance};
+use aria_tts::tts::TTS;
+use aria_utils::spell::{spell, SpellLevel};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::capitals::CapitalIndicator;
+use crate::error::CoreError;
+
+type Result<T> = std::result::Result<T, CoreError>;
+
+/// One utterance in the [`SpeechHistory`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub text: String,
+    pub time: DateTime<Local>,
+    /// The kind of event that made Aria speak.
+    pub source: Category,
+    /// Executable of the application that had focus, e.g. `notepad.exe`.
+    pub app: Option<String>,
+}
+
+impl HistoryEntry {
+    /// A line of the text export, e.g. `14:03:12 [focus] notepad.exe: File, menu`.
+    fn to_line(&self) -> String {
+        let app = self
+            .app
+            .as_ref()
+            .map(|app| format!(" {}:", app))
+            .unwrap_or_default();
+        format!(
+            "{} [{}]{} {}",
+            self.time.format("%H:%M:%S"),
+            source_name(self.source),
+            app,
+            self.text
+        )
+    }
+}
+
+fn source_name(source: Category) -> &'static str {
+    match source {
+        Category::Focus => "focus",
+        Category::KeyEcho => "key",
+        Category::System => "system",
+        Category::Notification => "notification",
+        Category::Review => "review",
+        Category::Other => "other",
+    }
+}
+
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    text: &'a str,
+    time: String,
+    source: &'static str,
+    app: Option<&'a str>,
+}
+
+#[derive(Debug)]
+struct HistoryState {
+    entries: VecDeque<HistoryEntry>,
+    /// Index of the entry under review; `None` means the latest one.
+    cursor: Option<usize>,
+    /// Application recorded with new entries.
+    app: Option<String>,
+}
+
+/// The most recent utterances, oldest first, with a review cursor.
+///
+/// Recording a new utterance moves the cursor back to it.
+#[derive(Debug)]
+pub struct SpeechHistory {
+    state: Mutex<HistoryState>,
+    capacity: usize,
+}
+
+impl SpeechHistory {
+    /// Keep the last `capacity` utterances.
+    pub fn new(capacity: usize) -> Self {
+        SpeechHistory {
+            state: Mutex::new(HistoryState {
+                entries: VecDeque::new(),
+                cursor: None,
+                app: None,
+            }),
+            capacity,
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, HistoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record later utterances as said in `app`.
+    pub fn set_app(&self, app: Option<String>) {
+        self.state().app = app;
+    }
+
+    /// Record `text` as just spoken because of a `source` event.
+    pub fn record(&self, text: &str, source: Category) {
+        let app = self.state().app.clone();
+        self.push(HistoryEntry {
+            text: text.to_string(),
+            time: Local::now(),
+            source,
+            app,
+        });
+    }
+
+    pub fn push(&self, entry: HistoryEntry) {
+        if self.capacity == 0 || entry.text.trim().is_empty() {
+            return;
+        }
+        let mut state = self.state();
+        if state.entries.len() == self.capacity {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(entry);
+        state.cursor = None;
+    }
+
+    /// Every recorded utterance, oldest first.
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.state().entries.iter().cloned().collect()
+    }
+
+    /// The entry under review, the latest one unless the cursor was moved.
+    pub fn current(&self) -> Option<HistoryEntry> {
+        let state = self.state();
+        match state.cursor {
+            Some(index) => state.entries.get(index).cloned(),
+            None => state.entries.back().cloned(),
+        }
+    }
+
+    /// Move the cursor one entry back. Returns `None` at the oldest entry.
+    pub fn previous(&self) -> Option<HistoryEntry> {
+        let mut state = self.state();
+        let index = match state.cursor {
+            Some(index) => index.checked_sub(1)?,
+            None => state.entries.len().checked_sub(2)?,
+        };
+        state.cursor = Some(index);
+        state.entries.get(index).cloned()
+    }
+
+    /// Move the cursor one entry forward. Returns `None` at the latest entry.
+    pub fn next(&self) -> Option<HistoryEntry> {
+        let mut state = self.state();
+        let index = state.cursor? + 1;
+        let entry = state.entries.get(index).cloned()?;
+        state.cursor = if index + 1 == state.entries.len() {
+            None
+        } else {
+            Some(index)
+        };
+        Some(entry)
+    }
+
+    /// One line per utterance, see [`HistoryEntry`].
+    pub fn to_text(&self) -> String {
+        self.state()
+            .entries
+            .iter()
+            .map(|entry| entry.to_line() + "\n")
+            .collect()
+    }
+
+    /// A JSON array of `{text, time, source, app}` objects, with RFC 3339 times.
+    pub fn to_json(&self) -> Result<String> {
+        let state = self.state();
+        let entries: Vec<_> = state
+            .entries
+            .iter()
+            .map(|entry| JsonEntry {
+                text: &entry.text,
+                time: entry.time.to_rfc3339(),
+                source: source_name(entry.source),
+                app: entry.app.as_deref(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&entries).map_err(|e| CoreError::Export(e.to_string()))
+    }
+
+    /// Write the whole history to `path`, as JSON if it ends in `.json` and as
+    /// text otherwise.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let contents = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => self.to_json()?,
+            _ => self.to_text(),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Counts quick presses of the spell hotkey. Each press within [`Self::REPEAT`]
+/// of the one before spells at the next [`SpellLevel`].
+#[derive(Debug, Default)]
+pub struct SpellPresses {
+    last: Mutex<Option<(Instant, SpellLevel)>>,
+}
+
+impl SpellPresses {
+    pub const REPEAT: Duration = Duration::from_millis(500);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a press and return the level to spell at.
+    pub fn press(&self) -> SpellLevel {
+        self.press_at(Instant::now())
+    }
+
+    fn press_at(&self, now: Instant) -> SpellLevel {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let level = match *last {
+            Some((at, level)) if now.duration_since(at) <= Self::REPEAT => level.next(),
+            _ => SpellLevel::default(),
+        };
+        *last = Some((now, level));
+        level
+    }
+}
+
+async fn say(tts: &TTS, text: impl Into<String>) -> Result<()> {
+    tts.speak(Utterance::review(text))
+        .await
+        .map_err(|e| CoreError::TTS(e.to_string()))
+}
+
+/// Say the latest utterance again.
+pub async fn repeat_last(tts: &TTS, history: &SpeechHistory) -> Result<()> {
+    let entries = history.entries();
+    match entries.last() {
+        Some(entry) => say(tts, entry.text.clone()).await,
+        None => say(tts, "Nothing said yet").await,
+    }
+}
+
+/// Step back to the utterance before the one under review and say it.
+pub async fn review_previous(tts: &TTS, history: &SpeechHistory) -> Result<()> {
+    match history.previous() {
+        Some(entry) => say(tts, entry.text).await,
+        None => say(tts, "Start of history").await,
+    }
+}
+
+/// Step forward to the utterance after the one under review and say it.
+pub async fn review_next(tts: &TTS, history: &SpeechHistory) -> Result<()> {
+    match history.next() {
+        Some(entry) => say(tts, entry.text).await,
+        None => say(tts, "End of history").await,
+    }
+}
+
+/// Spell the utterance under review one character at a time at `level`, setting
+/// capital letters apart.
+pub async fn spell_current(
+    tts: &TTS,
+    history: &SpeechHistory,
+    level: SpellLevel,
+    capitals: &CapitalIndicator,
+) -> Result<()> {
+    let Some(entry) = history.current() else {
+        return say(tts, "Nothing said yet").await;
+    };
+    for (i, spelled) in spell(&entry.text, level).into_iter().enumerate() {
+        // The first character replaces what was being said, the rest follow it.
+        let policy = if i == 0 {
+            Policy::ReplaceAll
+        } else {
+            Policy::Append
+        };
+        let utterance = capitals.mark(spelled.grapheme, spelled.description, |text| {
+            Utterance::new(text, Priority::Interrupt, policy, Category::Review)
+        });
+        tts.speak(utterance)
+            .await
+            .map_err(|e| CoreError::TTS(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Copy the utterance under review to the clipboard.
+pub async fn copy_current(tts: &TTS, history: &SpeechHistory) -> Result<()> {
+    let Some(entry) = history.current() else {
+        return say(tts, "Nothing said yet").await;
+    };
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(entry.text))
+        .map_err(|e| CoreError::Clipboard(e.to_string()))?;
+    say(tts, "Copied").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aria_tts::engine::RecordingEngine;
+    use aria_tts::state::SpeechState;
+    use aria_utils::config::CapitalIndication;
+    use chrono::TimeZone;
+    use std::sync::Arc;
+
+    fn history(texts: &[&str]) -> SpeechHistory {
+        let history = SpeechHistory::new(3);
+        for text in texts {
+            history.record(text, Category::Focus);
+        }
+        history
+    }
+
+    fn text(entry: Option<HistoryEntry>) -> Option<String> {
+        entry.map(|e| e.text)
+    }
+
+    #[test]
+    fn keeps_only_the_latest_entries() {
+        let history = history(&["One", "Two", "Three", "Four"]);
+        let texts: Vec<_> = history.entries().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["Two", "Three", "Four"]);
+    }
+
+    #[test]
+    fn cursor_steps_through_entries() {
+        let history = history(&["One", "Two", "Three"]);
+
+        assert_eq!(text(history.current()), Some("Three".to_string()));
+        assert_eq!(text(history.previous()), Some("Two".to_string()));
+        assert_eq!(text(history.previous()), Some("One".to_string()));
+        assert_eq!(text(history.previous()), None);
+        assert_eq!(text(history.current()), Some("One".to_string()));
+        assert_eq!(text(history.next()), Some("Two".to_string()));
+        assert_eq!(text(history.next()), Some("Three".to_string()));
+        assert_eq!(text(history.next()), None);
+
+        history.previous();
+        history.record("Four", Category::KeyEcho);
+        assert_eq!(text(history.current()), Some("Four".to_string()));
+    }
+
+    #[test]
+    fn exports_text_and_json() {
+        let history = SpeechHistory::new(10);
+        history.push(HistoryEntry {
+            text: "File, menu".to_string(),
+            time: Local.with_ymd_and_hms(2024, 5, 1, 14, 3, 12).unwrap(),
+            source: Category::Focus,
+            app: Some("notepad.exe".to_string()),
+        });
+
+        assert_eq!(
+            history.to_text(),
+            "14:03:12 [focus] notepad.exe: File, menu\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&history.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["text"], "File, menu");
+        assert_eq!(json[0]["source"], "focus");
+        assert_eq!(json[0]["app"], "notepad.exe");
+    }
+
+    #[test]
+    fn quick_presses_spell_in_more_detail() {
+        let presses = SpellPresses::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let levels: Vec<_> = [0, 300, 600, 900, 2000]
+            .into_iter()
+            .map(|ms| presses.press_at(at(ms)))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                SpellLevel::Characters,
+                SpellLevel::Phonetic,
+                SpellLevel::Unicode,
+                SpellLevel::Characters,
+                SpellLevel::Characters,
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spelling_names_whitespace_and_sets_capitals_apart() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        tts.set_state(SpeechState::Running).await.unwrap();
+        let history = history(&["Hi b"]);
+        let capitals = CapitalIndicator::new(&[CapitalIndication::Pitch], 20.0);
+
+        spell_current(&tts, &history, SpellLevel::Phonetic, &capitals)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["Hotel", "India", "space", "Bravo"]
+        );
+        let pitches: Vec<_> = engine
+            .utterances()
+            .iter()
+            .map(|u| u.prosody.pitch)
+            .collect();
+        assert_eq!(pitches, vec![Some(1.2), None, None, None]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn review_commands_speak_without_recording() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        tts.set_state(SpeechState::Running).await.unwrap();
+        let history = history(&["Hi", "OK, button"]);
+
+        repeat_last(&tts, &history).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        review_previous(&tts, &history).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        spell_current(
+            &tts,
+            &history,
+            SpellLevel::Characters,
+            &CapitalIndicator::default(),
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(engine.spoken_texts(), vec!["OK, button", "Hi", "H", "i"]);
+        assert_eq!(history.entries().len(), 2);
+    }
+}
@@ -1,3 +1,10 @@
+pub mod announce;
+pub mod capitals;
+#[cfg(all(windows, feature = "windows"))]
 pub mod driver;
 pub mod error;
+pub mod history;
+pub mod profiles;
+pub mod progress;
+pub mod settings;
 pub mod sound;
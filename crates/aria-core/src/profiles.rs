@@ -0,0 +1,417 @@
+//! Settings for particular applications.
+//!
+//! A profile under `[profiles]` in aria.toml names the executables and window
+//! classes it is for and overrides the cleaning rules, speech rate, voice,
+//! punctuation level and key echo while one of them has focus. The first profile,
+//! in name order, that matches the focused application is active; without a match
+//! the settings at the top of aria.toml and the settings ring apply. The driver
+//! reads the focused application through an [`ElementSource`], so switching can be
+//! tested with a fake one.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use aria_tts::engine::VoiceInfo;
+use aria_tts::tts::TTS;
+use aria_tts::voice::VoiceSelector;
+use aria_utils::clean_text::RegexCleanerPair;
+use aria_utils::config::{AppProfile, AriaConfig, PunctuationLevel};
+
+use crate::error::CoreError;
+use crate::settings::{apply, SettingsRing, SpeechSettings};
+
+type Result<T> = std::result::Result<T, CoreError>;
+
+/// Where the focused application is read from: UI Automation in the driver.
+pub trait ElementSource {
+    /// File name of the executable that owns the focused element, e.g. `code.exe`.
+    fn process_name(&self) -> Option<String>;
+    /// Class of the foreground window, e.g. `ConsoleWindowClass`.
+    fn window_class(&self) -> Option<String>;
+}
+
+/// The application that has focus, as far as profiles are concerned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FocusedApp {
+    pub process: Option<String>,
+    pub window_class: Option<String>,
+}
+
+impl FocusedApp {
+    pub fn read(source: &dyn ElementSource) -> Self {
+        FocusedApp {
+            process: source.process_name(),
+            window_class: source.window_class(),
+        }
+    }
+}
+
+/// A profile ready to be switched to.
+#[derive(Debug)]
+struct Profile {
+    name: String,
+    /// Lowercase, to match ignoring case.
+    processes: Vec<String>,
+    window_classes: Vec<String>,
+    clean_list: Arc<Vec<RegexCleanerPair>>,
+    rate: Option<f64>,
+    voice: Option<VoiceInfo>,
+    punctuation: Option<PunctuationLevel>,
+    key_echo: Option<bool>,
+}
+
+impl Profile {
+    fn new(
+        name: &str,
+        profile: &AppProfile,
+        config: &AriaConfig,
+        voices: &[VoiceInfo],
+    ) -> Result<Self> {
+        let lowercase = |names: &[String]| names.iter().map(|n| n.to_lowercase()).collect();
+        let voice = profile.voice.as_ref().and_then(|selector| {
+            let voice = selector
+                .parse::<VoiceSelector>()
+                .and_then(|selector| selector.select(voices).cloned());
+            voice
+                .map_err(|e| log::warn!("Ignoring the voice of profile {}: {}", name, e))
+                .ok()
+        });
+        Ok(Profile {
+            name: name.to_string(),
+            processes: lowercase(&profile.processes),
+            window_classes: lowercase(&profile.window_classes),
+            clean_list: Arc::new(
+                config
+                    .profile_clean_list(profile)
+                    .map_err(|e| CoreError::Config(format!("profile {}: {}", name, e)))?,
+            ),
+            rate: profile.speech_rate,
+            voice,
+            punctuation: profile.punctuation,
+            key_echo: profile.key_echo,
+        })
+    }
+
+    fn matches(&self, app: &FocusedApp) -> bool {
+        let is_in = |names: &[String], name: &Option<String>| {
+            name.as_ref()
+                .is_some_and(|name| names.contains(&name.to_lowercase()))
+        };
+        is_in(&self.processes, &app.process) || is_in(&self.window_classes, &app.window_class)
+    }
+
+    /// `settings` with the profile's overrides.
+    fn over(&self, mut settings: SpeechSettings) -> SpeechSettings {
+        settings.rate = self.rate.unwrap_or(settings.rate);
+        settings.voice = self.voice.clone().or(settings.voice);
+        settings.punctuation = self.punctuation.unwrap_or(settings.punctuation);
+        settings
+    }
+}
+
+/// What the focus and key handlers go by while a profile, or none, is active.
+#[derive(Debug, Clone)]
+pub struct ActiveProfile {
+    /// `None` without a matching profile.
+    pub name: Option<String>,
+    pub clean_list: Arc<Vec<RegexCleanerPair>>,
+    pub key_echo: bool,
+}
+
+/// The profiles of aria.toml and which of them is active. See the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    clean_list: Arc<Vec<RegexCleanerPair>>,
+    key_echo: bool,
+    announce: bool,
+    active: Mutex<Option<usize>>,
+}
+
+impl Profiles {
+    /// Compile the profiles of `config`, resolving their voices among `voices`. A
+    /// voice that is not found is ignored, an invalid cleaning rule is an error.
+    pub fn new(config: &AriaConfig, voices: &[VoiceInfo]) -> Result<Self> {
+        Ok(Profiles {
+            profiles: config
+                .profiles
+                .iter()
+                .map(|(name, profile)| Profile::new(name, profile, config, voices))
+                .collect::<Result<_>>()?,
+            clean_list: Arc::new(
+                config
+                    .clean_list()
+                    .map_err(|e| CoreError::Config(e.to_string()))?,
+            ),
+            key_echo: config.key_echo,
+            announce: config.announce_profile,
+            active: Mutex::new(None),
+        })
+    }
+
+    /// Compile the profiles of `config` for the voices of the engine behind `tts`.
+    pub async fn load(tts: &TTS, config: &AriaConfig) -> Result<Self> {
+        let voices = tts
+            .get_available_voices()
+            .await
+            .map_err(|e| CoreError::TTS(e.to_string()))?;
+        Self::new(config, &voices)
+    }
+
+    fn active_index(&self) -> MutexGuard<'_, Option<usize>> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn active(&self) -> ActiveProfile {
+        match *self.active_index() {
+            Some(index) => {
+                let profile = &self.profiles[index];
+                ActiveProfile {
+                    name: Some(profile.name.clone()),
+                    clean_list: profile.clean_list.clone(),
+                    key_echo: profile.key_echo.unwrap_or(self.key_echo),
+                }
+            }
+            None => ActiveProfile {
+                name: None,
+                clean_list: self.clean_list.clone(),
+                key_echo: self.key_echo,
+            },
+        }
+    }
+
+    /// `settings` with the overrides of the active profile, if any.
+    pub fn over(&self, settings: SpeechSettings) -> SpeechSettings {
+        let active = *self.active_index();
+        match active {
+            Some(index) => self.profiles[index].over(settings),
+            None => settings,
+        }
+    }
+
+    /// The settings in effect while the profile at `index` is active.
+    fn settings(&self, index: Option<usize>, ring: &SettingsRing) -> SpeechSettings {
+        let settings = ring.settings();
+        match index {
+            Some(index) => self.profiles[index].over(settings),
+            None => settings,
+        }
+    }
+
+    /// Make the profile for `app` active, if it is not already, and apply its
+    /// speech settings over those of `ring`. Returns what to say about the switch
+    /// if `announce_profile` is set, to be said before the focused element.
+    pub async fn focus(
+        &self,
+        tts: &TTS,
+        ring: &SettingsRing,
+        app: &FocusedApp,
+    ) -> Result<Option<String>> {
+        let next = self.profiles.iter().position(|p| p.matches(app));
+        let previous = std::mem::replace(&mut *self.active_index(), next);
+        if previous == next {
+            return Ok(None);
+        }
+
+        let before = self.settings(previous, ring);
+        let after = self.settings(next, ring);
+        apply(tts, &after, before.voice != after.voice).await?;
+        log::info!(
+            "Profile {}",
+            next.map_or("default", |index| &self.profiles[index].name)
+        );
+
+        Ok(self.announce.then(|| match next {
+            Some(index) => format!("{} profile", self.profiles[index].name),
+            None => "Default profile".to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{select_setting, step_setting};
+    use aria_tts::engine::RecordingEngine;
+    use aria_tts::state::SpeechState;
+    use aria_utils::clean_text::clean_text;
+    use aria_utils::clean_text::rules::CleaningRule;
+    use std::time::Duration;
+
+    struct FakeElement {
+        process: &'static str,
+        class: &'static str,
+    }
+
+    impl ElementSource for FakeElement {
+        fn process_name(&self) -> Option<String> {
+            Some(self.process.to_string())
+        }
+
+        fn window_class(&self) -> Option<String> {
+            Some(self.class.to_string())
+        }
+    }
+
+    fn app(process: &'static str, class: &'static str) -> FocusedApp {
+        FocusedApp::read(&FakeElement { process, class })
+    }
+
+    fn voice(name: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: name.to_lowercase(),
+            display_name: name.to_string(),
+            language: "en-US".to_string(),
+            gender: "Female".to_string(),
+        }
+    }
+
+    fn config() -> AriaConfig {
+        let mut config = AriaConfig::default();
+        config.profiles.insert(
+            "code".to_string(),
+            AppProfile {
+                processes: vec!["Code.exe".to_string()],
+                cleaning: vec![CleaningRule::new("arrows", "=>", "arrow")],
+                speech_rate: Some(1.5),
+                voice: Some("Hedda".to_string()),
+                ..AppProfile::default()
+            },
+        );
+        config.profiles.insert(
+            "terminal".to_string(),
+            AppProfile {
+                window_classes: vec!["ConsoleWindowClass".to_string()],
+                punctuation: Some(PunctuationLevel::All),
+                key_echo: Some(false),
+                ..AppProfile::default()
+            },
+        );
+        config
+    }
+
+    async fn setup(config: &AriaConfig) -> (Arc<RecordingEngine>, TTS, SettingsRing, Profiles) {
+        let engine = Arc::new(RecordingEngine::with_voices(vec![
+            voice("Zira"),
+            voice("Hedda"),
+        ]));
+        let tts = TTS::new(engine.clone());
+        tts.set_state(SpeechState::Running).await.unwrap();
+        let ring = SettingsRing::load(&tts, config).await.unwrap();
+        let profiles = Profiles::load(&tts, config).await.unwrap();
+        (engine, tts, ring, profiles)
+    }
+
+    async fn speak(tts: &TTS, text: &str) {
+        tts.speak_and_wait(text).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn profiles_follow_the_focused_app() {
+        let (engine, tts, ring, profiles) = setup(&config()).await;
+
+        let announcement = profiles
+            .focus(&tts, &ring, &app("code.exe", "Chrome_WidgetWin_1"))
+            .await
+            .unwrap();
+        assert_eq!(announcement, None);
+        let active = profiles.active();
+        assert_eq!(active.name.as_deref(), Some("code"));
+        assert!(active.key_echo);
+        let cleaned: String = clean_text("a  =>  b", &active.clean_list);
+        assert_eq!(cleaned, "a arrow b");
+        speak(&tts, "In code").await;
+
+        profiles
+            .focus(&tts, &ring, &app("cmd.exe", "ConsoleWindowClass"))
+            .await
+            .unwrap();
+        let active = profiles.active();
+        assert_eq!(active.name.as_deref(), Some("terminal"));
+        assert!(!active.key_echo);
+        let cleaned: String = clean_text("a  =>  b", &active.clean_list);
+        assert_eq!(cleaned, "a => b");
+        speak(&tts, "In the terminal").await;
+
+        profiles
+            .focus(&tts, &ring, &app("notepad.exe", "Notepad"))
+            .await
+            .unwrap();
+        assert_eq!(profiles.active().name, None);
+        speak(&tts, "Elsewhere").await;
+
+        let utterances = engine.utterances();
+        let spoken: Vec<_> = utterances
+            .iter()
+            .map(|u| (u.text.as_str(), u.voice.id.as_str(), u.prosody.rate))
+            .collect();
+        assert_eq!(
+            spoken,
+            vec![
+                ("In code", "hedda", Some(1.5)),
+                ("In the terminal", "zira", Some(1.0)),
+                ("Elsewhere", "zira", Some(1.0)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stepped_settings_keep_the_overrides_of_the_profile() {
+        let (engine, tts, ring, profiles) = setup(&config()).await;
+        let code = app("code.exe", "Chrome_WidgetWin_1");
+        profiles.focus(&tts, &ring, &code).await.unwrap();
+
+        // From rate to pitch, then up a step.
+        select_setting(&tts, &ring, 1).await.unwrap();
+        step_setting(&tts, &ring, &profiles, 1).await.unwrap();
+        speak(&tts, "In code").await;
+        profiles
+            .focus(&tts, &ring, &app("notepad.exe", "Notepad"))
+            .await
+            .unwrap();
+        speak(&tts, "Elsewhere").await;
+
+        let utterances = engine.utterances();
+        let spoken: Vec<_> = utterances
+            .iter()
+            .map(|u| {
+                let prosody = &u.prosody;
+                (
+                    u.text.as_str(),
+                    u.voice.id.as_str(),
+                    prosody.rate,
+                    prosody.pitch,
+                )
+            })
+            .collect();
+        assert_eq!(
+            spoken,
+            vec![
+                ("Pitch 100 percent", "hedda", Some(1.5), Some(1.0)),
+                ("Pitch 110 percent", "hedda", Some(1.5), Some(1.1)),
+                ("In code", "hedda", Some(1.5), Some(1.1)),
+                ("Elsewhere", "zira", Some(1.0), Some(1.1)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn switches_are_announced_when_asked() {
+        let mut config = config();
+        config.announce_profile = true;
+        let (_engine, tts, ring, profiles) = setup(&config).await;
+
+        let code = app("code.exe", "Chrome_WidgetWin_1");
+        let focus = |app| profiles.focus(&tts, &ring, app);
+        assert_eq!(focus(&code).await.unwrap().as_deref(), Some("code profile"));
+        assert_eq!(focus(&code).await.unwrap(), None);
+        assert_eq!(
+            focus(&app("explorer.exe", "CabinetWClass"))
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("Default profile")
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Following the read position of an utterance in the text it was cleaned from.
+//!
+//! Synthesizers report word and sentence boundaries as UTF-16 offsets into the
+//! text they were given, which is the original normalized as in focus
+//! announcements and then run through [`clean_text`]. The index maps of both steps
+//! translate those offsets back to the original text, e.g. to move the caret along
+//! during say-all.
+
+use std::ops::Range;
+
+use aria_tts::error::TTSError;
+use aria_tts::event::{Boundary, BoundaryKind};
+use aria_tts::queue::Utterance;
+use aria_tts::tts::TTS;
+use aria_utils::clean_text::{
+    clean_text, clean_text_u16idx_in, clean_text_u16idx_out, RegexCleanerPair, Rewritten,
+};
+use aria_utils::normalize::Normalizer;
+use aria_utils::wide_string::{invert_idx, u8idx_from_u16idx};
+use tokio::sync::mpsc;
+
+type Result<T> = std::result::Result<T, TTSError>;
+
+/// Text before and after normalizing and cleaning, with the index maps between
+/// them.
+#[derive(Debug, Clone)]
+pub struct CleanedText {
+    original: String,
+    normalized: Rewritten,
+    cleaned: String,
+    /// Index maps from the normalized to the cleaned text.
+    u16idx_in: Vec<usize>,
+    u16idx_out: Vec<usize>,
+}
+
+impl CleanedText {
+    pub fn new<T: Into<String>>(
+        original: T,
+        normalizer: &Normalizer,
+        clean_list: &[RegexCleanerPair],
+    ) -> Self {
+        let original = original.into();
+        let normalized = normalizer.rewrite(&original);
+        CleanedText {
+            cleaned: clean_text(&normalized.text, clean_list),
+            u16idx_in: clean_text_u16idx_in(&normalized.text, clean_list),
+            u16idx_out: clean_text_u16idx_out(&normalized.text, clean_list),
+            normalized,
+            original,
+        }
+    }
+
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The text handed to the synthesizer.
+    pub fn cleaned(&self) -> &str {
+        &self.cleaned
+    }
+
+    /// Map a UTF-16 range in the cleaned text to a byte range in the original text.
+    pub fn original_range(&self, spoken: &Range<usize>) -> Range<usize> {
+        let mut u16range = invert_idx(&self.u16idx_in, &self.u16idx_out, spoken);
+        // A range inside a single replacement comes back reversed; it was spoken
+        // for the whole replaced text.
+        if u16range.start > u16range.end {
+            u16range = u16range.end..u16range.start;
+        }
+        let u16range = self.normalized.input_u16range(&u16range);
+        u8idx_from_u16idx(&self.original, &u16range)
+    }
+}
+
+/// A word or sentence being read, as a byte range in the original text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadPosition {
+    pub kind: BoundaryKind,
+    pub range: Range<usize>,
+}
+
+/// The read position of an utterance queued with [`speak_tracked`].
+#[derive(Debug)]
+pub struct SpeechProgress {
+    text: CleanedText,
+    boundaries: mpsc::UnboundedReceiver<Boundary>,
+}
+
+impl SpeechProgress {
+    pub fn text(&self) -> &CleanedText {
+        &self.text
+    }
+
+    /// Wait for the next word or sentence. Returns `None` once the utterance has
+    /// been spoken or was dropped from the queue.
+    pub async fn next(&mut self) -> Option<ReadPosition> {
+        let boundary = self.boundaries.recv().await?;
+        Some(ReadPosition {
+            kind: boundary.kind,
+            range: self.text.original_range(&boundary.range),
+        })
+    }
+}
+
+/// Normalize, clean and queue `utterance`, reporting its read position in the
+/// original text.
+pub async fn speak_tracked(
+    tts: &TTS,
+    utterance: Utterance,
+    normalizer: &Normalizer,
+    clean_list: &[RegexCleanerPair],
+) -> Result<SpeechProgress> {
+    let text = CleanedText::new(utterance.text.as_str(), normalizer, clean_list);
+    let utterance = Utterance {
+        text: text.cleaned().to_string(),
+        ..utterance
+    };
+    let boundaries = tts.speak_with_progress(utterance).await?;
+    Ok(SpeechProgress { text, boundaries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aria_tts::engine::{CachingEngine, RecordingEngine};
+    use std::sync::Arc;
+
+    fn clean_list() -> Vec<RegexCleanerPair> {
+        RegexCleanerPair::prep_list(&[(r"&", "and"), (r"\s+", " ")]).unwrap()
+    }
+
+    #[test]
+    fn ranges_map_back_to_the_original() {
+        let text = CleanedText::new(
+            "Save\t&\t\u{1d565}  close",
+            &Normalizer::default(),
+            &clean_list(),
+        );
+        assert_eq!(text.cleaned(), "Save and \u{1d565} close");
+
+        let original = |spoken: Range<usize>| &text.original()[text.original_range(&spoken)];
+        assert_eq!(original(0..4), "Save");
+        // "and" stands for the ampersand; the surrogate pair counts as two units.
+        assert_eq!(original(5..8), "&");
+        assert_eq!(original(9..11), "\u{1d565}");
+        assert_eq!(original(12..17), "close");
+    }
+
+    #[test]
+    fn numbers_map_back_through_the_normalizer() {
+        let text = CleanedText::new("Pay\t$5 & go", &Normalizer::default(), &clean_list());
+        assert_eq!(text.cleaned(), "Pay five dollars and go");
+
+        let original = |spoken: Range<usize>| &text.original()[text.original_range(&spoken)];
+        assert_eq!(original(0..3), "Pay");
+        assert_eq!(original(4..8), "$5");
+        assert_eq!(original(9..16), "$5");
+        assert_eq!(original(17..20), "&");
+        assert_eq!(original(21..23), "go");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tracks_words_of_the_original_text() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+
+        let mut progress = speak_tracked(
+            &tts,
+            Utterance::from("Tom\t&  Jerry"),
+            &Normalizer::default(),
+            &clean_list(),
+        )
+        .await
+        .unwrap();
+
+        let mut words = Vec::new();
+        while let Some(position) = progress.next().await {
+            assert_eq!(position.kind, BoundaryKind::Word);
+            words.push(progress.text().original()[position.range].to_string());
+        }
+        assert_eq!(engine.spoken_texts(), vec!["Tom and Jerry"]);
+        assert_eq!(words, vec!["Tom", "&", "Jerry"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tracks_words_of_cached_speech() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(Arc::new(CachingEngine::new(engine.clone(), 1 << 20, 200)));
+        tts.speak_and_wait("Tom and Jerry").await.unwrap();
+
+        let mut progress = speak_tracked(
+            &tts,
+            Utterance::from("Tom\t&  Jerry"),
+            &Normalizer::default(),
+            &clean_list(),
+        )
+        .await
+        .unwrap();
+
+        let mut words = Vec::new();
+        while let Some(position) = progress.next().await {
+            words.push(progress.text().original()[position.range].to_string());
+        }
+        assert_eq!(words, vec!["Tom", "&", "Jerry"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tracks_words_through_pronunciation() {
+        use aria_utils::config::PunctuationLevel;
+        use aria_utils::pronunciation::{
+            DictionaryScope, PronunciationDictionaries, PronunciationEntry,
+        };
+
+        let mut dictionaries = PronunciationDictionaries::default();
+        let entry = PronunciationEntry::new("Aria", "Ahria");
+        dictionaries.add(&DictionaryScope::Global, entry).unwrap();
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        tts.set_pronunciation(dictionaries.compile().unwrap());
+        tts.set_punctuation(PunctuationLevel::Some);
+
+        let utterance = Utterance::from("Aria\tsays  x = yz. Ask @Tom.");
+        let mut progress = speak_tracked(&tts, utterance, &Normalizer::default(), &clean_list())
+            .await
+            .unwrap();
+
+        let mut words = Vec::new();
+        while let Some(position) = progress.next().await {
+            words.push(progress.text().original()[position.range].to_string());
+        }
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["Ahria says x equals yz.", "Ask at Tom."]
+        );
+        assert_eq!(
+            words,
+            vec!["Aria", "says", "x", "=", "yz.", "Ask", "@", "Tom."]
+        );
+    }
+}
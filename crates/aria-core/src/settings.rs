@@ -0,0 +1,392 @@
+//! Speech settings that can be changed while Aria runs.
+//!
+//! The settings ring holds one value per [`Setting`]. One setting is selected at
+//! a time; the driver binds hotkeys to move the selection around the ring and to
+//! step the selected value. Every change is applied to the speech controller
+//! right away and spoken, and can be saved back to aria.toml.
+
+use std::sync::{Mutex, MutexGuard};
+
+use aria_tts::engine::{Prosody, VoiceInfo};
+use aria_tts::queue::Utterance;
+use aria_tts::tts::TTS;
+use aria_tts::voice::VoiceSelector;
+use aria_utils::config::{save_speech_settings, AriaConfig, PunctuationLevel};
+
+use crate::error::CoreError;
+use crate::profiles::Profiles;
+
+type Result<T> = std::result::Result<T, CoreError>;
+
+/// A setting in the ring, in ring order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Setting {
+    Voice,
+    Rate,
+    Pitch,
+    Volume,
+    Punctuation,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 5] = [
+        Setting::Voice,
+        Setting::Rate,
+        Setting::Pitch,
+        Setting::Volume,
+        Setting::Punctuation,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Setting::Voice => "Voice",
+            Setting::Rate => "Rate",
+            Setting::Pitch => "Pitch",
+            Setting::Volume => "Volume",
+            Setting::Punctuation => "Punctuation",
+        }
+    }
+}
+
+const PUNCTUATION_LEVELS: [PunctuationLevel; 4] = [
+    PunctuationLevel::None,
+    PunctuationLevel::Some,
+    PunctuationLevel::Most,
+    PunctuationLevel::All,
+];
+
+/// The values of every setting in the ring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeechSettings {
+    /// `None` until the engine reports a voice.
+    pub voice: Option<VoiceInfo>,
+    pub rate: f64,
+    pub pitch: f64,
+    pub volume: f64,
+    pub punctuation: PunctuationLevel,
+}
+
+impl SpeechSettings {
+    pub fn prosody(&self) -> Prosody {
+        Prosody {
+            rate: Some(self.rate),
+            pitch: Some(self.pitch),
+            volume: Some(self.volume),
+        }
+    }
+
+    /// Copy the settings into `config`, to be saved.
+    pub fn write_to(&self, config: &mut AriaConfig) {
+        config.speech_rate = self.rate;
+        config.pitch = self.pitch;
+        config.volume = self.volume;
+        config.punctuation = self.punctuation;
+        if let Some(voice) = &self.voice {
+            config.voice = Some(format!("id:{}", voice.id));
+        }
+    }
+}
+
+/// Move `value` by `steps` increments of `step`, staying within `range`.
+fn step_number(value: f64, steps: i32, step: f64, range: (f64, f64)) -> f64 {
+    let stepped = (value + step * steps as f64).clamp(range.0, range.1);
+    // Keep repeated steps from drifting away from round numbers.
+    (stepped * 100.0).round() / 100.0
+}
+
+/// Move to the item `steps` away from `index`, wrapping around.
+fn step_index(index: usize, steps: i32, len: usize) -> usize {
+    (index as i64 + steps as i64).rem_euclid(len as i64) as usize
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.0} percent", value * 100.0)
+}
+
+#[derive(Debug)]
+struct RingState {
+    selected: Setting,
+    settings: SpeechSettings,
+}
+
+/// The settings ring. See the [module documentation](self).
+#[derive(Debug)]
+pub struct SettingsRing {
+    voices: Vec<VoiceInfo>,
+    state: Mutex<RingState>,
+}
+
+impl SettingsRing {
+    pub const RATE_STEP: f64 = 0.1;
+    pub const RATE_RANGE: (f64, f64) = (0.5, 6.0);
+    pub const PITCH_STEP: f64 = 0.1;
+    pub const PITCH_RANGE: (f64, f64) = (0.0, 2.0);
+    pub const VOLUME_STEP: f64 = 0.1;
+    pub const VOLUME_RANGE: (f64, f64) = (0.0, 1.0);
+
+    /// Start from `settings`, stepping the voice through `voices`. Rate is selected first.
+    pub fn new(settings: SpeechSettings, voices: Vec<VoiceInfo>) -> Self {
+        SettingsRing {
+            voices,
+            state: Mutex::new(RingState {
+                selected: Setting::Rate,
+                settings,
+            }),
+        }
+    }
+
+    /// Start from the values in `config` and the voices of the engine behind `tts`.
+    pub async fn load(tts: &TTS, config: &AriaConfig) -> Result<Self> {
+        let tts_error = |e: aria_tts::error::TTSError| CoreError::TTS(e.to_string());
+        let voices = tts.get_available_voices().await.map_err(tts_error)?;
+        let configured = config.voice.as_ref().and_then(|selector| {
+            let selector = selector.parse::<VoiceSelector>().ok()?;
+            selector.select(&voices).ok().cloned()
+        });
+        let voice = match configured {
+            Some(voice) => Some(voice),
+            None => tts.get_default_voice().await.ok(),
+        };
+
+        Ok(Self::new(
+            SpeechSettings {
+                voice,
+                rate: config.speech_rate,
+                pitch: config.pitch,
+                volume: config.volume,
+                punctuation: config.punctuation,
+            },
+            voices,
+        ))
+    }
+
+    fn state(&self) -> MutexGuard<'_, RingState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn selected(&self) -> Setting {
+        self.state().selected
+    }
+
+    pub fn settings(&self) -> SpeechSettings {
+        self.state().settings.clone()
+    }
+
+    /// Select the setting `steps` places further around the ring.
+    pub fn select(&self, steps: i32) -> Setting {
+        let mut state = self.state();
+        let index = Setting::ALL
+            .iter()
+            .position(|s| *s == state.selected)
+            .unwrap_or(0);
+        state.selected = Setting::ALL[step_index(index, steps, Setting::ALL.len())];
+        state.selected
+    }
+
+    /// Step the selected setting by `steps` increments and return the new values.
+    /// Numbers stop at the ends of their range; voices and punctuation levels wrap.
+    pub fn step(&self, steps: i32) -> SpeechSettings {
+        let mut state = self.state();
+        let selected = state.selected;
+        let settings = &mut state.settings;
+        match selected {
+            Setting::Voice if !self.voices.is_empty() => {
+                let index = settings
+                    .voice
+                    .as_ref()
+                    .and_then(|voice| self.voices.iter().position(|v| v.id == voice.id));
+                let next = match index {
+                    Some(index) => step_index(index, steps, self.voices.len()),
+                    None => 0,
+                };
+                settings.voice = Some(self.voices[next].clone());
+            }
+            Setting::Voice => {}
+            Setting::Rate => {
+                settings.rate = step_number(settings.rate, steps, Self::RATE_STEP, Self::RATE_RANGE)
+            }
+            Setting::Pitch => {
+                settings.pitch =
+                    step_number(settings.pitch, steps, Self::PITCH_STEP, Self::PITCH_RANGE)
+            }
+            Setting::Volume => {
+                settings.volume = step_number(
+                    settings.volume,
+                    steps,
+                    Self::VOLUME_STEP,
+                    Self::VOLUME_RANGE,
+                )
+            }
+            Setting::Punctuation => {
+                let index = PUNCTUATION_LEVELS
+                    .iter()
+                    .position(|level| *level == settings.punctuation)
+                    .unwrap_or(0);
+                settings.punctuation =
+                    PUNCTUATION_LEVELS[step_index(index, steps, PUNCTUATION_LEVELS.len())];
+            }
+        }
+        settings.clone()
+    }
+
+    /// What is spoken for the selected setting, e.g. "Rate 120 percent".
+    pub fn describe(&self) -> String {
+        let state = self.state();
+        let settings = &state.settings;
+        let value = match state.selected {
+            Setting::Voice => settings
+                .voice
+                .as_ref()
+                .map(|voice| voice.display_name.clone())
+                .unwrap_or_else(|| "default".to_string()),
+            Setting::Rate => percent(settings.rate),
+            Setting::Pitch => percent(settings.pitch),
+            Setting::Volume => percent(settings.volume),
+            Setting::Punctuation => format!("{:?}", settings.punctuation).to_lowercase(),
+        };
+        format!("{} {}", state.selected.name(), value)
+    }
+}
+
+async fn say(tts: &TTS, text: String) -> Result<()> {
+    tts.speak(Utterance::system(text))
+        .await
+        .map_err(|e| CoreError::TTS(e.to_string()))
+}
+
+/// Apply `settings` to the speech controller. The voice is only switched if
+/// `switch_voice` is set, since that may be slow.
+pub async fn apply(tts: &TTS, settings: &SpeechSettings, switch_voice: bool) -> Result<()> {
+    let tts_error = |e: aria_tts::error::TTSError| CoreError::TTS(e.to_string());
+    if switch_voice {
+        if let Some(voice) = &settings.voice {
+            tts.use_voice(voice).await.map_err(tts_error)?;
+        }
+    }
+    tts.set_punctuation(settings.punctuation);
+    tts.set_prosody(settings.prosody()).await.map_err(tts_error)
+}
+
+/// Select the setting `steps` places around the ring and say its value.
+pub async fn select_setting(tts: &TTS, ring: &SettingsRing, steps: i32) -> Result<()> {
+    ring.select(steps);
+    say(tts, ring.describe()).await
+}
+
+/// Step the selected setting, apply the result under the overrides of the active
+/// profile and say it, already with the new values.
+pub async fn step_setting(
+    tts: &TTS,
+    ring: &SettingsRing,
+    profiles: &Profiles,
+    steps: i32,
+) -> Result<()> {
+    let before = profiles.over(ring.settings());
+    let after = profiles.over(ring.step(steps));
+    apply(tts, &after, before.voice != after.voice).await?;
+    say(tts, ring.describe()).await
+}
+
+/// Save the ring's values to aria.toml, leaving the rest of the file alone.
+pub async fn save_settings(tts: &TTS, ring: &SettingsRing) -> Result<()> {
+    let mut config = AriaConfig::default();
+    ring.settings().write_to(&mut config);
+    save_speech_settings(&config).map_err(|e| CoreError::Config(e.to_string()))?;
+    say(tts, "Settings saved".to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aria_tts::engine::RecordingEngine;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn voice(name: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: name.to_lowercase(),
+            display_name: name.to_string(),
+            language: "en-US".to_string(),
+            gender: "Female".to_string(),
+        }
+    }
+
+    fn ring() -> SettingsRing {
+        let config = AriaConfig::default();
+        SettingsRing::new(
+            SpeechSettings {
+                voice: Some(voice("Zira")),
+                rate: config.speech_rate,
+                pitch: config.pitch,
+                volume: config.volume,
+                punctuation: config.punctuation,
+            },
+            vec![voice("Zira"), voice("Hedda")],
+        )
+    }
+
+    #[test]
+    fn selection_wraps_around_the_ring() {
+        let ring = ring();
+        assert_eq!(ring.selected(), Setting::Rate);
+        assert_eq!(ring.select(-2), Setting::Punctuation);
+        assert_eq!(ring.select(1), Setting::Voice);
+    }
+
+    #[test]
+    fn numbers_stop_at_their_limits() {
+        let ring = ring();
+        ring.select(2);
+        assert_eq!(ring.step(-3).volume, 0.7);
+        assert_eq!(ring.step(10).volume, 1.0);
+        assert_eq!(ring.describe(), "Volume 100 percent");
+    }
+
+    #[test]
+    fn voices_and_punctuation_wrap() {
+        let ring = ring();
+        ring.select(-1);
+        assert_eq!(ring.step(1).voice, Some(voice("Hedda")));
+        assert_eq!(ring.step(1).voice, Some(voice("Zira")));
+        ring.select(-1);
+        assert_eq!(ring.step(-2).punctuation, PunctuationLevel::All);
+        assert_eq!(ring.describe(), "Punctuation all");
+    }
+
+    #[test]
+    fn settings_are_written_to_the_config() {
+        let ring = ring();
+        ring.step(2);
+        let mut config = AriaConfig::default();
+        ring.settings().write_to(&mut config);
+        assert_eq!(config.speech_rate, 1.2);
+        assert_eq!(config.voice.as_deref(), Some("id:zira"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn steps_are_applied_and_spoken() {
+        let engine = Arc::new(RecordingEngine::with_voices(vec![
+            voice("Zira"),
+            voice("Hedda"),
+        ]));
+        let tts = TTS::new(engine.clone());
+        let ring = SettingsRing::load(&tts, &AriaConfig::default())
+            .await
+            .unwrap();
+        let profiles = Profiles::new(&AriaConfig::default(), &[]).unwrap();
+
+        step_setting(&tts, &ring, &profiles, 1).await.unwrap();
+        select_setting(&tts, &ring, -1).await.unwrap();
+        step_setting(&tts, &ring, &profiles, 1).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        let utterances = engine.utterances();
+        let spoken: Vec<_> = utterances.iter().map(|u| u.text.as_str()).collect();
+        assert_eq!(
+            spoken,
+            vec!["Rate 110 percent", "Voice Zira", "Voice Hedda"]
+        );
+        assert_eq!(utterances[0].prosody.rate, Some(1.1));
+        assert_eq!(utterances[2].voice, voice("Hedda"));
+    }
+}
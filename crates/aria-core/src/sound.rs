@@ -1,5 +1,11 @@
+#[cfg(feature = "sound")]
+use rodio::source::{SineWave, Source};
+#[cfg(feature = "sound")]
 use rodio::{Decoder, OutputStream, Sink};
+#[cfg(feature = "sound")]
 use std::io::Cursor;
+use std::time::Duration;
+#[cfg(feature = "sound")]
 use tokio::task;
 
 pub const STARTUP_SOUND: &[u8] = include_bytes!("../assets/sounds/startup.mp3");
@@ -8,6 +14,7 @@ pub const INPUT_FOCUSSED_SOUND: &[u8] = include_bytes!("../assets/sounds/input-f
 
 /// Plays the given sound data on a separate blocking thread using the default audio output.
 /// Errors during sound playback are logged.
+#[cfg(feature = "sound")]
 pub fn play_sound(sound_data: &'static [u8]) {
     task::spawn_blocking(move || match OutputStream::try_default() {
         Ok((_stream, stream_handle)) => match Sink::try_new(&stream_handle) {
@@ -26,3 +33,35 @@ pub fn play_sound(sound_data: &'static [u8]) {
         Err(e) => log::error!("Failed to get default audio output stream: {}", e),
     });
 }
+
+/// Sound support was compiled out; the sound is skipped.
+#[cfg(not(feature = "sound"))]
+pub fn play_sound(_sound_data: &'static [u8]) {
+    log::debug!("Built without the `sound` feature, skipping sound playback.");
+}
+
+/// Plays a quiet sine tone, e.g. a beep for capital letters, the same way as
+/// [`play_sound`].
+#[cfg(feature = "sound")]
+pub fn play_tone(frequency: f32, duration: Duration) {
+    task::spawn_blocking(move || match OutputStream::try_default() {
+        Ok((_stream, stream_handle)) => match Sink::try_new(&stream_handle) {
+            Ok(sink) => {
+                sink.append(
+                    SineWave::new(frequency)
+                        .take_duration(duration)
+                        .amplify(0.2),
+                );
+                sink.sleep_until_end();
+            }
+            Err(e) => log::error!("Failed to create audio sink: {}", e),
+        },
+        Err(e) => log::error!("Failed to get default audio output stream: {}", e),
+    });
+}
+
+/// Sound support was compiled out; the tone is skipped.
+#[cfg(not(feature = "sound"))]
+pub fn play_tone(_frequency: f32, _duration: Duration) {
+    log::debug!("Built without the `sound` feature, skipping tone playback.");
+}
@@ -0,0 +1,234 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{Result, TTSError};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Synthesized speech as interleaved little-endian PCM samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioBuffer {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub data: Vec<u8>,
+}
+
+/// Channels, sample rate and bits per sample from the `fmt ` chunk of a WAV file.
+fn parse_fmt(chunk: &[u8]) -> Result<(u16, u32, u16)> {
+    if chunk.len() < 16 {
+        return Err(TTSError::Audio("truncated fmt chunk".to_string()));
+    }
+    let u16_at = |i: usize| u16::from_le_bytes([chunk[i], chunk[i + 1]]);
+    let mut format_tag = u16_at(0);
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+        // The first two bytes of the sub-format GUID hold the actual format.
+        format_tag = u16_at(24);
+    }
+    if format_tag != WAVE_FORMAT_PCM {
+        return Err(TTSError::Audio(format!(
+            "unsupported WAVE format tag {:#06x}",
+            format_tag
+        )));
+    }
+    Ok((
+        u16_at(2),
+        u32::from_le_bytes(chunk[4..8].try_into().unwrap()),
+        u16_at(14),
+    ))
+}
+
+/// The format of a WAV file that is still coming in, as a buffer without samples,
+/// and where its samples start. `None` until `bytes` reach the data chunk.
+pub fn wav_header(bytes: &[u8]) -> Result<Option<(AudioBuffer, usize)>> {
+    if bytes.len() < 12 {
+        return Ok(None);
+    }
+    if &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(TTSError::Audio("not a RIFF/WAVE file".to_string()));
+    }
+
+    let mut format = None;
+    let mut offset: usize = 12;
+    while offset.saturating_add(8) <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+        let start = offset + 8;
+        if id == b"data" {
+            let (channels, sample_rate, bits_per_sample) =
+                format.ok_or_else(|| TTSError::Audio("missing fmt chunk".to_string()))?;
+            let audio = AudioBuffer {
+                sample_rate,
+                channels,
+                bits_per_sample,
+                data: Vec::new(),
+            };
+            return Ok(Some((audio, start)));
+        }
+        let end = start.saturating_add(size as usize);
+        if id == b"fmt " {
+            match bytes.get(start..end) {
+                Some(chunk) => format = Some(parse_fmt(chunk)?),
+                None => return Ok(None),
+            }
+        }
+        offset = end.saturating_add(size as usize % 2);
+    }
+    Ok(None)
+}
+
+impl AudioBuffer {
+    /// Number of bytes in one sample for all channels.
+    pub fn block_align(&self) -> u16 {
+        self.channels * self.bits_per_sample.div_ceil(8)
+    }
+
+    /// Number of samples per channel.
+    pub fn frames(&self) -> usize {
+        match self.block_align() {
+            0 => 0,
+            block_align => self.data.len() / block_align as usize,
+        }
+    }
+
+    /// How long the audio plays.
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// Parse a RIFF/WAVE file holding uncompressed PCM.
+    pub fn from_wav(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(TTSError::Audio("not a RIFF/WAVE file".to_string()));
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+            let start = offset + 8;
+            // Streaming writers leave the size unset, so trust the file length instead.
+            let end = start.saturating_add(size as usize).min(bytes.len());
+            let chunk = &bytes[start..end];
+
+            match id {
+                b"fmt " => format = Some(parse_fmt(chunk)?),
+                b"data" => data = Some(chunk.to_vec()),
+                _ => {}
+            }
+
+            // Chunks are padded to an even length.
+            offset = end + (end - start) % 2;
+        }
+
+        let (channels, sample_rate, bits_per_sample) =
+            format.ok_or_else(|| TTSError::Audio("missing fmt chunk".to_string()))?;
+        let data = data.ok_or_else(|| TTSError::Audio("missing data chunk".to_string()))?;
+        Ok(AudioBuffer {
+            sample_rate,
+            channels,
+            bits_per_sample,
+            data,
+        })
+    }
+
+    /// Encode as a canonical 44-byte-header WAV file.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let block_align = self.block_align();
+        let data_len = self.data.len() as u32;
+
+        let mut wav = Vec::with_capacity(44 + self.data.len() + 1);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len + data_len % 2).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        wav.extend_from_slice(&self.channels.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend_from_slice(&self.data);
+        if data_len % 2 == 1 {
+            wav.push(0);
+        }
+        wav
+    }
+
+    /// Write the audio to `path` as a WAV file.
+    pub fn write_wav<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_wav()).map_err(|e| TTSError::Audio(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone() -> AudioBuffer {
+        AudioBuffer {
+            sample_rate: 16_000,
+            channels: 1,
+            bits_per_sample: 16,
+            data: (0..3200u32).map(|i| (i % 256) as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn wav_round_trip() {
+        let audio = tone();
+        let wav = audio.to_wav();
+        assert_eq!(wav.len(), 44 + audio.data.len());
+        assert_eq!(AudioBuffer::from_wav(&wav).unwrap(), audio);
+        assert_eq!(audio.duration(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn skips_unknown_chunks() {
+        let audio = tone();
+        let wav = audio.to_wav();
+        // Insert an odd-sized LIST chunk (with its padding byte) between fmt and data.
+        let mut with_list = wav[..36].to_vec();
+        with_list.extend_from_slice(b"LIST");
+        with_list.extend_from_slice(&3u32.to_le_bytes());
+        with_list.extend_from_slice(b"abc\0");
+        with_list.extend_from_slice(&wav[36..]);
+        assert_eq!(AudioBuffer::from_wav(&with_list).unwrap(), audio);
+    }
+
+    #[test]
+    fn header_is_read_once_it_has_arrived() {
+        let audio = tone();
+        let wav = audio.to_wav();
+        for len in [0, 12, 30, 43] {
+            assert_eq!(wav_header(&wav[..len]).unwrap(), None);
+        }
+        let (format, start) = wav_header(&wav[..50]).unwrap().unwrap();
+        assert_eq!(start, 44);
+        assert_eq!(
+            format,
+            AudioBuffer {
+                data: Vec::new(),
+                ..audio
+            }
+        );
+        assert!(wav_header(b"ID3\x04not a wave file").is_err());
+    }
+
+    #[test]
+    fn rejects_non_wav() {
+        assert!(AudioBuffer::from_wav(b"ID3\x04not a wave file").is_err());
+        let mut float_wav = tone().to_wav();
+        float_wav[20] = 3;
+        assert!(AudioBuffer::from_wav(&float_wav).is_err());
+    }
+}
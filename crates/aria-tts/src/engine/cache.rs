@@ -0,0 +1,350 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use lru::LruCache;
+use tokio::sync::broadcast;
+
+use super::{Prosody, SpeechEngine, VoiceInfo};
+use crate::audio::AudioBuffer;
+use crate::error::Result;
+use crate::event::SpeechEvent;
+
+/// Everything that changes what an utterance sounds like.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    text: String,
+    voice: Option<String>,
+    /// Rate, pitch and volume as bit patterns, since `f64` is not `Hash`.
+    prosody: [Option<u64>; 3],
+}
+
+impl CacheKey {
+    fn new(text: &str, voice: Option<String>, prosody: &Prosody) -> Self {
+        CacheKey {
+            text: text.to_string(),
+            voice,
+            prosody: [prosody.rate, prosody.pitch, prosody.volume].map(|v| v.map(f64::to_bits)),
+        }
+    }
+}
+
+/// How well an [`CachingEngine`] is doing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    /// Size of all cached audio.
+    pub bytes: usize,
+}
+
+#[derive(Debug)]
+struct AudioCache {
+    entries: LruCache<CacheKey, Arc<AudioBuffer>>,
+    max_bytes: usize,
+    stats: CacheStats,
+}
+
+impl AudioCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Arc<AudioBuffer>> {
+        let audio = self.entries.get(key).cloned();
+        match audio {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        audio
+    }
+
+    fn insert(&mut self, key: CacheKey, audio: Arc<AudioBuffer>) {
+        if audio.data.len() > self.max_bytes {
+            return;
+        }
+        self.stats.bytes += audio.data.len();
+        if let Some((_, replaced)) = self.entries.push(key, audio) {
+            self.stats.bytes -= replaced.data.len();
+        }
+        while self.stats.bytes > self.max_bytes {
+            match self.entries.pop_lru() {
+                Some((_, evicted)) => self.stats.bytes -= evicted.data.len(),
+                None => break,
+            }
+        }
+        self.stats.entries = self.entries.len();
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.stats.entries = 0;
+        self.stats.bytes = 0;
+    }
+}
+
+/// Wraps another engine and keeps the audio of recent utterances, so that
+/// announcements that come up again and again, such as "OK, button", are played
+/// without being synthesized again.
+///
+/// Entries are keyed by text, voice and prosody and evicted least recently used
+/// first once the audio exceeds the memory cap. Only utterances of up to
+/// `max_chars` characters are cached; longer text goes straight to the inner
+/// engine. Cached audio plays without word and sentence boundaries, so tracked
+/// speech always goes to the inner engine too.
+pub struct CachingEngine {
+    inner: Arc<dyn SpeechEngine>,
+    cache: Mutex<AudioCache>,
+    max_chars: usize,
+    /// The voice selected through this engine; `None` is the inner engine's initial voice.
+    voice: Mutex<Option<String>>,
+}
+
+impl CachingEngine {
+    /// Cache up to `max_bytes` of audio for utterances of up to `max_chars` characters.
+    pub fn new(inner: Arc<dyn SpeechEngine>, max_bytes: usize, max_chars: usize) -> Self {
+        CachingEngine {
+            inner,
+            cache: Mutex::new(AudioCache {
+                entries: LruCache::unbounded(),
+                max_bytes,
+                stats: CacheStats::default(),
+            }),
+            max_chars,
+            voice: Mutex::new(None),
+        }
+    }
+
+    fn cache(&self) -> MutexGuard<'_, AudioCache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cache().stats
+    }
+
+    /// Drop all cached audio, keeping the hit and miss counters.
+    pub fn clear(&self) {
+        self.cache().clear();
+    }
+
+    fn key(&self, text: &str, prosody: &Prosody) -> Option<CacheKey> {
+        if text.chars().count() > self.max_chars {
+            return None;
+        }
+        let voice = self.voice.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        Some(CacheKey::new(text, voice, prosody))
+    }
+
+    /// Cached audio for `key`, synthesizing and caching it on a miss.
+    async fn audio(&self, key: CacheKey, prosody: &Prosody) -> Result<Arc<AudioBuffer>> {
+        if let Some(audio) = self.cache().get(&key) {
+            return Ok(audio);
+        }
+        let audio = Arc::new(self.inner.synthesize(&key.text, prosody).await?);
+        self.cache().insert(key, audio.clone());
+        Ok(audio)
+    }
+}
+
+#[async_trait]
+impl SpeechEngine for CachingEngine {
+    async fn speak(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        match self.key(text, prosody) {
+            Some(key) => {
+                let audio = self.audio(key, prosody).await?;
+                self.inner.play(&audio).await
+            }
+            None => self.inner.speak(text, prosody).await,
+        }
+    }
+
+    async fn prepare(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        match self.key(text, prosody) {
+            Some(key) => self.audio(key, prosody).await.map(|_| ()),
+            None => self.inner.prepare(text, prosody).await,
+        }
+    }
+
+    async fn speak_tracked(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        self.inner.speak_tracked(text, prosody).await
+    }
+
+    async fn prepare_tracked(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        self.inner.prepare_tracked(text, prosody).await
+    }
+
+    async fn synthesize(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer> {
+        match self.key(text, prosody) {
+            Some(key) => Ok(self.audio(key, prosody).await?.as_ref().clone()),
+            None => self.inner.synthesize(text, prosody).await,
+        }
+    }
+
+    async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        self.inner.play(audio).await
+    }
+
+    async fn wait_for_completion(&self) -> Result<()> {
+        self.inner.wait_for_completion().await
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        self.inner.subscribe()
+    }
+
+    async fn stop(&self) -> Result<()> {
+        self.inner.stop().await
+    }
+
+    async fn voices(&self) -> Result<Vec<VoiceInfo>> {
+        self.inner.voices().await
+    }
+
+    async fn default_voice(&self) -> Result<VoiceInfo> {
+        self.inner.default_voice().await
+    }
+
+    async fn set_voice(&self, voice: &VoiceInfo) -> Result<()> {
+        self.inner.set_voice(voice).await?;
+        *self.voice.lock().unwrap_or_else(|e| e.into_inner()) = Some(voice.id.clone());
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        let stats = self.stats();
+        log::info!(
+            "Speech cache: {} hits, {} misses, {} entries ({} bytes)",
+            stats.hits,
+            stats.misses,
+            stats.entries,
+            stats.bytes
+        );
+        self.inner.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::RecordingEngine;
+
+    fn caching(max_bytes: usize) -> (Arc<RecordingEngine>, CachingEngine) {
+        let inner = Arc::new(RecordingEngine::with_voices(vec![
+            VoiceInfo {
+                id: "zira".to_string(),
+                display_name: "Zira".to_string(),
+                language: "en-US".to_string(),
+                gender: "Female".to_string(),
+            },
+            VoiceInfo {
+                id: "hedda".to_string(),
+                display_name: "Hedda".to_string(),
+                language: "de-DE".to_string(),
+                gender: "Female".to_string(),
+            },
+        ]));
+        let engine = CachingEngine::new(inner.clone(), max_bytes, 20);
+        (inner, engine)
+    }
+
+    fn synthesized_texts(engine: &RecordingEngine) -> Vec<String> {
+        engine.synthesized().into_iter().map(|u| u.text).collect()
+    }
+
+    #[tokio::test]
+    async fn repeated_utterances_are_synthesized_once() {
+        let (inner, engine) = caching(1 << 20);
+        let normal = Prosody::default();
+
+        engine.speak("OK, button", &normal).await.unwrap();
+        engine.speak("Cancel, button", &normal).await.unwrap();
+        engine.speak("OK, button", &normal).await.unwrap();
+
+        assert_eq!(
+            synthesized_texts(&inner),
+            vec!["OK, button", "Cancel, button"]
+        );
+        assert_eq!(inner.played().len(), 3);
+        assert_eq!(inner.played()[0], inner.played()[2]);
+        let stats = engine.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+    }
+
+    #[tokio::test]
+    async fn voice_and_prosody_are_part_of_the_key() {
+        let (inner, engine) = caching(1 << 20);
+        let raised = Prosody {
+            pitch: Some(1.5),
+            ..Prosody::default()
+        };
+
+        engine.speak("A", &Prosody::default()).await.unwrap();
+        engine.speak("A", &raised).await.unwrap();
+        let hedda = inner.voices().await.unwrap()[1].clone();
+        engine.set_voice(&hedda).await.unwrap();
+        engine.speak("A", &Prosody::default()).await.unwrap();
+
+        assert_eq!(inner.synthesized().len(), 3);
+        assert_eq!(engine.stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_audio_is_evicted() {
+        // The recording engine synthesizes 320 bytes per character.
+        let (inner, engine) = caching(320 * 4);
+        let normal = Prosody::default();
+
+        engine.speak("ab", &normal).await.unwrap();
+        engine.speak("cd", &normal).await.unwrap();
+        engine.speak("ab", &normal).await.unwrap();
+        engine.speak("ef", &normal).await.unwrap();
+        assert_eq!(engine.stats().bytes, 320 * 4);
+
+        engine.speak("ab", &normal).await.unwrap();
+        engine.speak("cd", &normal).await.unwrap();
+        assert_eq!(synthesized_texts(&inner), vec!["ab", "cd", "ef", "cd"]);
+    }
+
+    #[tokio::test]
+    async fn prepared_text_is_played_from_the_cache() {
+        let (inner, engine) = caching(1 << 20);
+        let normal = Prosody::default();
+        let long = "This sentence is longer than twenty characters.";
+
+        engine.prepare("Next sentence.", &normal).await.unwrap();
+        engine.prepare(long, &normal).await.unwrap();
+        engine.speak("Next sentence.", &normal).await.unwrap();
+
+        assert_eq!(synthesized_texts(&inner), vec!["Next sentence."]);
+        assert_eq!(inner.prepared()[0].text, long);
+        assert_eq!(engine.stats().hits, 1);
+    }
+
+    #[tokio::test]
+    async fn tracked_speech_bypasses_the_cache() {
+        let (inner, engine) = caching(1 << 20);
+        let mut events = inner.subscribe();
+        let normal = Prosody::default();
+
+        engine.speak("OK, button", &normal).await.unwrap();
+        engine.prepare_tracked("OK, button", &normal).await.unwrap();
+        engine.speak_tracked("OK, button", &normal).await.unwrap();
+
+        assert_eq!(inner.spoken_texts(), vec!["OK, button"]);
+        assert_eq!(inner.prepared()[0].text, "OK, button");
+        assert_eq!(engine.stats().hits, 0);
+        let boundaries = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|e| matches!(e, SpeechEvent::Boundary(_)))
+            .count();
+        assert_eq!(boundaries, 2);
+    }
+
+    #[tokio::test]
+    async fn long_text_bypasses_the_cache() {
+        let (inner, engine) = caching(1 << 20);
+        let text = "This sentence is longer than twenty characters.";
+
+        engine.speak(text, &Prosody::default()).await.unwrap();
+
+        assert_eq!(inner.spoken_texts(), vec![text]);
+        assert!(inner.synthesized().is_empty());
+        assert_eq!(engine.stats(), CacheStats::default());
+    }
+}
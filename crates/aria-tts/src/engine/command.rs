@@ -0,0 +1,383 @@
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use aria_utils::config::{AriaConfig, AudioFormat, CommandEngineConfig};
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::broadcast;
+
+#[cfg(feature = "playback")]
+use super::playback::AudioPlayer;
+use super::{Prepared, Prosody, SpeechEngine, VoiceInfo};
+use crate::audio::AudioBuffer;
+use crate::error::{Result, TTSError};
+use crate::event::{SpeechEvent, SpeechEvents};
+use crate::voice::VoiceSelector;
+
+/// Speech engine that runs an external program, such as eSpeak NG or Piper, for
+/// every utterance and reads the audio it writes to stdout.
+///
+/// Configured by the `[command]` table of aria.toml, see [`CommandEngineConfig`].
+/// Playing the audio needs the `playback` feature; synthesizing to a buffer
+/// always works.
+pub struct CommandEngine {
+    config: CommandEngineConfig,
+    voices: Vec<VoiceInfo>,
+    /// `None` leaves the choice of voice to the program.
+    voice: Mutex<Option<VoiceInfo>>,
+    default_prosody: Prosody,
+    prepared: Prepared<AudioBuffer>,
+    events: Arc<SpeechEvents>,
+    #[cfg(feature = "playback")]
+    player: AudioPlayer,
+}
+
+impl CommandEngine {
+    pub fn new(config: CommandEngineConfig, default_prosody: Prosody) -> Self {
+        let voices = config
+            .voices
+            .iter()
+            .map(|v| VoiceInfo {
+                id: v.id.clone(),
+                display_name: v.name.clone().unwrap_or_else(|| v.id.clone()),
+                language: v.language.clone().unwrap_or_default(),
+                gender: v.gender.clone().unwrap_or_else(|| "Unknown".to_string()),
+            })
+            .collect();
+        let events = Arc::new(SpeechEvents::default());
+        CommandEngine {
+            config,
+            voices,
+            voice: Mutex::new(None),
+            default_prosody,
+            prepared: Prepared::default(),
+            #[cfg(feature = "playback")]
+            player: AudioPlayer::new(events.clone()),
+            events,
+        }
+    }
+
+    /// Create the engine from the `[command]` table, with the configured rate,
+    /// pitch, volume and voice.
+    pub fn from_config(config: &AriaConfig) -> Result<Self> {
+        let command = config.command.clone().ok_or(TTSError::Init(
+            "the command engine needs a [command] table in aria.toml",
+        ))?;
+        let engine = Self::new(
+            command,
+            Prosody {
+                rate: Some(config.speech_rate),
+                pitch: Some(config.pitch),
+                volume: Some(config.volume),
+            },
+        );
+
+        if let Some(selector) = &config.voice {
+            let selected = selector
+                .parse::<VoiceSelector>()
+                .and_then(|s| s.select(&engine.voices).cloned());
+            match selected {
+                Ok(voice) => *engine.voice() = Some(voice),
+                Err(e) => log::warn!("Ignoring voice from config: {}", e),
+            }
+        }
+        Ok(engine)
+    }
+
+    fn voice(&self) -> MutexGuard<'_, Option<VoiceInfo>> {
+        self.voice.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The configured arguments, with the voice arguments if `voice` is set.
+    fn templates(&self, voice: Option<&VoiceInfo>) -> impl Iterator<Item = &String> {
+        let voice_args = match voice {
+            Some(_) => &self.config.voice_args[..],
+            None => &[],
+        };
+        self.config.args.iter().chain(voice_args)
+    }
+
+    /// The program's arguments with every placeholder filled in.
+    fn args(&self, text: &str, prosody: &Prosody, voice: Option<&VoiceInfo>) -> Vec<String> {
+        let prosody = prosody.or(self.default_prosody);
+        let number = |value: Option<f64>| value.unwrap_or(1.0);
+        let percent = |value: Option<f64>| format!("{:.0}", number(value) * 100.0);
+        let values = [
+            ("text", text.to_string()),
+            ("voice", voice.map(|v| v.id.clone()).unwrap_or_default()),
+            ("rate", number(prosody.rate).to_string()),
+            ("pitch", number(prosody.pitch).to_string()),
+            ("volume", number(prosody.volume).to_string()),
+            ("rate_percent", percent(prosody.rate)),
+            ("pitch_percent", percent(prosody.pitch)),
+            ("volume_percent", percent(prosody.volume)),
+        ];
+
+        self.templates(voice)
+            .map(|arg| fill_placeholders(arg, &values))
+            .collect()
+    }
+
+    /// Run the program and return what it wrote to stdout.
+    async fn run(&self, text: &str, prosody: &Prosody) -> Result<Vec<u8>> {
+        let program = &self.config.program;
+        let voice = self.voice().clone();
+        let text_in_args = self
+            .templates(voice.as_ref())
+            .any(|arg| arg.contains("{text}"));
+
+        let mut child = Command::new(program)
+            .args(self.args(text, prosody, voice.as_ref()))
+            .stdin(if text_in_args {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| TTSError::Synthesis(format!("failed to run `{}`: {}", program, e)))?;
+
+        // Write concurrently with reading, so a program that streams its output
+        // before consuming all input cannot deadlock.
+        let stdin = child.stdin.take();
+        let input = text.to_string();
+        let write = async move {
+            if let Some(mut stdin) = stdin {
+                // A program that exits without reading everything is not an error here.
+                let _ = stdin.write_all(input.as_bytes()).await;
+            }
+        };
+        let (_, output) = tokio::join!(write, child.wait_with_output());
+        let output = output
+            .map_err(|e| TTSError::Synthesis(format!("failed to run `{}`: {}", program, e)))?;
+
+        if !output.status.success() {
+            return Err(TTSError::Synthesis(format!(
+                "`{}` exited with {}: {}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// `template` with every `{name}` of `values` replaced by its value in one pass,
+/// so placeholders within the values, such as in the text, stay as they are.
+fn fill_placeholders(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest.find('}').and_then(|close| {
+            let name = &rest[1..close];
+            let (_, value) = values
+                .iter()
+                .find(|(placeholder, _)| *placeholder == name)?;
+            Some((value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                filled.push_str(value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[async_trait]
+impl SpeechEngine for CommandEngine {
+    async fn speak(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let audio = match self.prepared.take(text, prosody) {
+            Some(audio) => audio,
+            None => self.synthesize(text, prosody).await?,
+        };
+        self.play(&audio).await
+    }
+
+    async fn prepare(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let audio = self.synthesize(text, prosody).await?;
+        self.prepared.put(text, prosody, audio);
+        Ok(())
+    }
+
+    async fn synthesize(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer> {
+        let output = self.run(text, prosody).await?;
+        match self.config.format {
+            AudioFormat::Wav => AudioBuffer::from_wav(&output),
+            AudioFormat::Raw => Ok(AudioBuffer {
+                sample_rate: self.config.sample_rate,
+                channels: self.config.channels,
+                bits_per_sample: self.config.bits_per_sample,
+                data: output,
+            }),
+        }
+    }
+
+    #[cfg(feature = "playback")]
+    async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        self.player.play(audio).await
+    }
+
+    #[cfg(not(feature = "playback"))]
+    async fn play(&self, _audio: &AudioBuffer) -> Result<()> {
+        Err(TTSError::Unsupported(
+            "audio playback; build aria-tts with the `playback` feature",
+        ))
+    }
+
+    async fn wait_for_completion(&self) -> Result<()> {
+        #[cfg(feature = "playback")]
+        self.player.wait().await;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        // External programs do not report their progress, only playback starts and ends.
+        self.events.subscribe()
+    }
+
+    async fn stop(&self) -> Result<()> {
+        #[cfg(feature = "playback")]
+        self.player.stop();
+        Ok(())
+    }
+
+    async fn voices(&self) -> Result<Vec<VoiceInfo>> {
+        Ok(self.voices.clone())
+    }
+
+    async fn default_voice(&self) -> Result<VoiceInfo> {
+        Ok(VoiceInfo {
+            id: String::new(),
+            display_name: format!("{} default", self.config.program),
+            language: String::new(),
+            gender: "Unknown".to_string(),
+        })
+    }
+
+    async fn set_voice(&self, voice: &VoiceInfo) -> Result<()> {
+        match self.voices.iter().find(|v| v.id == voice.id) {
+            Some(voice) => {
+                *self.voice() = Some(voice.clone());
+                self.prepared.clear();
+                Ok(())
+            }
+            None => Err(TTSError::VoiceNotFound {
+                selector: format!("id:{}", voice.id),
+                close_matches: Vec::new(),
+            }),
+        }
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.stop().await
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use aria_utils::config::{CommandVoiceConfig, EngineKind};
+
+    /// A `sh` script that writes its output as 8-bit raw audio.
+    fn script(script: &str) -> CommandEngineConfig {
+        CommandEngineConfig {
+            args: vec!["-c".to_string(), script.to_string(), "sh".to_string()],
+            format: AudioFormat::Raw,
+            sample_rate: 8_000,
+            bits_per_sample: 8,
+            ..CommandEngineConfig::new("sh")
+        }
+    }
+
+    #[tokio::test]
+    async fn text_is_piped_through_stdin() {
+        let engine = CommandEngine::new(script("cat"), Prosody::default());
+        let audio = engine
+            .synthesize("Hello", &Prosody::default())
+            .await
+            .unwrap();
+        assert_eq!(audio.data, b"Hello");
+        assert_eq!(audio.sample_rate, 8_000);
+    }
+
+    #[tokio::test]
+    async fn placeholders_and_voice_args_are_filled_in() {
+        let mut command = script(r#"printf '%s' "$*""#);
+        command.args.push("{text}".to_string());
+        command.voice_args = vec!["{voice}".to_string(), "{rate_percent}".to_string()];
+        command.voices = vec![CommandVoiceConfig {
+            id: "en-gb".to_string(),
+            name: Some("English (Great Britain)".to_string()),
+            language: Some("en-GB".to_string()),
+            gender: None,
+        }];
+        let config = AriaConfig {
+            engine: EngineKind::Command,
+            command: Some(command),
+            voice: Some("lang:en".to_string()),
+            ..AriaConfig::default()
+        };
+        let engine = CommandEngine::from_config(&config).unwrap();
+
+        let faster = Prosody {
+            rate: Some(1.5),
+            ..Prosody::default()
+        };
+        let audio = engine.synthesize("Hi there", &faster).await.unwrap();
+        assert_eq!(audio.data, b"Hi there en-gb 150");
+    }
+
+    #[tokio::test]
+    async fn placeholders_in_the_text_are_kept() {
+        let mut command = script(r#"printf '%s' "$*""#);
+        command
+            .args
+            .extend(["{{text}".to_string(), "{text}".to_string()]);
+        let engine = CommandEngine::new(command, Prosody::default());
+
+        let audio = engine
+            .synthesize("set {rate} for {voice}", &Prosody::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            audio.data,
+            b"{set {rate} for {voice} set {rate} for {voice}"
+        );
+    }
+
+    #[tokio::test]
+    async fn voice_args_without_a_voice_leave_the_text_on_stdin() {
+        let mut command = script("cat");
+        command.voice_args = vec!["{text}".to_string()];
+        let engine = CommandEngine::new(command, Prosody::default());
+
+        let audio = engine
+            .synthesize("Hello", &Prosody::default())
+            .await
+            .unwrap();
+        assert_eq!(audio.data, b"Hello");
+    }
+
+    #[tokio::test]
+    async fn failures_report_stderr() {
+        let engine = CommandEngine::new(script("echo 'no voice' >&2; exit 3"), Prosody::default());
+        let error = engine
+            .synthesize("Hello", &Prosody::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("no voice"), "{}", error);
+    }
+}
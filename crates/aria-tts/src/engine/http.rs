@@ -0,0 +1,690 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use aria_utils::config::{AriaConfig, HttpEngineConfig};
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::Value;
+use tokio::sync::{broadcast, OnceCell as TokioOnceCell};
+use tokio::time::Instant;
+
+#[cfg(feature = "playback")]
+use super::playback::AudioPlayer;
+use super::{default_engine, Prepared, Prosody, SpeechEngine, VoiceInfo};
+#[cfg(feature = "playback")]
+use crate::audio::wav_header;
+use crate::audio::AudioBuffer;
+use crate::error::{Result, TTSError};
+use crate::event::{SpeechEvent, SpeechEvents};
+use crate::voice::VoiceSelector;
+
+/// How long utterances go straight to the fallback engine after the server could
+/// not be reached, before it is tried again.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Whether the server could not be reached in time, as opposed to answering with an error.
+fn is_unavailable(error: &TTSError) -> bool {
+    matches!(error, TTSError::Http(e) if e.is_timeout() || e.is_connect())
+}
+
+/// Read a voice list in the format of OpenTTS (an object keyed by voice id) or
+/// as an array of objects with an `id` field.
+fn parse_voices(json: &Value) -> Vec<VoiceInfo> {
+    let field = |voice: &Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| voice.get(*key).and_then(Value::as_str))
+            .map(str::to_string)
+    };
+    let voice_info = |id: String, voice: &Value| VoiceInfo {
+        display_name: field(voice, &["name"]).unwrap_or_else(|| id.clone()),
+        language: field(voice, &["locale", "language"]).unwrap_or_default(),
+        gender: match field(voice, &["gender"]).as_deref() {
+            Some("M") | Some("m") => "Male".to_string(),
+            Some("F") | Some("f") => "Female".to_string(),
+            Some(gender) => gender.to_string(),
+            None => "Unknown".to_string(),
+        },
+        id,
+    };
+
+    match json {
+        Value::Object(voices) => voices
+            .iter()
+            .map(|(id, voice)| voice_info(id.clone(), voice))
+            .collect(),
+        Value::Array(voices) => voices
+            .iter()
+            .filter_map(|voice| Some(voice_info(field(voice, &["id"])?, voice)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Speech engine backed by a local TTS server with an OpenTTS-style HTTP API,
+/// such as OpenTTS, a Piper or Coqui server behind a small adapter, or a test
+/// stand-in.
+///
+/// Configured by the `[http]` table of aria.toml, see [`HttpEngineConfig`]. When
+/// the server does not answer in time, utterances go to an optional fallback
+/// engine instead, without asking the server again for [`RETRY_AFTER`]. With the
+/// `playback` feature, audio starts playing as soon as the first of it arrives.
+pub struct HttpEngine {
+    config: HttpEngineConfig,
+    client: reqwest::Client,
+    default_prosody: Prosody,
+    /// `None` leaves the choice of voice to the server.
+    voice: Mutex<Option<VoiceInfo>>,
+    /// The `voice` selector from aria.toml, applied once the server's voices are known.
+    configured_voice: Option<String>,
+    configured_voice_applied: TokioOnceCell<()>,
+    fallback: Option<Arc<dyn SpeechEngine>>,
+    /// Whether the utterance playing now was handed to the fallback engine.
+    using_fallback: AtomicBool,
+    /// Until when the server is left alone after it could not be reached.
+    retry_at: Mutex<Option<Instant>>,
+    prepared: Prepared<AudioBuffer>,
+    events: Arc<SpeechEvents>,
+    #[cfg(feature = "playback")]
+    player: AudioPlayer,
+}
+
+impl HttpEngine {
+    pub fn new(config: HttpEngineConfig, default_prosody: Prosody) -> Result<Self> {
+        // Only the gaps are limited, so long audio can take its time to stream in.
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let client = reqwest::Client::builder()
+            .connect_timeout(timeout)
+            .read_timeout(timeout)
+            .build()?;
+        let events = Arc::new(SpeechEvents::default());
+        Ok(HttpEngine {
+            config,
+            client,
+            default_prosody,
+            voice: Mutex::new(None),
+            configured_voice: None,
+            configured_voice_applied: TokioOnceCell::new(),
+            fallback: None,
+            using_fallback: AtomicBool::new(false),
+            retry_at: Mutex::new(None),
+            prepared: Prepared::default(),
+            #[cfg(feature = "playback")]
+            player: AudioPlayer::new(events.clone()),
+            events,
+        })
+    }
+
+    /// Speak through `fallback` whenever the server does not answer in time.
+    /// Must be called from within a Tokio runtime, which forwards the fallback's events.
+    pub fn with_fallback(mut self, fallback: Arc<dyn SpeechEngine>) -> Self {
+        let mut fallback_events = fallback.subscribe();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            loop {
+                match fallback_events.recv().await {
+                    Ok(event) => events.send(event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Create the engine from the `[http]` table, with the configured rate, pitch,
+    /// volume and voice, falling back to the built-in engine if enabled.
+    pub fn from_config(config: &AriaConfig) -> Result<Self> {
+        let http = config.http.clone().ok_or(TTSError::Init(
+            "the http engine needs an [http] table in aria.toml",
+        ))?;
+        let use_fallback = http.fallback;
+        let mut engine = Self::new(
+            http,
+            Prosody {
+                rate: Some(config.speech_rate),
+                pitch: Some(config.pitch),
+                volume: Some(config.volume),
+            },
+        )?;
+        engine.configured_voice = config.voice.clone();
+
+        if use_fallback {
+            match default_engine() {
+                Ok(fallback) => engine = engine.with_fallback(fallback),
+                Err(e) => log::info!("No fallback for the TTS server: {}", e),
+            }
+        }
+        Ok(engine)
+    }
+
+    fn voice(&self) -> MutexGuard<'_, Option<VoiceInfo>> {
+        self.voice.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The selected voice, resolving the configured selector on first use.
+    async fn current_voice(&self) -> Option<VoiceInfo> {
+        self.configured_voice_applied
+            .get_or_init(|| async {
+                let Some(selector) = &self.configured_voice else {
+                    return;
+                };
+                let selected = match (
+                    selector.parse::<VoiceSelector>(),
+                    self.server_voices().await,
+                ) {
+                    (Ok(selector), Ok(voices)) => selector.select(&voices).cloned(),
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                };
+                match selected {
+                    Ok(voice) => *self.voice() = Some(voice),
+                    Err(e) => log::warn!("Ignoring voice from config: {}", e),
+                }
+            })
+            .await;
+        self.voice().clone()
+    }
+
+    async fn server_voices(&self) -> Result<Vec<VoiceInfo>> {
+        let Some(url) = &self.config.voices_url else {
+            return Ok(Vec::new());
+        };
+        let body = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let json: Value = serde_json::from_slice(&body)
+            .map_err(|e| TTSError::Synthesis(format!("invalid voice list from {}: {}", url, e)))?;
+        Ok(parse_voices(&json))
+    }
+
+    /// Ask the server to synthesize `text`, returning once the response starts.
+    async fn request(&self, text: &str, prosody: &Prosody) -> Result<reqwest::Response> {
+        let prosody = prosody.or(self.default_prosody);
+        let mut query = Vec::new();
+        if let Some(voice) = self.current_voice().await {
+            query.push(("voice", voice.id));
+        }
+        for (name, value) in [
+            ("rate", prosody.rate),
+            ("pitch", prosody.pitch),
+            ("volume", prosody.volume),
+        ] {
+            if let Some(value) = value {
+                query.push((name, value.to_string()));
+            }
+        }
+
+        Ok(self
+            .client
+            .post(&self.config.url)
+            .query(&query)
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(text.to_string())
+            .send()
+            .await?
+            .error_for_status()?)
+    }
+
+    /// Ask the server to synthesize `text` and read all of the audio.
+    async fn fetch(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer> {
+        let mut response = self.request(text, prosody).await?;
+        let mut wav = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            wav.extend_from_slice(&chunk);
+        }
+        AudioBuffer::from_wav(&wav)
+    }
+
+    /// Ask the server to synthesize `text` and play the audio as it streams in,
+    /// starting as soon as the WAV header has arrived.
+    #[cfg(feature = "playback")]
+    async fn speak_on_server(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let mut response = self.request(text, prosody).await?;
+        let mut head = Vec::new();
+        let (format, start) = loop {
+            match response.chunk().await? {
+                Some(chunk) => head.extend_from_slice(&chunk),
+                None => return self.play(&AudioBuffer::from_wav(&head)?).await,
+            }
+            if let Some(header) = wav_header(&head)? {
+                break header;
+            }
+        };
+
+        // Samples cut in two by a chunk wait for the rest of them.
+        let block_align = format.block_align().max(1) as usize;
+        let mut pending = head.split_off(start);
+        let take_samples = move |pending: &mut Vec<u8>| AudioBuffer {
+            data: pending
+                .drain(..pending.len() / block_align * block_align)
+                .collect(),
+            ..format.clone()
+        };
+        let first = take_samples(&mut pending);
+
+        let (rest_tx, rest_rx) = std::sync::mpsc::channel();
+        self.stop().await?;
+        self.using_fallback.store(false, Ordering::SeqCst);
+        self.player.play_stream(&first, rest_rx).await?;
+        tokio::spawn(async move {
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => pending.extend_from_slice(&chunk),
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::warn!("TTS server broke off the audio: {}", e);
+                        break;
+                    }
+                }
+                // Fails once playback was stopped.
+                if rest_tx.send(take_samples(&mut pending)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Ask the server to synthesize `text` and play it once all of it arrived.
+    #[cfg(not(feature = "playback"))]
+    async fn speak_on_server(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        self.play(&self.fetch(text, prosody).await?).await
+    }
+
+    /// The fallback engine while the server is left alone after it could not be reached.
+    fn resting_fallback(&self) -> Option<&Arc<dyn SpeechEngine>> {
+        let fallback = self.fallback.as_ref()?;
+        let mut retry_at = self.retry_at.lock().unwrap_or_else(|e| e.into_inner());
+        match *retry_at {
+            Some(at) if Instant::now() < at => Some(fallback),
+            _ => {
+                *retry_at = None;
+                None
+            }
+        }
+    }
+
+    /// The fallback engine, if `error` means the server is unavailable and there is one.
+    /// The server is left alone for [`RETRY_AFTER`] from then on.
+    fn fallback_for(&self, error: &TTSError) -> Option<&Arc<dyn SpeechEngine>> {
+        let fallback = self.fallback.as_ref().filter(|_| is_unavailable(error))?;
+        log::warn!(
+            "TTS server unavailable, using the fallback engine for {:?}: {}",
+            RETRY_AFTER,
+            error
+        );
+        *self.retry_at.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(Instant::now() + RETRY_AFTER);
+        Some(fallback)
+    }
+
+    /// Speak `text` through `fallback`.
+    async fn speak_with_fallback(
+        &self,
+        fallback: &Arc<dyn SpeechEngine>,
+        text: &str,
+        prosody: &Prosody,
+    ) -> Result<()> {
+        self.using_fallback.store(true, Ordering::SeqCst);
+        fallback.speak(text, prosody).await
+    }
+}
+
+#[async_trait]
+impl SpeechEngine for HttpEngine {
+    async fn speak(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        self.stop().await?;
+        if let Some(audio) = self.prepared.take(text, prosody) {
+            return self.play(&audio).await;
+        }
+        if let Some(fallback) = self.resting_fallback() {
+            return self.speak_with_fallback(fallback, text, prosody).await;
+        }
+        match self.speak_on_server(text, prosody).await {
+            Err(e) => match self.fallback_for(&e) {
+                Some(fallback) => self.speak_with_fallback(fallback, text, prosody).await,
+                None => Err(e),
+            },
+            spoken => spoken,
+        }
+    }
+
+    async fn prepare(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        if let Some(fallback) = self.resting_fallback() {
+            return fallback.prepare(text, prosody).await;
+        }
+        match self.fetch(text, prosody).await {
+            Ok(audio) => {
+                self.prepared.put(text, prosody, audio);
+                Ok(())
+            }
+            Err(e) => match self.fallback_for(&e) {
+                Some(fallback) => fallback.prepare(text, prosody).await,
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn synthesize(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer> {
+        if let Some(fallback) = self.resting_fallback() {
+            return fallback.synthesize(text, prosody).await;
+        }
+        match self.fetch(text, prosody).await {
+            Err(e) => match self.fallback_for(&e) {
+                Some(fallback) => fallback.synthesize(text, prosody).await,
+                None => Err(e),
+            },
+            audio => audio,
+        }
+    }
+
+    #[cfg(feature = "playback")]
+    async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        self.stop().await?;
+        self.using_fallback.store(false, Ordering::SeqCst);
+        self.player.play(audio).await
+    }
+
+    /// Without a player of its own, the engine hands audio to the fallback.
+    #[cfg(not(feature = "playback"))]
+    async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        match &self.fallback {
+            Some(fallback) => {
+                self.using_fallback.store(true, Ordering::SeqCst);
+                fallback.play(audio).await
+            }
+            None => Err(TTSError::Unsupported(
+                "audio playback; build aria-tts with the `playback` feature",
+            )),
+        }
+    }
+
+    async fn wait_for_completion(&self) -> Result<()> {
+        if self.using_fallback.load(Ordering::SeqCst) {
+            if let Some(fallback) = &self.fallback {
+                return fallback.wait_for_completion().await;
+            }
+        }
+        #[cfg(feature = "playback")]
+        self.player.wait().await;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        self.events.subscribe()
+    }
+
+    async fn stop(&self) -> Result<()> {
+        #[cfg(feature = "playback")]
+        self.player.stop();
+        if self.using_fallback.load(Ordering::SeqCst) {
+            if let Some(fallback) = &self.fallback {
+                fallback.stop().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn voices(&self) -> Result<Vec<VoiceInfo>> {
+        if let Some(fallback) = self.resting_fallback() {
+            return fallback.voices().await;
+        }
+        match self.server_voices().await {
+            Err(e) => match self.fallback_for(&e) {
+                Some(fallback) => fallback.voices().await,
+                None => Err(e),
+            },
+            voices => voices,
+        }
+    }
+
+    async fn default_voice(&self) -> Result<VoiceInfo> {
+        Ok(VoiceInfo {
+            id: String::new(),
+            display_name: "Server default".to_string(),
+            language: String::new(),
+            gender: "Unknown".to_string(),
+        })
+    }
+
+    async fn set_voice(&self, voice: &VoiceInfo) -> Result<()> {
+        // Voices listed while the server was unavailable are the fallback's.
+        if let Some(fallback) = &self.fallback {
+            if fallback
+                .voices()
+                .await
+                .is_ok_and(|voices| voices.contains(voice))
+            {
+                return fallback.set_voice(voice).await;
+            }
+        }
+        // An explicit choice replaces the voice from the config.
+        let _ = self.configured_voice_applied.set(());
+        *self.voice() = Some(voice.clone());
+        self.prepared.clear();
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.stop().await?;
+        if let Some(fallback) = &self.fallback {
+            fallback.close().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::RecordingEngine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// A stand-in TTS server that answers every request with `body` after `delay`.
+    /// Returns its base URL and the requests it received, as request line and body.
+    async fn serve(body: Vec<u8>, delay: Duration) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                let requests = requests_tx.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    let (head_len, content_length) = loop {
+                        let read = socket.read(&mut buffer).await.unwrap();
+                        request.extend_from_slice(&buffer[..read]);
+                        let text = String::from_utf8_lossy(&request);
+                        if let Some(end) = text.find("\r\n\r\n") {
+                            let content_length = text[..end]
+                                .lines()
+                                .find_map(|line| {
+                                    let (name, value) = line.split_once(':')?;
+                                    name.eq_ignore_ascii_case("content-length")
+                                        .then(|| value.trim().parse::<usize>().unwrap())
+                                })
+                                .unwrap_or(0);
+                            break (end + 4, content_length);
+                        }
+                    };
+                    while request.len() < head_len + content_length {
+                        let read = socket.read(&mut buffer).await.unwrap();
+                        request.extend_from_slice(&buffer[..read]);
+                    }
+                    let text = String::from_utf8_lossy(&request).into_owned();
+                    let request_line = text.lines().next().unwrap_or_default();
+                    let _ = requests.send(format!("{} {}", request_line, &text[head_len..]));
+
+                    tokio::time::sleep(delay).await;
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&body).await;
+                });
+            }
+        });
+        (url, requests_rx)
+    }
+
+    fn tone() -> AudioBuffer {
+        AudioBuffer {
+            sample_rate: 22_050,
+            channels: 1,
+            bits_per_sample: 16,
+            data: vec![1, 2, 3, 4],
+        }
+    }
+
+    #[tokio::test]
+    async fn posts_text_with_voice_and_prosody() {
+        let (url, mut requests) = serve(tone().to_wav(), Duration::ZERO).await;
+        let engine = HttpEngine::new(
+            HttpEngineConfig::new(format!("{}/api/tts", url)),
+            Prosody::default(),
+        )
+        .unwrap();
+        engine
+            .set_voice(&VoiceInfo {
+                id: "larynx:harvard".to_string(),
+                display_name: "harvard".to_string(),
+                language: "en-us".to_string(),
+                gender: "Male".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let faster = Prosody {
+            rate: Some(1.5),
+            ..Prosody::default()
+        };
+        let audio = engine.synthesize("Hello, world", &faster).await.unwrap();
+
+        assert_eq!(audio, tone());
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "POST /api/tts?voice=larynx%3Aharvard&rate=1.5 HTTP/1.1 Hello, world"
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_voices_from_the_server() {
+        let voices = br#"{
+            "espeak:de": {"id": "de", "name": "german", "gender": "M", "locale": "de-de"},
+            "glow-speak:en-us_mary_ann": {"name": "mary_ann", "gender": "F", "language": "en"}
+        }"#;
+        let (url, _requests) = serve(voices.to_vec(), Duration::ZERO).await;
+        let mut config = HttpEngineConfig::new(format!("{}/api/tts", url));
+        config.voices_url = Some(format!("{}/api/voices", url));
+        let engine = HttpEngine::new(config, Prosody::default()).unwrap();
+
+        let voices = engine.voices().await.unwrap();
+        assert_eq!(voices.len(), 2);
+        assert_eq!(voices[0].id, "espeak:de");
+        assert_eq!(voices[0].language, "de-de");
+        assert_eq!(voices[1].display_name, "mary_ann");
+        assert_eq!(voices[1].gender, "Female");
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_the_server_is_too_slow() {
+        let (url, _requests) = serve(tone().to_wav(), Duration::from_secs(10)).await;
+        let mut config = HttpEngineConfig::new(url);
+        config.timeout_ms = 100;
+        let fallback = Arc::new(RecordingEngine::new());
+        let engine = HttpEngine::new(config, Prosody::default())
+            .unwrap()
+            .with_fallback(fallback.clone());
+
+        engine.speak("Hello", &Prosody::default()).await.unwrap();
+        engine.wait_for_completion().await.unwrap();
+
+        assert_eq!(fallback.spoken_texts(), vec!["Hello"]);
+    }
+
+    #[tokio::test]
+    async fn the_server_is_left_alone_after_a_timeout() {
+        let (url, mut requests) = serve(tone().to_wav(), Duration::from_secs(10)).await;
+        let mut config = HttpEngineConfig::new(url);
+        config.timeout_ms = 100;
+        let fallback = Arc::new(RecordingEngine::new());
+        let engine = HttpEngine::new(config, Prosody::default())
+            .unwrap()
+            .with_fallback(fallback.clone());
+
+        engine.speak("Hello", &Prosody::default()).await.unwrap();
+        let started = Instant::now();
+        engine.speak("again", &Prosody::default()).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(fallback.spoken_texts(), vec!["Hello", "again"]);
+        assert!(requests.recv().await.is_some());
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn voices_of_the_fallback_are_set_on_the_fallback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let mut config = HttpEngineConfig::new(format!("{}/api/tts", url));
+        config.voices_url = Some(format!("{}/api/voices", url));
+        let voice = |id: &str| VoiceInfo {
+            id: id.to_string(),
+            display_name: id.to_string(),
+            language: "en-US".to_string(),
+            gender: "Female".to_string(),
+        };
+        let fallback = Arc::new(RecordingEngine::with_voices(vec![
+            voice("zira"),
+            voice("aria"),
+        ]));
+        let engine = HttpEngine::new(config, Prosody::default())
+            .unwrap()
+            .with_fallback(fallback.clone());
+
+        let voices = engine.voices().await.unwrap();
+        engine.set_voice(&voices[1]).await.unwrap();
+        engine.speak("Hello", &Prosody::default()).await.unwrap();
+
+        assert_eq!(engine.voice().clone(), None);
+        assert_eq!(fallback.utterances()[0].voice, voice("aria"));
+    }
+
+    #[tokio::test]
+    async fn server_errors_do_not_fall_back() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = [0; 1024];
+                let _ = socket.read(&mut buffer).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+        let fallback = Arc::new(RecordingEngine::new());
+        let engine = HttpEngine::new(HttpEngineConfig::new(url), Prosody::default())
+            .unwrap()
+            .with_fallback(fallback.clone());
+
+        assert!(engine
+            .synthesize("Hello", &Prosody::default())
+            .await
+            .is_err());
+        assert!(fallback.synthesized().is_empty());
+    }
+}
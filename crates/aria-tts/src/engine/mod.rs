@@ -0,0 +1,274 @@
+use std::sync::{Arc, Mutex};
+
+use aria_utils::config::{get_config, AriaConfig, EngineKind};
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::audio::AudioBuffer;
+use crate::error::Result;
+use crate::event::SpeechEvent;
+
+mod cache;
+mod command;
+mod http;
+#[cfg(feature = "playback")]
+mod playback;
+mod recording;
+#[cfg(all(windows, feature = "windows"))]
+mod winrt;
+
+pub use self::cache::*;
+pub use self::command::*;
+pub use self::http::*;
+pub use self::recording::*;
+#[cfg(all(windows, feature = "windows"))]
+pub use self::winrt::*;
+
+/// Per-utterance overrides of the configured speech rate, pitch and volume.
+/// `None` keeps the engine's current setting.
+///
+/// Rate is a multiplier of normal speed, pitch ranges from 0 to 2 with 1 as the
+/// voice's natural pitch, and volume ranges from 0 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Prosody {
+    pub rate: Option<f64>,
+    pub pitch: Option<f64>,
+    pub volume: Option<f64>,
+}
+
+impl Prosody {
+    /// Fill in every value that is not overridden from `fallback`.
+    pub fn or(self, fallback: Prosody) -> Prosody {
+        Prosody {
+            rate: self.rate.or(fallback.rate),
+            pitch: self.pitch.or(fallback.pitch),
+            volume: self.volume.or(fallback.volume),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Prosody::default()
+    }
+}
+
+/// Speech an engine synthesized in [`SpeechEngine::prepare`], kept until the same
+/// text is spoken with the same prosody. Only the latest preparation is kept.
+pub(crate) struct Prepared<T> {
+    slot: Mutex<Option<(String, Prosody, T)>>,
+}
+
+impl<T> Default for Prepared<T> {
+    fn default() -> Self {
+        Prepared {
+            slot: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> Prepared<T> {
+    pub(crate) fn put(&self, text: &str, prosody: &Prosody, speech: T) {
+        *self.slot.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((text.to_string(), *prosody, speech));
+    }
+
+    /// The speech prepared for `text` and `prosody`, if that is what was prepared.
+    pub(crate) fn take(&self, text: &str, prosody: &Prosody) -> Option<T> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.take() {
+            Some((prepared, with, speech)) if prepared == text && with == *prosody => Some(speech),
+            _ => None,
+        }
+    }
+
+    /// Forget the prepared speech, e.g. because the voice changed.
+    pub(crate) fn clear(&self) {
+        *self.slot.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceInfo {
+    pub id: String,
+    pub display_name: String,
+    pub language: String,
+    pub gender: String,
+}
+
+/// A speech synthesis backend.
+///
+/// The `TTS` controller owns one engine and decides *when* something is spoken;
+/// the engine only knows *how* to turn text into audio on its platform.
+#[async_trait]
+pub trait SpeechEngine: Send + Sync {
+    /// Start speaking `text`, replacing whatever the engine is currently playing.
+    /// Values set in `prosody` override the engine's settings for this utterance only.
+    /// Returns once playback has started.
+    async fn speak(&self, text: &str, prosody: &Prosody) -> Result<()>;
+
+    /// Synthesize `text` ahead of time while something else plays, so that speaking
+    /// it with the same `prosody` next starts sooner. Engines that cannot do this
+    /// ignore it.
+    async fn prepare(&self, _text: &str, _prosody: &Prosody) -> Result<()> {
+        Ok(())
+    }
+
+    /// Like [`SpeechEngine::speak`], for an utterance whose word and sentence
+    /// boundaries are being followed. Engines that play some speech without
+    /// reporting boundaries, such as audio from a cache, must report them here.
+    async fn speak_tracked(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        self.speak(text, prosody).await
+    }
+
+    /// Like [`SpeechEngine::prepare`], for text to be spoken with
+    /// [`SpeechEngine::speak_tracked`].
+    async fn prepare_tracked(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        self.prepare(text, prosody).await
+    }
+
+    /// Synthesize `text` without playing it, e.g. to save it to a file.
+    async fn synthesize(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer>;
+
+    /// Start playing audio returned by [`SpeechEngine::synthesize`], replacing
+    /// whatever the engine is currently playing. Returns once playback has started.
+    async fn play(&self, audio: &AudioBuffer) -> Result<()>;
+
+    /// Resolves once the current utterance has finished playing, failed or was
+    /// stopped, as soon as the engine reports it.
+    async fn wait_for_completion(&self) -> Result<()>;
+
+    /// Receive the playback events of everything this engine plays from now on,
+    /// in the order described at [`SpeechEvent`].
+    fn subscribe(&self) -> broadcast::Receiver<SpeechEvent>;
+
+    /// Stop the current utterance, if any.
+    async fn stop(&self) -> Result<()>;
+
+    /// Get a list of all voices this engine can speak with.
+    async fn voices(&self) -> Result<Vec<VoiceInfo>>;
+
+    /// Get the voice used when no other voice has been selected.
+    async fn default_voice(&self) -> Result<VoiceInfo>;
+
+    /// Speak with `voice`, one of the voices returned by [`SpeechEngine::voices`].
+    async fn set_voice(&self, voice: &VoiceInfo) -> Result<()>;
+
+    /// Release any platform resources held by the engine.
+    async fn close(&self) -> Result<()>;
+}
+
+/// Create the speech engine built into the current platform.
+#[cfg(all(windows, feature = "windows"))]
+pub fn default_engine() -> Result<Arc<dyn SpeechEngine>> {
+    Ok(Arc::new(WinRtEngine::new()))
+}
+
+/// Create the speech engine built into the current platform.
+#[cfg(not(all(windows, feature = "windows")))]
+pub fn default_engine() -> Result<Arc<dyn SpeechEngine>> {
+    Err(crate::error::TTSError::Unsupported(
+        "no built-in speech engine; build for Windows with the `windows` feature",
+    ))
+}
+
+/// Create the engine selected by the `engine` key of aria.toml, behind an
+/// audio cache unless `cache_size_mb` is 0.
+pub fn engine_from_config(config: &AriaConfig) -> Result<Arc<dyn SpeechEngine>> {
+    let engine: Arc<dyn SpeechEngine> = match config.engine {
+        EngineKind::Builtin => default_engine()?,
+        EngineKind::Command => Arc::new(CommandEngine::from_config(config)?),
+        EngineKind::Http => Arc::new(HttpEngine::from_config(config)?),
+    };
+    if config.cache_size_mb == 0 {
+        return Ok(engine);
+    }
+    Ok(Arc::new(CachingEngine::new(
+        engine,
+        (config.cache_size_mb as usize).saturating_mul(1024 * 1024),
+        config.cache_max_chars as usize,
+    )))
+}
+
+/// Read aria.toml and create the engine it selects.
+pub fn configured_engine() -> Result<Arc<dyn SpeechEngine>> {
+    engine_from_config(&get_config()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::SpeechEvent;
+    use std::time::Duration;
+
+    #[test]
+    fn prosody_overrides_only_set_values() {
+        let defaults = Prosody {
+            rate: Some(1.0),
+            pitch: Some(1.0),
+            volume: Some(0.8),
+        };
+        let raised = Prosody {
+            pitch: Some(1.5),
+            ..Prosody::default()
+        };
+        assert_eq!(
+            raised.or(defaults),
+            Prosody {
+                rate: Some(1.0),
+                pitch: Some(1.5),
+                volume: Some(0.8),
+            }
+        );
+        assert!(Prosody::default().is_empty());
+        assert!(!raised.is_empty());
+    }
+
+    /// What every engine must report while it plays, checked against an engine
+    /// whose playback lasts until `finish` is called.
+    async fn assert_playback_contract(engine: &dyn SpeechEngine, finish: impl Fn()) {
+        let mut events = engine.subscribe();
+        let mut next = || {
+            std::iter::from_fn(|| events.try_recv().ok())
+                .find(|e| !matches!(e, SpeechEvent::Boundary(_)))
+        };
+        let normal = Prosody::default();
+
+        engine.speak("one", &normal).await.unwrap();
+        assert_eq!(next(), Some(SpeechEvent::Started));
+        // Replacing speech ends what was playing before the next start.
+        engine.speak("two", &normal).await.unwrap();
+        assert_eq!(next(), Some(SpeechEvent::Ended));
+        assert_eq!(next(), Some(SpeechEvent::Started));
+
+        // Waiting resolves with the end event, not on a timer.
+        let waited = tokio::time::timeout(Duration::from_millis(10), engine.wait_for_completion());
+        assert!(waited.await.is_err());
+        finish();
+        engine.wait_for_completion().await.unwrap();
+        assert_eq!(next(), Some(SpeechEvent::Ended));
+
+        // Stopping reports the end once, and nothing when idle.
+        let audio = engine.synthesize("three", &normal).await.unwrap();
+        engine.play(&audio).await.unwrap();
+        assert_eq!(next(), Some(SpeechEvent::Started));
+        engine.stop().await.unwrap();
+        engine.stop().await.unwrap();
+        assert_eq!(next(), Some(SpeechEvent::Ended));
+        assert_eq!(next(), None);
+        engine.wait_for_completion().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn recording_engine_keeps_the_playback_contract() {
+        let engine = RecordingEngine::new();
+        engine.hold_playback(true);
+        assert_playback_contract(&engine, || engine.finish_playback()).await;
+    }
+
+    #[tokio::test]
+    async fn caching_engine_keeps_the_playback_contract() {
+        let inner = Arc::new(RecordingEngine::new());
+        inner.hold_playback(true);
+        let engine = CachingEngine::new(inner.clone(), 1 << 20, 200);
+        assert_playback_contract(&engine, || inner.finish_playback()).await;
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+
+use rodio::{Decoder, OutputStream, Sink};
+use tokio::sync::oneshot;
+
+use crate::audio::AudioBuffer;
+use crate::error::{Result, TTSError};
+use crate::event::{SpeechEvent, SpeechEvents};
+
+/// Plays audio buffers on the default output device, one at a time, and reports
+/// their start and end to the engine's [`SpeechEvents`].
+///
+/// Each buffer gets its own output stream on a dedicated thread, since rodio's
+/// `OutputStream` cannot be moved between threads.
+pub(crate) struct AudioPlayer {
+    /// Also locked by the playing thread while it appends audio, so nothing is
+    /// appended to a sink once it was stopped.
+    current: Arc<Mutex<Option<Arc<Sink>>>>,
+    /// Counts buffers, so a thread whose buffer was replaced does not report the
+    /// end of its successor.
+    generation: Arc<AtomicU64>,
+    events: Arc<SpeechEvents>,
+}
+
+impl AudioPlayer {
+    pub(crate) fn new(events: Arc<SpeechEvents>) -> Self {
+        AudioPlayer {
+            current: Arc::new(Mutex::new(None)),
+            generation: Arc::new(AtomicU64::new(0)),
+            events,
+        }
+    }
+
+    fn current(&self) -> MutexGuard<'_, Option<Arc<Sink>>> {
+        self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Start playing `audio`, stopping whatever was playing before.
+    pub(crate) async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        let (_, rest) = mpsc::channel();
+        self.play_stream(audio, rest).await
+    }
+
+    /// Start playing `first`, stopping whatever was playing before, and play the
+    /// buffers received from `rest` after it as they arrive. Playback ends once
+    /// `rest` is closed and everything received has been played.
+    pub(crate) async fn play_stream(
+        &self,
+        first: &AudioBuffer,
+        rest: mpsc::Receiver<AudioBuffer>,
+    ) -> Result<()> {
+        self.stop();
+
+        let wav = first.to_wav();
+        let generation = self.generation.clone();
+        let this_generation = generation.load(Ordering::SeqCst);
+        let current = self.current.clone();
+        let events = self.events.clone();
+        let (sink_tx, sink_rx) = oneshot::channel();
+        std::thread::spawn(move || {
+            let started = OutputStream::try_default()
+                .map_err(|e| TTSError::MediaPlayer(format!("no audio output: {}", e)))
+                .and_then(|(stream, handle)| {
+                    let sink =
+                        Sink::try_new(&handle).map_err(|e| TTSError::MediaPlayer(e.to_string()))?;
+                    sink.append(decode(wav)?);
+                    Ok((stream, Arc::new(sink)))
+                });
+            match started {
+                Ok((_stream, sink)) => {
+                    // Reported here, so a short buffer cannot end before it started.
+                    events.send(SpeechEvent::Started);
+                    let _ = sink_tx.send(Ok(sink.clone()));
+                    for audio in rest {
+                        let source = match decode(audio.to_wav()) {
+                            Ok(source) => source,
+                            Err(e) => {
+                                log::warn!("Skipping streamed audio: {}", e);
+                                continue;
+                            }
+                        };
+                        let _current = current.lock().unwrap_or_else(|e| e.into_inner());
+                        if generation.load(Ordering::SeqCst) != this_generation {
+                            return;
+                        }
+                        sink.append(source);
+                    }
+                    sink.sleep_until_end();
+                    if generation.load(Ordering::SeqCst) == this_generation {
+                        events.send(SpeechEvent::Ended);
+                    }
+                }
+                Err(e) => {
+                    let _ = sink_tx.send(Err(e));
+                }
+            }
+        });
+
+        let started = sink_rx
+            .await
+            .map_err(|_| TTSError::MediaPlayer("audio thread exited".to_string()))
+            .and_then(|started| started);
+        match started {
+            Ok(sink) => {
+                *self.current() = Some(sink);
+                Ok(())
+            }
+            Err(e) => {
+                self.events.send(SpeechEvent::Failed(e.to_string()));
+                Err(e)
+            }
+        }
+    }
+
+    /// Resolves once the current buffer has finished playing or was stopped.
+    pub(crate) async fn wait(&self) {
+        self.events.wait_until_idle().await;
+    }
+
+    pub(crate) fn stop(&self) {
+        if let Some(sink) = self.current().take() {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            sink.stop();
+            self.events.send(SpeechEvent::Ended);
+        }
+    }
+}
+
+fn decode(wav: Vec<u8>) -> Result<Decoder<Cursor<Vec<u8>>>> {
+    Decoder::new(Cursor::new(wav)).map_err(|e| TTSError::Audio(e.to_string()))
+}
@@ -0,0 +1,421 @@
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use super::{Prosody, SpeechEngine, VoiceInfo};
+use crate::audio::AudioBuffer;
+use crate::error::{Result, TTSError};
+use crate::event::{Boundary, BoundaryKind, SpeechEvent, SpeechEvents};
+
+/// An utterance captured by the [`RecordingEngine`], together with the
+/// parameters it would have been spoken with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedUtterance {
+    pub text: String,
+    pub voice: VoiceInfo,
+    pub prosody: Prosody,
+}
+
+#[derive(Debug)]
+struct RecordingState {
+    voices: Vec<VoiceInfo>,
+    voice: VoiceInfo,
+    utterances: Vec<RecordedUtterance>,
+    synthesized: Vec<RecordedUtterance>,
+    prepared: Vec<RecordedUtterance>,
+    played: Vec<AudioBuffer>,
+    stop_count: usize,
+    is_closed: bool,
+    holds_playback: bool,
+}
+
+/// In-memory speech engine that never produces audio.
+///
+/// Every call to [`SpeechEngine::speak`] is recorded so tests can assert exactly
+/// what would have been said. Speaking reports [`SpeechEvent::Started`], a word
+/// boundary for every run of non-whitespace and [`SpeechEvent::Ended`] right away.
+/// Calls to [`SpeechEngine::prepare`] are recorded separately and change nothing
+/// else. [`SpeechEngine::synthesize`] returns silence lasting
+/// [`RecordingEngine::SILENCE_PER_CHAR`] per character, and [`SpeechEngine::play`]
+/// records the buffers it is given. Playback completes instantly unless
+/// [`RecordingEngine::hold_playback`] is enabled, in which case it ends with
+/// [`RecordingEngine::finish_playback`] or [`RecordingEngine::fail_playback`].
+#[derive(Debug)]
+pub struct RecordingEngine {
+    state: Mutex<RecordingState>,
+    events: SpeechEvents,
+}
+
+impl Default for RecordingEngine {
+    fn default() -> Self {
+        Self::with_voices(vec![VoiceInfo {
+            id: "recording-default".to_string(),
+            display_name: "Recording Default".to_string(),
+            language: "en-US".to_string(),
+            gender: "Unknown".to_string(),
+        }])
+    }
+}
+
+impl RecordingEngine {
+    /// Sample rate of the audio returned by [`SpeechEngine::synthesize`].
+    pub const SAMPLE_RATE: u32 = 16_000;
+    /// Milliseconds of silence synthesized per character of text.
+    pub const SILENCE_PER_CHAR: u32 = 10;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an engine offering the given voices. The first voice is the default.
+    pub fn with_voices(voices: Vec<VoiceInfo>) -> Self {
+        assert!(
+            !voices.is_empty(),
+            "a recording engine needs at least one voice"
+        );
+        RecordingEngine {
+            state: Mutex::new(RecordingState {
+                voice: voices[0].clone(),
+                voices,
+                utterances: Vec::new(),
+                synthesized: Vec::new(),
+                prepared: Vec::new(),
+                played: Vec::new(),
+                stop_count: 0,
+                is_closed: false,
+                holds_playback: false,
+            }),
+            events: SpeechEvents::default(),
+        }
+    }
+
+    /// Keep each utterance "playing" until it is stopped or
+    /// [`RecordingEngine::finish_playback`] is called, to simulate speech that
+    /// takes time.
+    pub fn hold_playback(&self, holds_playback: bool) {
+        self.state().holds_playback = holds_playback;
+    }
+
+    /// Report `event` to subscribers as if the engine had emitted it.
+    pub fn emit(&self, event: SpeechEvent) {
+        self.events.send(event);
+    }
+
+    /// End the utterance that is currently playing.
+    pub fn finish_playback(&self) {
+        self.emit(SpeechEvent::Ended);
+    }
+
+    /// Break off the utterance that is currently playing with an error.
+    pub fn fail_playback(&self, reason: &str) {
+        if self.is_playing() {
+            self.emit(SpeechEvent::Failed(reason.to_string()));
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.events.is_playing()
+    }
+
+    /// Report the start of playback, and its end unless playback is held.
+    fn start_playback(&self, holds_playback: bool, boundaries: Vec<std::ops::Range<usize>>) {
+        self.emit(SpeechEvent::Started);
+        for range in boundaries {
+            self.emit(SpeechEvent::Boundary(Boundary {
+                kind: BoundaryKind::Word,
+                range,
+            }));
+        }
+        if !holds_playback {
+            self.finish_playback();
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, RecordingState> {
+        // A panicking test must not hide the recording from the other assertions.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Every utterance spoken so far, in order.
+    pub fn utterances(&self) -> Vec<RecordedUtterance> {
+        self.state().utterances.clone()
+    }
+
+    /// The text of every utterance spoken so far, in order.
+    pub fn spoken_texts(&self) -> Vec<String> {
+        self.state()
+            .utterances
+            .iter()
+            .map(|u| u.text.clone())
+            .collect()
+    }
+
+    /// Every utterance passed to [`SpeechEngine::synthesize`] so far, in order.
+    pub fn synthesized(&self) -> Vec<RecordedUtterance> {
+        self.state().synthesized.clone()
+    }
+
+    /// Every utterance passed to [`SpeechEngine::prepare`] so far, in order.
+    pub fn prepared(&self) -> Vec<RecordedUtterance> {
+        self.state().prepared.clone()
+    }
+
+    /// Every buffer passed to [`SpeechEngine::play`] so far, in order.
+    pub fn played(&self) -> Vec<AudioBuffer> {
+        self.state().played.clone()
+    }
+
+    /// How many times [`SpeechEngine::stop`] has been called.
+    pub fn stop_count(&self) -> usize {
+        self.state().stop_count
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state().is_closed
+    }
+
+    /// Forget everything recorded so far, keeping the selected voice.
+    pub fn clear(&self) {
+        let mut state = self.state();
+        state.utterances.clear();
+        state.synthesized.clear();
+        state.prepared.clear();
+        state.played.clear();
+        state.stop_count = 0;
+    }
+}
+
+/// UTF-16 ranges of the whitespace-separated words in `text`.
+fn word_ranges_utf16(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for c in text.chars() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(offset),
+            (true, Some(s)) => {
+                ranges.push(s..offset);
+                start = None;
+            }
+            _ => {}
+        }
+        offset += c.len_utf16();
+    }
+    if let Some(s) = start {
+        ranges.push(s..offset);
+    }
+    ranges
+}
+
+#[async_trait]
+impl SpeechEngine for RecordingEngine {
+    async fn speak(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let mut state = self.state();
+        if state.is_closed {
+            return Err(TTSError::NotInitialized);
+        }
+        let voice = state.voice.clone();
+        state.utterances.push(RecordedUtterance {
+            text: text.to_string(),
+            voice,
+            prosody: *prosody,
+        });
+        let holds_playback = state.holds_playback;
+        drop(state);
+
+        // Whatever was playing is replaced.
+        self.finish_playback();
+        self.start_playback(holds_playback, word_ranges_utf16(text));
+        Ok(())
+    }
+
+    async fn prepare(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let mut state = self.state();
+        if state.is_closed {
+            return Err(TTSError::NotInitialized);
+        }
+        let voice = state.voice.clone();
+        state.prepared.push(RecordedUtterance {
+            text: text.to_string(),
+            voice,
+            prosody: *prosody,
+        });
+        Ok(())
+    }
+
+    async fn synthesize(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer> {
+        let mut state = self.state();
+        if state.is_closed {
+            return Err(TTSError::NotInitialized);
+        }
+        let voice = state.voice.clone();
+        state.synthesized.push(RecordedUtterance {
+            text: text.to_string(),
+            voice,
+            prosody: *prosody,
+        });
+
+        let frames =
+            text.chars().count() * (Self::SAMPLE_RATE * Self::SILENCE_PER_CHAR / 1000) as usize;
+        Ok(AudioBuffer {
+            sample_rate: Self::SAMPLE_RATE,
+            channels: 1,
+            bits_per_sample: 16,
+            data: vec![0; frames * 2],
+        })
+    }
+
+    async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        let mut state = self.state();
+        if state.is_closed {
+            return Err(TTSError::NotInitialized);
+        }
+        state.played.push(audio.clone());
+        let holds_playback = state.holds_playback;
+        drop(state);
+
+        self.finish_playback();
+        self.start_playback(holds_playback, Vec::new());
+        Ok(())
+    }
+
+    async fn wait_for_completion(&self) -> Result<()> {
+        self.events.wait_until_idle().await;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        self.events.subscribe()
+    }
+
+    async fn stop(&self) -> Result<()> {
+        self.state().stop_count += 1;
+        self.finish_playback();
+        Ok(())
+    }
+
+    async fn voices(&self) -> Result<Vec<VoiceInfo>> {
+        Ok(self.state().voices.clone())
+    }
+
+    async fn default_voice(&self) -> Result<VoiceInfo> {
+        Ok(self.state().voices[0].clone())
+    }
+
+    async fn set_voice(&self, voice: &VoiceInfo) -> Result<()> {
+        let mut state = self.state();
+        match state.voices.iter().find(|v| v.id == voice.id).cloned() {
+            Some(voice) => {
+                state.voice = voice;
+                Ok(())
+            }
+            None => Err(TTSError::VoiceNotFound {
+                selector: format!("id:{}", voice.id),
+                close_matches: Vec::new(),
+            }),
+        }
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.state().is_closed = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(name: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: name.to_lowercase(),
+            display_name: name.to_string(),
+            language: "en-US".to_string(),
+            gender: "Female".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_text_with_selected_voice() {
+        let engine = RecordingEngine::with_voices(vec![voice("Zira"), voice("Hedda")]);
+        let raised = Prosody {
+            pitch: Some(1.5),
+            ..Prosody::default()
+        };
+        engine.speak("Hello", &Prosody::default()).await.unwrap();
+        engine.set_voice(&voice("Hedda")).await.unwrap();
+        engine.speak("Hallo", &raised).await.unwrap();
+
+        assert_eq!(
+            engine.utterances(),
+            vec![
+                RecordedUtterance {
+                    text: "Hello".to_string(),
+                    voice: voice("Zira"),
+                    prosody: Prosody::default(),
+                },
+                RecordedUtterance {
+                    text: "Hallo".to_string(),
+                    voice: voice("Hedda"),
+                    prosody: raised,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_word_boundaries_in_utf16() {
+        let engine = RecordingEngine::new();
+        let mut events = engine.subscribe();
+        engine
+            .speak("\u{1d565}x  ok", &Prosody::default())
+            .await
+            .unwrap();
+
+        let word = |range| {
+            SpeechEvent::Boundary(Boundary {
+                kind: BoundaryKind::Word,
+                range,
+            })
+        };
+        assert_eq!(events.recv().await.unwrap(), SpeechEvent::Started);
+        assert_eq!(events.recv().await.unwrap(), word(0..3));
+        assert_eq!(events.recv().await.unwrap(), word(5..7));
+        assert_eq!(events.recv().await.unwrap(), SpeechEvent::Ended);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn unknown_voice_is_rejected() {
+        let engine = RecordingEngine::new();
+        assert!(engine.set_voice(&voice("Nobody")).await.is_err());
+        assert_eq!(
+            engine.default_voice().await.unwrap(),
+            engine.voices().await.unwrap()[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn synthesizes_silence_without_speaking() {
+        let engine = RecordingEngine::new();
+        let audio = engine
+            .synthesize("Hello", &Prosody::default())
+            .await
+            .unwrap();
+
+        assert_eq!(audio.sample_rate, RecordingEngine::SAMPLE_RATE);
+        assert_eq!(audio.duration(), std::time::Duration::from_millis(50));
+        assert!(audio.data.iter().all(|&b| b == 0));
+        assert_eq!(engine.synthesized()[0].text, "Hello");
+        assert!(engine.utterances().is_empty());
+    }
+
+    #[tokio::test]
+    async fn closed_engine_refuses_to_speak() {
+        let engine = RecordingEngine::new();
+        engine.close().await.unwrap();
+        assert!(engine.speak("Hello", &Prosody::default()).await.is_err());
+        assert!(engine.utterances().is_empty());
+    }
+}
@@ -0,0 +1,488 @@
+use std::sync::{Arc, Mutex};
+
+use aria_utils::config::get_config;
+use async_trait::async_trait;
+use tokio::sync::{broadcast, Mutex as TokioMutex, OnceCell as TokioOnceCell};
+use windows::{
+    core::{IInspectable, Interface, HSTRING},
+    Foundation::{
+        Collections::{CollectionChange, IVectorChangedEventArgs},
+        TypedEventHandler,
+    },
+    Media::{
+        Core::{MediaCueEventArgs, MediaSource, SpeechCue, TimedMetadataTrack},
+        Playback::{
+            MediaPlaybackItem, MediaPlaybackTimedMetadataTrackList, MediaPlayer,
+            MediaPlayerFailedEventArgs, TimedMetadataTrackPresentationMode,
+        },
+        SpeechSynthesis::{
+            SpeechAppendedSilence, SpeechPunctuationSilence, SpeechSynthesisStream,
+            SpeechSynthesizer, VoiceInformation,
+        },
+    },
+    Storage::Streams::{DataReader, DataWriter, InMemoryRandomAccessStream},
+};
+
+use super::{Prepared, Prosody, SpeechEngine, VoiceInfo};
+use crate::audio::AudioBuffer;
+use crate::error::{Result, TTSError};
+use crate::event::{Boundary, BoundaryKind, SpeechEvent, SpeechEvents};
+use crate::voice::VoiceSelector;
+
+/// Apply every value set in `prosody`, clamped to the range WinRT accepts.
+fn apply_prosody(synthesizer: &SpeechSynthesizer, prosody: &Prosody) -> Result<()> {
+    let synthesizer_options = synthesizer.Options().map_err(TTSError::Windows)?;
+    if let Some(rate) = prosody.rate {
+        synthesizer_options
+            .SetSpeakingRate(rate.clamp(0.5, 6.0))
+            .map_err(TTSError::Windows)?;
+    }
+    if let Some(pitch) = prosody.pitch {
+        synthesizer_options
+            .SetAudioPitch(pitch.clamp(0.0, 2.0))
+            .map_err(TTSError::Windows)?;
+    }
+    if let Some(volume) = prosody.volume {
+        synthesizer_options
+            .SetAudioVolume(volume.clamp(0.0, 1.0))
+            .map_err(TTSError::Windows)?;
+    }
+    Ok(())
+}
+
+async fn create_and_configure_synthesizer(
+    default_prosody: &Mutex<Prosody>,
+) -> Result<SpeechSynthesizer> {
+    let synthesizer = SpeechSynthesizer::new().map_err(TTSError::Windows)?;
+    let synthesizer_options = synthesizer.Options().map_err(TTSError::Windows)?;
+    let config = get_config()?;
+
+    let configured_prosody = Prosody {
+        rate: Some(config.speech_rate),
+        pitch: Some(config.pitch),
+        volume: Some(config.volume),
+    };
+    apply_prosody(&synthesizer, &configured_prosody)?;
+    *default_prosody.lock().unwrap_or_else(|e| e.into_inner()) = configured_prosody;
+
+    synthesizer_options
+        .SetAppendedSilence(if config.append_silence {
+            SpeechAppendedSilence::Default
+        } else {
+            SpeechAppendedSilence::Min
+        })
+        .map_err(TTSError::Windows)?;
+    synthesizer_options
+        .SetPunctuationSilence(if config.punctuation_silence {
+            SpeechPunctuationSilence::Default
+        } else {
+            SpeechPunctuationSilence::Min
+        })
+        .map_err(TTSError::Windows)?;
+
+    // Report word and sentence positions as timed metadata tracks during playback.
+    synthesizer_options
+        .SetIncludeWordBoundaryMetadata(true)
+        .map_err(TTSError::Windows)?;
+    synthesizer_options
+        .SetIncludeSentenceBoundaryMetadata(true)
+        .map_err(TTSError::Windows)?;
+
+    // Set voice if specified in config, keeping the system default if nothing matches
+    if let Some(selector) = &config.voice {
+        match select_configured_voice(selector).await {
+            Ok(voice) => synthesizer.SetVoice(&voice).map_err(TTSError::Windows)?,
+            Err(e) => log::warn!("Ignoring voice from config: {}", e),
+        }
+    }
+
+    Ok(synthesizer)
+}
+
+/// Create the media player and report the end of everything it plays to `events`.
+async fn create_media_player(events: Arc<SpeechEvents>) -> Result<MediaPlayer> {
+    let player = MediaPlayer::new().map_err(TTSError::Windows)?;
+
+    let ended = events.clone();
+    player
+        .MediaEnded(&TypedEventHandler::<MediaPlayer, IInspectable>::new(
+            move |_, _| {
+                ended.send(SpeechEvent::Ended);
+                Ok(())
+            },
+        ))
+        .map_err(TTSError::Windows)?;
+    player
+        .MediaFailed(
+            &TypedEventHandler::<MediaPlayer, MediaPlayerFailedEventArgs>::new(move |_, args| {
+                let reason = match args {
+                    Some(args) => args.ErrorMessage()?.to_string(),
+                    None => "unknown media error".to_string(),
+                };
+                events.send(SpeechEvent::Failed(reason));
+                Ok(())
+            }),
+        )
+        .map_err(TTSError::Windows)?;
+
+    Ok(player)
+}
+
+/// Forward the cues of the boundary track at `index` as [`SpeechEvent`]s.
+fn watch_boundary_track(
+    tracks: &MediaPlaybackTimedMetadataTrackList,
+    index: u32,
+    events: &Arc<SpeechEvents>,
+) -> windows::core::Result<()> {
+    let track = tracks.GetAt(index)?;
+    let kind = match track.Id()?.to_string().as_str() {
+        "SpeechWord" => BoundaryKind::Word,
+        "SpeechSentence" => BoundaryKind::Sentence,
+        _ => return Ok(()),
+    };
+    // Cues of application-presented tracks are raised as events instead of rendered.
+    tracks.SetPresentationMode(
+        index,
+        TimedMetadataTrackPresentationMode::ApplicationPresented,
+    )?;
+
+    let events = events.clone();
+    track.CueEntered(
+        &TypedEventHandler::<TimedMetadataTrack, MediaCueEventArgs>::new(move |_, args| {
+            if let Some(args) = args {
+                let cue: SpeechCue = args.Cue()?.cast()?;
+                let start = cue.StartPositionInInput()?.Value()?;
+                // The end position is inclusive.
+                let end = cue.EndPositionInInput()?.Value()? + 1;
+                events.send(SpeechEvent::Boundary(Boundary {
+                    kind,
+                    range: start.max(0) as usize..end.max(start) as usize,
+                }));
+            }
+            Ok(())
+        }),
+    )?;
+    Ok(())
+}
+
+/// Report the word and sentence boundaries of `item` while it plays. The tracks may
+/// be added after the item was created, so new tracks are watched as they appear.
+fn watch_boundaries(
+    item: &MediaPlaybackItem,
+    events: &Arc<SpeechEvents>,
+) -> windows::core::Result<()> {
+    let tracks = item.TimedMetadataTracks()?;
+    for index in 0..tracks.Size()? {
+        watch_boundary_track(&tracks, index, events)?;
+    }
+
+    let events = events.clone();
+    item.TimedMetadataTracksChanged(&TypedEventHandler::<
+        MediaPlaybackItem,
+        IVectorChangedEventArgs,
+    >::new(move |item, args| {
+        if let (Some(item), Some(args)) = (item, args) {
+            if args.CollectionChange()? == CollectionChange::ItemInserted {
+                watch_boundary_track(&item.TimedMetadataTracks()?, args.Index()?, &events)?;
+            }
+        }
+        Ok(())
+    }))?;
+    Ok(())
+}
+
+fn voice_info(voice: &VoiceInformation) -> Result<VoiceInfo> {
+    let display_name = voice.DisplayName().map_err(TTSError::Windows)?.to_string();
+    let id = voice.Id().map_err(TTSError::Windows)?.to_string();
+    let language = voice.Language().map_err(TTSError::Windows)?.to_string();
+    let gender = match voice.Gender().map_err(TTSError::Windows)? {
+        windows::Media::SpeechSynthesis::VoiceGender::Male => "Male".to_string(),
+        windows::Media::SpeechSynthesis::VoiceGender::Female => "Female".to_string(),
+        _ => "Unknown".to_string(),
+    };
+
+    Ok(VoiceInfo {
+        id,
+        display_name,
+        language,
+        gender,
+    })
+}
+
+async fn get_installed_voices() -> Result<Vec<VoiceInfo>> {
+    tokio::task::spawn_blocking(|| {
+        let voices = SpeechSynthesizer::AllVoices().map_err(TTSError::Windows)?;
+
+        let mut voice_list = Vec::new();
+        let voice_count = voices.Size().map_err(TTSError::Windows)?;
+
+        for i in 0..voice_count {
+            if let Ok(voice) = voices.GetAt(i) {
+                voice_list.push(voice_info(&voice)?);
+            }
+        }
+
+        Ok(voice_list)
+    })
+    .await
+    .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+}
+
+async fn find_voice_by_id(voice_id: &str) -> Result<Option<VoiceInformation>> {
+    let voice_id = voice_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let voices = SpeechSynthesizer::AllVoices().map_err(TTSError::Windows)?;
+        let voice_count = voices.Size().map_err(TTSError::Windows)?;
+
+        for i in 0..voice_count {
+            if let Ok(voice) = voices.GetAt(i) {
+                let id = voice.Id().map_err(TTSError::Windows)?.to_string();
+                if id == voice_id {
+                    return Ok(Some(voice));
+                }
+            }
+        }
+
+        Ok(None)
+    })
+    .await
+    .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+}
+
+async fn select_configured_voice(selector: &str) -> Result<VoiceInformation> {
+    let selector: VoiceSelector = selector.parse()?;
+    let voices = get_installed_voices().await?;
+    let voice = selector.select(&voices)?;
+    find_voice_by_id(&voice.id)
+        .await?
+        .ok_or_else(|| TTSError::VoiceNotFound {
+            selector: format!("id:{}", voice.id),
+            close_matches: Vec::new(),
+        })
+}
+
+/// Speech engine backed by the WinRT `SpeechSynthesizer` and a `MediaPlayer`
+/// playing to the default audio device.
+///
+/// Both objects are created lazily on first use, with the synthesizer configured
+/// from `aria.toml`.
+pub struct WinRtEngine {
+    synthesizer: TokioOnceCell<SpeechSynthesizer>,
+    player: TokioOnceCell<MediaPlayer>,
+    /// Rate, pitch and volume restored after an utterance that overrides them.
+    default_prosody: Mutex<Prosody>,
+    /// Kept as a stream rather than audio, so its boundaries are still reported.
+    prepared: Prepared<SpeechSynthesisStream>,
+    /// Held from setting the voice or prosody of the one synthesizer until the
+    /// text is synthesized with them, so a sentence being prepared cannot take on
+    /// the settings of the one being spoken. The blocking task owns it, so an
+    /// aborted preparation keeps it until its synthesis is done.
+    synthesis: Arc<TokioMutex<()>>,
+    events: Arc<SpeechEvents>,
+}
+
+impl Default for WinRtEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WinRtEngine {
+    pub fn new() -> Self {
+        WinRtEngine {
+            synthesizer: TokioOnceCell::new(),
+            player: TokioOnceCell::new(),
+            default_prosody: Mutex::new(Prosody::default()),
+            prepared: Prepared::default(),
+            synthesis: Arc::new(TokioMutex::new(())),
+            events: Arc::new(SpeechEvents::default()),
+        }
+    }
+
+    async fn get_synthesizer(&self) -> Result<&SpeechSynthesizer> {
+        self.synthesizer
+            .get_or_try_init(|| create_and_configure_synthesizer(&self.default_prosody))
+            .await
+    }
+
+    async fn get_player(&self) -> Result<&MediaPlayer> {
+        self.player
+            .get_or_try_init(|| create_media_player(self.events.clone()))
+            .await
+    }
+
+    /// Play the player's source. Started is reported first, since a short
+    /// utterance may end before `Play` returns.
+    fn start_playing(&self, player: &MediaPlayer) -> Result<()> {
+        self.events.send(SpeechEvent::Started);
+        player.Play().map_err(|e| {
+            self.events.send(SpeechEvent::Failed(e.to_string()));
+            TTSError::Windows(e)
+        })
+    }
+
+    /// Synthesize `text` into a WAV stream with `prosody` applied on top of the defaults.
+    async fn synthesize_stream(
+        &self,
+        text: &str,
+        prosody: &Prosody,
+    ) -> Result<SpeechSynthesisStream> {
+        let synthesizer = self.get_synthesizer().await?.clone();
+
+        let default_prosody = *self
+            .default_prosody
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let prosody = prosody.or(default_prosody);
+        let text_hstring = HSTRING::from(text);
+
+        let synthesis = self.synthesis.clone().lock_owned().await;
+        tokio::task::spawn_blocking(move || {
+            let _synthesis = synthesis;
+            apply_prosody(&synthesizer, &prosody)?;
+            synthesizer
+                .SynthesizeTextToStreamAsync(&text_hstring)
+                .and_then(|operation| operation.get())
+                .map_err(TTSError::Windows)
+        })
+        .await
+        .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+    }
+}
+
+/// Read a whole synthesis stream into memory.
+async fn read_stream(stream: SpeechSynthesisStream) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || {
+        let size = u32::try_from(stream.Size().map_err(TTSError::Windows)?)
+            .map_err(|_| TTSError::Audio("synthesized audio exceeds 4 GiB".to_string()))?;
+        let input = stream.GetInputStreamAt(0).map_err(TTSError::Windows)?;
+        let reader = DataReader::CreateDataReader(&input).map_err(TTSError::Windows)?;
+        reader
+            .LoadAsync(size)
+            .and_then(|operation| operation.get())
+            .map_err(TTSError::Windows)?;
+
+        let mut bytes = vec![0; size as usize];
+        reader.ReadBytes(&mut bytes).map_err(TTSError::Windows)?;
+        Ok(bytes)
+    })
+    .await
+    .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+}
+
+/// Copy a WAV file into a stream the media player can read.
+async fn wav_stream(wav: Vec<u8>) -> Result<InMemoryRandomAccessStream> {
+    tokio::task::spawn_blocking(move || {
+        let stream = InMemoryRandomAccessStream::new()?;
+        let writer = DataWriter::CreateDataWriter(&stream)?;
+        writer.WriteBytes(&wav)?;
+        writer.StoreAsync()?.get()?;
+        // Keep the stream open when the writer is dropped.
+        writer.DetachStream()?;
+        stream.Seek(0)?;
+        Ok(stream)
+    })
+    .await
+    .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+    .map_err(TTSError::Windows)
+}
+
+#[async_trait]
+impl SpeechEngine for WinRtEngine {
+    async fn speak(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let player = self.get_player().await?;
+        let stream = match self.prepared.take(text, prosody) {
+            Some(stream) => stream,
+            None => self.synthesize_stream(text, prosody).await?,
+        };
+
+        let content_type = stream.ContentType().map_err(TTSError::Windows)?;
+        let media_source =
+            MediaSource::CreateFromStream(&stream, &content_type).map_err(TTSError::Windows)?;
+        let item = MediaPlaybackItem::Create(&media_source).map_err(TTSError::Windows)?;
+        watch_boundaries(&item, &self.events).map_err(TTSError::Windows)?;
+
+        // The item that was playing is replaced without an end event of its own.
+        self.events.send(SpeechEvent::Ended);
+        player.SetSource(&item).map_err(TTSError::Windows)?;
+        self.start_playing(player)
+    }
+
+    async fn prepare(&self, text: &str, prosody: &Prosody) -> Result<()> {
+        let stream = self.synthesize_stream(text, prosody).await?;
+        self.prepared.put(text, prosody, stream);
+        Ok(())
+    }
+
+    async fn synthesize(&self, text: &str, prosody: &Prosody) -> Result<AudioBuffer> {
+        let stream = self.synthesize_stream(text, prosody).await?;
+        AudioBuffer::from_wav(&read_stream(stream).await?)
+    }
+
+    async fn play(&self, audio: &AudioBuffer) -> Result<()> {
+        let player = self.get_player().await?;
+        let stream = wav_stream(audio.to_wav()).await?;
+
+        let media_source = MediaSource::CreateFromStream(&stream, &HSTRING::from("audio/wav"))
+            .map_err(TTSError::Windows)?;
+        self.events.send(SpeechEvent::Ended);
+        player.SetSource(&media_source).map_err(TTSError::Windows)?;
+        self.start_playing(player)
+    }
+
+    async fn wait_for_completion(&self) -> Result<()> {
+        self.events.wait_until_idle().await;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        self.events.subscribe()
+    }
+
+    async fn stop(&self) -> Result<()> {
+        if let Some(player) = self.player.get() {
+            player.Pause().map_err(TTSError::Windows)?;
+            self.events.send(SpeechEvent::Ended);
+        } else {
+            log::info!("Player not initialized, nothing to stop.");
+        }
+        Ok(())
+    }
+
+    async fn voices(&self) -> Result<Vec<VoiceInfo>> {
+        get_installed_voices().await
+    }
+
+    async fn default_voice(&self) -> Result<VoiceInfo> {
+        tokio::task::spawn_blocking(|| {
+            let default_voice = SpeechSynthesizer::DefaultVoice().map_err(TTSError::Windows)?;
+            voice_info(&default_voice)
+        })
+        .await
+        .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+    }
+
+    async fn set_voice(&self, voice: &VoiceInfo) -> Result<()> {
+        let synthesizer = self.get_synthesizer().await?;
+        let found = find_voice_by_id(&voice.id).await?;
+
+        let _synthesis = self.synthesis.lock().await;
+        self.prepared.clear();
+        match found {
+            Some(voice) => synthesizer.SetVoice(&voice).map_err(TTSError::Windows),
+            None => Err(TTSError::VoiceNotFound {
+                selector: format!("id:{}", voice.id),
+                close_matches: Vec::new(),
+            }),
+        }
+    }
+
+    async fn close(&self) -> Result<()> {
+        if let Some(player) = self.player.get() {
+            player.Close().map_err(TTSError::Windows)?;
+        }
+
+        log::info!("TTS resources associated with the WinRT player are closed (if initialized).");
+        Ok(())
+    }
+}
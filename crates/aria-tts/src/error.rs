@@ -1,13 +1,25 @@
 use thiserror::Error;
 
+use crate::state::SpeechState;
+
 #[derive(Error, Debug)]
 pub enum TTSError {
+    #[cfg(all(windows, feature = "windows"))]
     #[error("Windows API error: {0}")]
     Windows(#[from] windows::core::Error),
 
+    #[error("Config error: {0}")]
+    Config(#[from] aria_utils::error::ConfigError),
+
+    #[error("TTS server error: {0}")]
+    Http(#[from] reqwest::Error),
+
     #[error("Speech synthesis failed: {0}")]
     Synthesis(String),
 
+    #[error("Audio error: {0}")]
+    Audio(String),
+
     #[error("Media player error: {0}")]
     MediaPlayer(String),
 
@@ -20,6 +32,31 @@ pub enum TTSError {
     #[error("Synchronization error: {0}")]
     Sync(String),
 
+    #[error("No voice matches `{selector}`{}", did_you_mean(close_matches))]
+    VoiceNotFound {
+        selector: String,
+        close_matches: Vec<String>,
+    },
+
+    #[error("Invalid voice selector: {0}")]
+    InvalidVoiceSelector(String),
+
     #[error("TTS not initialized or already destroyed")]
     NotInitialized,
+
+    #[error("Speech cannot go from {from:?} to {to:?}")]
+    InvalidTransition { from: SpeechState, to: SpeechState },
+
+    #[error("Not supported on this platform: {0}")]
+    Unsupported(&'static str),
 }
+
+fn did_you_mean(close_matches: &[String]) -> String {
+    if close_matches.is_empty() {
+        String::new()
+    } else {
+        format!("; closest voices: {}", close_matches.join("; "))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, TTSError>;
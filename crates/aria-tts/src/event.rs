@@ -0,0 +1,108 @@
+use std::ops::Range;
+
+use tokio::sync::{broadcast, watch};
+
+/// What a [`Boundary`] marks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoundaryKind {
+    Word,
+    Sentence,
+}
+
+/// The synthesizer has reached a word or sentence of the utterance being played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Boundary {
+    pub kind: BoundaryKind,
+    /// Position in the spoken text, in UTF-16 code units as reported by the synthesizer.
+    pub range: Range<usize>,
+}
+
+/// Progress reported by a [`SpeechEngine`](crate::engine::SpeechEngine) while it plays.
+///
+/// Every utterance or buffer that starts playing reports [`SpeechEvent::Started`]
+/// and then exactly one of [`SpeechEvent::Ended`], when it finished or was stopped,
+/// or [`SpeechEvent::Failed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpeechEvent {
+    Started,
+    Boundary(Boundary),
+    Ended,
+    /// Playback could not start or broke off, with the reason.
+    Failed(String),
+}
+
+/// Broadcasts an engine's [`SpeechEvent`]s and follows them to know whether it
+/// is playing, so that waiting for the end of speech needs no polling.
+#[derive(Debug)]
+pub struct SpeechEvents {
+    events: broadcast::Sender<SpeechEvent>,
+    playing: watch::Sender<bool>,
+}
+
+impl Default for SpeechEvents {
+    fn default() -> Self {
+        SpeechEvents {
+            events: broadcast::channel(64).0,
+            playing: watch::channel(false).0,
+        }
+    }
+}
+
+impl SpeechEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        self.events.subscribe()
+    }
+
+    /// Report `event` to subscribers. [`SpeechEvent::Ended`] is only reported while
+    /// something is playing, so an engine may send it whenever it stops.
+    pub fn send(&self, event: SpeechEvent) {
+        let report = match event {
+            SpeechEvent::Started => {
+                self.playing.send_replace(true);
+                true
+            }
+            SpeechEvent::Ended => self.playing.send_replace(false),
+            SpeechEvent::Failed(_) => {
+                self.playing.send_replace(false);
+                true
+            }
+            SpeechEvent::Boundary(_) => true,
+        };
+        if report {
+            let _ = self.events.send(event);
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        *self.playing.borrow()
+    }
+
+    /// Resolves once playback has ended or failed, right away if nothing is playing.
+    pub async fn wait_until_idle(&self) {
+        let mut playing = self.playing.subscribe();
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = playing.wait_for(|playing| !playing).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ended_is_reported_once_per_start() {
+        let events = SpeechEvents::default();
+        let mut received = events.subscribe();
+
+        events.send(SpeechEvent::Ended);
+        events.send(SpeechEvent::Started);
+        assert!(events.is_playing());
+        events.send(SpeechEvent::Ended);
+        events.send(SpeechEvent::Ended);
+        events.wait_until_idle().await;
+
+        assert_eq!(received.try_recv(), Ok(SpeechEvent::Started));
+        assert_eq!(received.try_recv(), Ok(SpeechEvent::Ended));
+        assert!(received.try_recv().is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Choosing a voice for the language of each sentence.
+//!
+//! Languages are told apart offline from trigram statistics, only among the
+//! languages that have a voice, so a short German sentence is not taken for Dutch
+//! when only English and German are configured. A language tag that comes with
+//! the text, such as the culture of a UI element, is trusted over detection. Text
+//! in a language without a voice, or too short to tell, is left to the default voice.
+
+use whatlang::{Detector, Lang};
+
+use crate::engine::VoiceInfo;
+
+/// Fewer letters than this are not enough to tell languages apart.
+const MIN_LETTERS: usize = 12;
+
+/// The language of a BCP 47 tag such as `de-AT`, or an ISO 639 code such as `de`
+/// or `deu`.
+pub(crate) fn parse_language(tag: &str) -> Option<Lang> {
+    let primary = tag
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let code = match primary.as_str() {
+        "af" => "afr",
+        "ak" => "aka",
+        "am" => "amh",
+        "ar" => "ara",
+        "az" => "aze",
+        "be" => "bel",
+        "bg" => "bul",
+        "bn" => "ben",
+        "ca" => "cat",
+        "cs" => "ces",
+        "da" => "dan",
+        "de" => "deu",
+        "el" => "ell",
+        "en" => "eng",
+        "eo" => "epo",
+        "es" => "spa",
+        "et" => "est",
+        "fa" => "pes",
+        "fi" => "fin",
+        "fr" => "fra",
+        "gu" => "guj",
+        "he" => "heb",
+        "hi" => "hin",
+        "hr" => "hrv",
+        "hu" => "hun",
+        "hy" => "hye",
+        "id" => "ind",
+        "it" => "ita",
+        "ja" => "jpn",
+        "jv" => "jav",
+        "ka" => "kat",
+        "km" => "khm",
+        "kn" => "kan",
+        "ko" => "kor",
+        "la" => "lat",
+        "lt" => "lit",
+        "lv" => "lav",
+        "mk" => "mkd",
+        "ml" => "mal",
+        "mr" => "mar",
+        "my" => "mya",
+        "nb" | "no" => "nob",
+        "ne" => "nep",
+        "nl" => "nld",
+        "or" => "ori",
+        "pa" => "pan",
+        "pl" => "pol",
+        "pt" => "por",
+        "ro" => "ron",
+        "ru" => "rus",
+        "si" => "sin",
+        "sk" => "slk",
+        "sl" => "slv",
+        "sn" => "sna",
+        "sr" => "srp",
+        "sv" => "swe",
+        "ta" => "tam",
+        "te" => "tel",
+        "th" => "tha",
+        "tk" => "tuk",
+        "tl" | "fil" => "tgl",
+        "tr" => "tur",
+        "uk" => "ukr",
+        "ur" => "urd",
+        "uz" => "uzb",
+        "vi" => "vie",
+        "yi" => "yid",
+        "zh" => "cmn",
+        "zu" => "zul",
+        other => other,
+    };
+    Lang::from_code(code)
+}
+
+/// The voice to speak each language with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LanguageVoices {
+    voices: Vec<(Lang, VoiceInfo)>,
+}
+
+impl LanguageVoices {
+    /// Speak `language`, a tag as read by [`parse_language`], with `voice`.
+    /// Returns `false` if the language is unknown.
+    pub fn add(&mut self, language: &str, voice: VoiceInfo) -> bool {
+        let Some(lang) = parse_language(language) else {
+            return false;
+        };
+        self.voices.retain(|(other, _)| *other != lang);
+        self.voices.push((lang, voice));
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+
+    /// The language of `text` among those with a voice, if it is long enough to tell.
+    pub(crate) fn detect(&self, text: &str) -> Option<Lang> {
+        if self.voices.is_empty()
+            || text.chars().filter(|c| c.is_alphabetic()).count() < MIN_LETTERS
+        {
+            return None;
+        }
+        if let [(only, _)] = self.voices[..] {
+            return Some(only).filter(|_| whatlang::detect_lang(text) == Some(only));
+        }
+        let languages = self.voices.iter().map(|(lang, _)| *lang).collect();
+        Detector::with_allowlist(languages).detect_lang(text)
+    }
+
+    /// The voice for `text`, whose language is `tag` if the accessibility layer
+    /// knows it. `None` stands for the default voice.
+    pub fn voice_for(&self, text: &str, tag: Option<&str>) -> Option<&VoiceInfo> {
+        let lang = match tag {
+            Some(tag) => parse_language(tag),
+            None => self.detect(text),
+        }?;
+        self.voices
+            .iter()
+            .find(|(other, _)| *other == lang)
+            .map(|(_, voice)| voice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(name: &str, language: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: name.to_lowercase(),
+            display_name: name.to_string(),
+            language: language.to_string(),
+            gender: "Female".to_string(),
+        }
+    }
+
+    fn english_and_german() -> LanguageVoices {
+        let mut voices = LanguageVoices::default();
+        assert!(voices.add("en", voice("Zira", "en-US")));
+        assert!(voices.add("de-DE", voice("Hedda", "de-DE")));
+        assert!(!voices.add("klingon", voice("Worf", "tlh")));
+        voices
+    }
+
+    fn voice_name<'a>(
+        voices: &'a LanguageVoices,
+        text: &str,
+        tag: Option<&str>,
+    ) -> Option<&'a str> {
+        voices
+            .voice_for(text, tag)
+            .map(|voice| voice.display_name.as_str())
+    }
+
+    #[test]
+    fn tags_and_codes_are_understood() {
+        assert_eq!(parse_language("de-AT"), Some(Lang::Deu));
+        assert_eq!(parse_language("EN_gb"), Some(Lang::Eng));
+        assert_eq!(parse_language("fra"), Some(Lang::Fra));
+        assert_eq!(parse_language("zh-Hans-CN"), Some(Lang::Cmn));
+        assert_eq!(parse_language("x-klingon"), None);
+    }
+
+    #[test]
+    fn sentences_get_the_voice_of_their_language() {
+        let voices = english_and_german();
+        assert_eq!(
+            voice_name(&voices, "The file could not be saved.", None),
+            Some("Zira")
+        );
+        assert_eq!(
+            voice_name(&voices, "Die Datei konnte nicht gespeichert werden.", None),
+            Some("Hedda")
+        );
+        // Too short to tell.
+        assert_eq!(voice_name(&voices, "OK, button", None), None);
+    }
+
+    #[test]
+    fn tags_are_trusted_over_detection() {
+        let voices = english_and_german();
+        assert_eq!(
+            voice_name(&voices, "The file could not be saved.", Some("de-CH")),
+            Some("Hedda")
+        );
+        assert_eq!(voice_name(&voices, "OK", Some("en-US")), Some("Zira"));
+        // A language without a voice is left to the default voice.
+        assert_eq!(
+            voice_name(
+                &voices,
+                "Die Datei konnte nicht gespeichert werden.",
+                Some("fr")
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn a_single_voice_needs_a_clear_match() {
+        let mut voices = LanguageVoices::default();
+        voices.add("de", voice("Hedda", "de-DE"));
+        assert_eq!(
+            voice_name(&voices, "Die Datei konnte nicht gespeichert werden.", None),
+            Some("Hedda")
+        );
+        assert_eq!(
+            voice_name(&voices, "The file could not be saved.", None),
+            None
+        );
+    }
+}
@@ -1,2 +1,10 @@
+pub mod audio;
+pub mod engine;
 pub mod error;
+pub mod event;
+pub mod language;
+mod pronunciation;
+pub mod queue;
+pub mod state;
 pub mod tts;
+pub mod voice;
@@ -0,0 +1,92 @@
+use std::sync::{Mutex, MutexGuard};
+
+use aria_utils::clean_text::{run_stages, CleanerStage, Pair, Rewritten};
+use aria_utils::config::PunctuationLevel;
+use aria_utils::pronunciation::Pronunciation;
+use aria_utils::symbols::SymbolNames;
+
+use crate::engine::VoiceInfo;
+
+#[derive(Debug)]
+struct PronouncerState {
+    dictionaries: Pronunciation,
+    voice: Option<VoiceInfo>,
+    app: Option<String>,
+    punctuation: PunctuationLevel,
+}
+
+impl Default for PronouncerState {
+    /// No dictionaries and no symbol names, so text is spoken as it is.
+    fn default() -> Self {
+        PronouncerState {
+            dictionaries: Pronunciation::default(),
+            voice: None,
+            app: None,
+            punctuation: PunctuationLevel::None,
+        }
+    }
+}
+
+/// Applies the user's pronunciation dictionaries for the current voice and
+/// application, then names the symbols of the current punctuation level, just
+/// before text is synthesized.
+///
+/// Word boundaries reported while speaking refer to the rewritten text, and are
+/// mapped back to the text that was queued.
+#[derive(Debug, Default)]
+pub(crate) struct Pronouncer {
+    state: Mutex<PronouncerState>,
+}
+
+impl Pronouncer {
+    fn state(&self) -> MutexGuard<'_, PronouncerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn set_dictionaries(&self, dictionaries: Pronunciation) {
+        self.state().dictionaries = dictionaries;
+    }
+
+    pub(crate) fn set_voice(&self, voice: VoiceInfo) {
+        self.state().voice = Some(voice);
+    }
+
+    pub(crate) fn set_app(&self, app: Option<String>) {
+        self.state().app = app;
+    }
+
+    pub(crate) fn set_punctuation(&self, level: PunctuationLevel) {
+        self.state().punctuation = level;
+    }
+
+    pub(crate) fn apply(&self, text: &str) -> String {
+        self.apply_with(text, None).text
+    }
+
+    /// Like [`Pronouncer::apply`], but with the dictionary of `voice` rather than
+    /// that of the current voice, if one is given, and with the index maps to
+    /// report boundaries in `text` rather than in what is spoken.
+    pub(crate) fn apply_with(&self, text: &str, voice: Option<&VoiceInfo>) -> Rewritten {
+        let state = self.state();
+        let voice = match voice.or(state.voice.as_ref()) {
+            Some(voice) => vec![voice.display_name.as_str(), voice.id.as_str()],
+            None => Vec::new(),
+        };
+        let symbols = SymbolNames(state.punctuation);
+        let stages = state
+            .dictionaries
+            .stages(&voice, state.app.as_deref())
+            .chain(Some(&symbols as &dyn CleanerStage));
+        // Symbols are only named outside of what the dictionaries replaced, so
+        // a phoneme string is passed on as it is.
+        let mut pairs: Vec<Pair> = run_stages(text, stages).collect();
+        // A name at either end is set off from text that is not there.
+        if let Some((_, Some(first))) = pairs.first_mut() {
+            *first = first.trim_start_matches(' ').to_string().into();
+        }
+        if let Some((_, Some(last))) = pairs.last_mut() {
+            *last = last.trim_end_matches(' ').to_string().into();
+        }
+        Rewritten::from_pairs(pairs)
+    }
+}
@@ -0,0 +1,843 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use aria_utils::clean_text::Rewritten;
+use aria_utils::config::Language;
+use aria_utils::sentences::split_sentences;
+use aria_utils::wide_string::LenUtf;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+use crate::engine::{Prosody, SpeechEngine, VoiceInfo};
+use crate::error::{Result, TTSError};
+use crate::event::{Boundary, SpeechEvent};
+use crate::language::LanguageVoices;
+use crate::pronunciation::Pronouncer;
+use crate::state::SpeechState;
+
+/// How urgently an utterance should be spoken.
+///
+/// Higher priorities are spoken first and cut off lower-priority speech that is
+/// already playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Background,
+    Normal,
+    Interrupt,
+}
+
+/// What happens to speech that is already queued or playing when an utterance arrives.
+///
+/// Replacing only ever affects speech of the same or a lower priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Queue behind everything else.
+    Append,
+    /// Drop all other speech.
+    ReplaceAll,
+    /// Drop other speech of the same [`Category`].
+    ReplaceSameCategory,
+}
+
+/// Where an utterance came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Focus,
+    KeyEcho,
+    System,
+    Notification,
+    /// Speech read back on request, such as the speech history.
+    Review,
+    Other,
+}
+
+/// Something to do as an utterance starts to play, such as a beep for a capital
+/// letter. Queued utterances may wait, so a sound that goes with one is only
+/// played once the queue gets to it.
+#[derive(Clone)]
+pub struct Cue(Arc<dyn Fn() + Send + Sync>);
+
+impl Cue {
+    pub fn new<F: Fn() + Send + Sync + 'static>(cue: F) -> Self {
+        Cue(Arc::new(cue))
+    }
+
+    fn play(&self) {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Cue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cue")
+    }
+}
+
+impl PartialEq for Cue {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A piece of text waiting to be spoken, together with its scheduling rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Utterance {
+    pub text: String,
+    pub priority: Priority,
+    pub policy: Policy,
+    pub category: Category,
+    /// Rate, pitch and volume overrides for this utterance only.
+    pub prosody: Prosody,
+    /// Multiplies the pitch in effect, e.g. 1.3 to say a capital letter higher
+    /// than the text around it whatever the pitch setting is.
+    pub pitch_factor: Option<f64>,
+    /// The language of the text as a BCP 47 tag, if its source knows it. Otherwise
+    /// the language of each sentence is detected.
+    pub language: Option<String>,
+    /// Played as the utterance starts, and again if it is cut off and resumed
+    /// from the start.
+    pub cue: Option<Cue>,
+}
+
+impl Utterance {
+    pub fn new<T: Into<String>>(
+        text: T,
+        priority: Priority,
+        policy: Policy,
+        category: Category,
+    ) -> Self {
+        Utterance {
+            text: text.into(),
+            priority,
+            policy,
+            category,
+            prosody: Prosody::default(),
+            pitch_factor: None,
+            language: None,
+            cue: None,
+        }
+    }
+
+    /// Speak this utterance with different rate, pitch or volume, for example a
+    /// raised pitch for capital letters.
+    pub fn with_prosody(mut self, prosody: Prosody) -> Self {
+        self.prosody = prosody;
+        self
+    }
+
+    pub fn with_pitch_factor(mut self, factor: f64) -> Self {
+        self.pitch_factor = Some(factor);
+        self
+    }
+
+    /// Speak this utterance with the voice for `tag`, e.g. `de-DE`, if there is one.
+    pub fn with_language<T: Into<String>>(mut self, tag: T) -> Self {
+        self.language = Some(tag.into());
+        self
+    }
+
+    pub fn with_cue(mut self, cue: Cue) -> Self {
+        self.cue = Some(cue);
+        self
+    }
+
+    /// The prosody to speak this utterance with while `live` is in effect.
+    pub fn prosody_over(&self, live: Prosody) -> Prosody {
+        let mut prosody = self.prosody.or(live);
+        if let Some(factor) = self.pitch_factor {
+            prosody.pitch = Some(prosody.pitch.unwrap_or(1.0) * factor);
+        }
+        prosody
+    }
+
+    /// The element that has focus, replacing everything said before.
+    pub fn focus<T: Into<String>>(text: T) -> Self {
+        Self::new(
+            text,
+            Priority::Interrupt,
+            Policy::ReplaceAll,
+            Category::Focus,
+        )
+    }
+
+    /// A typed key. Fast typing only keeps the latest key, and never cuts off a
+    /// focus announcement.
+    pub fn key_echo<T: Into<String>>(text: T) -> Self {
+        Self::new(
+            text,
+            Priority::Normal,
+            Policy::ReplaceSameCategory,
+            Category::KeyEcho,
+        )
+    }
+
+    /// Messages about Aria itself, such as startup and shutdown.
+    pub fn system<T: Into<String>>(text: T) -> Self {
+        Self::new(
+            text,
+            Priority::Interrupt,
+            Policy::ReplaceAll,
+            Category::System,
+        )
+    }
+
+    /// Something the user asked to hear again, replacing everything said before.
+    pub fn review<T: Into<String>>(text: T) -> Self {
+        Self::new(
+            text,
+            Priority::Interrupt,
+            Policy::ReplaceAll,
+            Category::Review,
+        )
+    }
+
+    /// Something worth mentioning once nothing more important is being said.
+    pub fn notification<T: Into<String>>(text: T) -> Self {
+        Self::new(
+            text,
+            Priority::Background,
+            Policy::Append,
+            Category::Notification,
+        )
+    }
+}
+
+impl From<&str> for Utterance {
+    fn from(text: &str) -> Self {
+        Self::new(text, Priority::Normal, Policy::Append, Category::Other)
+    }
+}
+
+impl From<String> for Utterance {
+    fn from(text: String) -> Self {
+        Self::new(text, Priority::Normal, Policy::Append, Category::Other)
+    }
+}
+
+/// A sentence as it is spoken.
+#[derive(Debug, Clone)]
+struct Sentence {
+    /// Where the sentence starts in the utterance's text, in UTF-16 code units.
+    offset: usize,
+    /// The sentence with pronunciation applied, and the way back to the sentence.
+    spoken: Rewritten,
+    /// The voice for the sentence's language; `None` is the default voice.
+    voice: Option<VoiceInfo>,
+}
+
+/// The sentences of an utterance, spoken with one engine call each.
+#[derive(Debug)]
+struct Sentences {
+    spoken: Vec<Sentence>,
+    /// The sentence playing or about to play.
+    current: usize,
+}
+
+impl Sentences {
+    fn new(utterance: &Utterance, settings: &Settings, pronouncer: &Pronouncer) -> Self {
+        let text = utterance.text.as_str();
+        let tag = utterance.language.as_deref();
+        let sentence = |offset, text: &str| {
+            let voice = settings.voices.voice_for(text, tag).cloned();
+            Sentence {
+                offset,
+                spoken: pronouncer.apply_with(text, voice.as_ref()),
+                voice,
+            }
+        };
+        let mut spoken: Vec<_> = split_sentences(text, settings.language)
+            .into_iter()
+            .map(|range| sentence(text[..range.start].len_utf16(), &text[range]))
+            .collect();
+        if spoken.is_empty() {
+            spoken.push(sentence(0, text));
+        }
+        Sentences { spoken, current: 0 }
+    }
+
+    fn current(&self) -> Option<&Sentence> {
+        self.spoken.get(self.current)
+    }
+
+    /// The sentence after the current one, to prepare while the current one plays.
+    fn following(&self) -> Option<&Sentence> {
+        self.spoken.get(self.current + 1)
+    }
+
+    /// Map a UTF-16 range of what the current sentence was spoken as to the
+    /// utterance's text.
+    fn original_range(&self, range: &Range<usize>) -> Range<usize> {
+        match self.current() {
+            Some(sentence) => {
+                let range = sentence.spoken.input_u16range(range);
+                range.start + sentence.offset..range.end + sentence.offset
+            }
+            None => range.clone(),
+        }
+    }
+}
+
+/// An utterance in the queue. Dropping `_done` tells whoever is waiting on it that
+/// the utterance has been spoken or discarded; `progress` receives its boundaries
+/// while it plays.
+#[derive(Debug)]
+struct Entry {
+    utterance: Utterance,
+    _done: Option<oneshot::Sender<()>>,
+    progress: Option<mpsc::UnboundedSender<Boundary>>,
+    /// Split once the utterance starts, and kept if it is preempted, so that it
+    /// resumes at the sentence it was cut off in.
+    sentences: Option<Sentences>,
+}
+
+/// The scheduling rules of the speech queue, without any I/O.
+#[derive(Debug, Default)]
+struct SpeechQueue {
+    pending: VecDeque<Entry>,
+    current: Option<Entry>,
+}
+
+impl SpeechQueue {
+    /// Add an utterance according to its policy and priority.
+    /// Returns `true` if the utterance that is currently playing must be stopped.
+    fn push(&mut self, entry: Entry) -> bool {
+        let incoming = &entry.utterance;
+        let replaces = |other: &Utterance| {
+            other.priority <= incoming.priority
+                && match incoming.policy {
+                    Policy::Append => false,
+                    Policy::ReplaceAll => true,
+                    Policy::ReplaceSameCategory => other.category == incoming.category,
+                }
+        };
+
+        self.pending.retain(|e| !replaces(&e.utterance));
+
+        let mut stop_current = false;
+        if let Some(current) = self.current.take() {
+            if replaces(&current.utterance) {
+                stop_current = true;
+            } else if incoming.priority > current.utterance.priority {
+                // Preempted rather than replaced: say it again once there is time.
+                self.pending.push_front(current);
+                stop_current = true;
+            } else {
+                self.current = Some(current);
+            }
+        }
+
+        // Keep the queue ordered by priority, first come first served within one.
+        let position = self
+            .pending
+            .iter()
+            .position(|e| e.utterance.priority < incoming.priority)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(position, entry);
+
+        stop_current
+    }
+
+    /// Make the next pending utterance current and return it.
+    fn start_next(&mut self) -> Option<&Utterance> {
+        self.current = self.pending.pop_front();
+        self.current.as_ref().map(|e| &e.utterance)
+    }
+
+    /// The current utterance's sentences, split with `split` when it first starts.
+    fn sentences(&mut self, split: impl FnOnce(&Utterance) -> Sentences) -> Option<&Sentences> {
+        let current = self.current.as_mut()?;
+        let utterance = &current.utterance;
+        Some(current.sentences.get_or_insert_with(|| split(utterance)))
+    }
+
+    /// Move on to the next sentence of the current utterance. Returns `false`,
+    /// finishing the utterance, if that was its last sentence.
+    fn next_sentence(&mut self) -> bool {
+        let sentences = self.current.as_mut().and_then(|e| e.sentences.as_mut());
+        match sentences {
+            Some(sentences) if sentences.following().is_some() => {
+                sentences.current += 1;
+                true
+            }
+            _ => {
+                self.finish_current();
+                false
+            }
+        }
+    }
+
+    /// Forward a boundary of the playing sentence to whoever tracks the progress
+    /// of its utterance, as a position in the whole utterance.
+    fn report(&self, mut boundary: Boundary) {
+        let Some(current) = &self.current else {
+            return;
+        };
+        if let Some(progress) = &current.progress {
+            if let Some(sentences) = &current.sentences {
+                boundary.range = sentences.original_range(&boundary.range);
+            }
+            let _ = progress.send(boundary);
+        }
+    }
+
+    fn finish_current(&mut self) {
+        self.current = None;
+    }
+
+    /// Drop everything. Returns `true` if something was playing.
+    fn clear(&mut self) -> bool {
+        self.pending.clear();
+        self.current.take().is_some()
+    }
+
+    /// Drop every utterance `keep` rejects. Returns `true` if that includes the
+    /// one that is playing.
+    fn retain(&mut self, keep: impl Fn(&Utterance) -> bool) -> bool {
+        self.pending.retain(|e| keep(&e.utterance));
+        match &self.current {
+            Some(current) if !keep(&current.utterance) => {
+                self.current = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum QueueCommand {
+    Enqueue(
+        Utterance,
+        Option<oneshot::Sender<()>>,
+        Option<mpsc::UnboundedSender<Boundary>>,
+    ),
+    Clear,
+    Skip,
+    SetState(SpeechState, oneshot::Sender<Result<()>>),
+    SetProsody(Prosody),
+    SetLanguage(Language),
+    SetLanguageVoices(LanguageVoices),
+    /// The engine now speaks with this voice, chosen by the user.
+    SetDefaultVoice(VoiceInfo),
+}
+
+/// Spawn the task that owns the queue and the [`SpeechState`] and feeds the queue
+/// to `engine` one sentence at a time, preparing each sentence while the one
+/// before it plays. The task starts in [`SpeechState::Starting`] and publishes
+/// every transition on the returned watch channel.
+pub(crate) fn spawn_queue(
+    engine: Arc<dyn SpeechEngine>,
+    pronouncer: Arc<Pronouncer>,
+) -> (mpsc::Sender<QueueCommand>, watch::Receiver<SpeechState>) {
+    let (tx, rx) = mpsc::channel(64);
+    let (state_tx, state_rx) = watch::channel(SpeechState::Starting);
+    tokio::spawn(run_queue(engine, pronouncer, rx, state_tx));
+    (tx, state_rx)
+}
+
+/// Move to `next`, dropping speech the new state does not allow. Returns `true`
+/// if the utterance that is playing must be stopped.
+async fn transition(
+    engine: &dyn SpeechEngine,
+    queue: &mut SpeechQueue,
+    state: &watch::Sender<SpeechState>,
+    next: SpeechState,
+) -> Result<bool> {
+    let current = *state.borrow();
+    if !current.can_become(next) {
+        return Err(TTSError::InvalidTransition {
+            from: current,
+            to: next,
+        });
+    }
+    log::info!("Speech state: {:?} -> {:?}", current, next);
+    state.send_replace(next);
+
+    if next == SpeechState::Stopped {
+        queue.clear();
+        engine.stop().await?;
+        engine.close().await?;
+        return Ok(false);
+    }
+    Ok(queue.retain(|u| next.allows(u)))
+}
+
+/// How the queue speaks, besides the settings of each utterance.
+#[derive(Debug, Default)]
+struct Settings {
+    /// Applied to every utterance, underneath the utterance's own overrides.
+    prosody: Prosody,
+    language: Language,
+    voices: LanguageVoices,
+    /// The voice the user chose, if any. Otherwise the engine's default voice is.
+    default_voice: Option<VoiceInfo>,
+    /// The voice of another language the engine was switched to, if it was.
+    switched_voice: Option<VoiceInfo>,
+}
+
+impl Settings {
+    /// Switch the engine to `voice`, or back to the default voice for `None`.
+    async fn use_voice(&mut self, engine: &dyn SpeechEngine, voice: Option<&VoiceInfo>) {
+        let target = match voice {
+            Some(voice) if self.switched_voice.as_ref() == Some(voice) => return,
+            Some(voice)
+                if self.switched_voice.is_none() && self.default_voice.as_ref() == Some(voice) =>
+            {
+                return
+            }
+            Some(voice) => voice.clone(),
+            None if self.switched_voice.is_none() => return,
+            None => match &self.default_voice {
+                Some(voice) => voice.clone(),
+                None => match engine.default_voice().await {
+                    Ok(voice) => voice,
+                    Err(e) => {
+                        log::error!("Could not find the default voice: {:?}", e);
+                        return;
+                    }
+                },
+            },
+        };
+        if let Err(e) = engine.set_voice(&target).await {
+            log::error!("Could not switch to {}: {:?}", target.display_name, e);
+            return;
+        }
+        self.switched_voice = voice.cloned();
+    }
+}
+
+/// A sentence being synthesized ahead of time.
+struct Preparation {
+    text: String,
+    task: JoinHandle<()>,
+}
+
+/// Speak the current sentence of the current utterance, in the voice for its
+/// language, and start preparing the next one if it has the same voice. Returns
+/// `false`, finishing the utterance, if speaking failed.
+async fn speak_sentence(
+    engine: &Arc<dyn SpeechEngine>,
+    queue: &mut SpeechQueue,
+    pronouncer: &Pronouncer,
+    settings: &mut Settings,
+    preparing: &mut Option<Preparation>,
+) -> bool {
+    let Some((prosody, cue, tracked)) = queue.current.as_ref().map(|e| {
+        let utterance = &e.utterance;
+        (
+            utterance.prosody_over(settings.prosody),
+            utterance.cue.clone(),
+            e.progress.is_some(),
+        )
+    }) else {
+        return false;
+    };
+    let Some(sentences) =
+        queue.sentences(|utterance| Sentences::new(utterance, settings, pronouncer))
+    else {
+        return false;
+    };
+    let Some(sentence) = sentences.current().cloned() else {
+        return false;
+    };
+    let cue = cue.filter(|_| sentences.current == 0);
+    let following = sentences
+        .following()
+        .filter(|following| following.voice == sentence.voice)
+        .map(|following| following.spoken.text.clone());
+    let text = sentence.spoken.text;
+
+    settings
+        .use_voice(engine.as_ref(), sentence.voice.as_ref())
+        .await;
+
+    // Let a preparation of this sentence finish rather than synthesize it twice.
+    if let Some(preparation) = preparing.take() {
+        if preparation.text == text {
+            let _ = preparation.task.await;
+        } else {
+            preparation.task.abort();
+        }
+    }
+    if let Some(cue) = cue {
+        cue.play();
+    }
+    let spoken = if tracked {
+        engine.speak_tracked(&text, &prosody).await
+    } else {
+        engine.speak(&text, &prosody).await
+    };
+    if let Err(e) = spoken {
+        log::error!("TTS speak failed for queued utterance: {:?}", e);
+        queue.finish_current();
+        return false;
+    }
+
+    if let Some(following) = following {
+        let engine = engine.clone();
+        let text = following.clone();
+        let task = tokio::spawn(async move {
+            let prepared = if tracked {
+                engine.prepare_tracked(&text, &prosody).await
+            } else {
+                engine.prepare(&text, &prosody).await
+            };
+            if let Err(e) = prepared {
+                log::warn!("Could not prepare the next sentence: {:?}", e);
+            }
+        });
+        *preparing = Some(Preparation {
+            text: following,
+            task,
+        });
+    }
+    true
+}
+
+async fn run_queue(
+    engine: Arc<dyn SpeechEngine>,
+    pronouncer: Arc<Pronouncer>,
+    mut commands: mpsc::Receiver<QueueCommand>,
+    state: watch::Sender<SpeechState>,
+) {
+    let mut queue = SpeechQueue::default();
+    let mut settings = Settings::default();
+    let mut preparing = None;
+    let mut events = engine.subscribe();
+    let mut events_open = true;
+
+    loop {
+        if !queue.is_playing() && queue.start_next().is_some() {
+            // Skip events still buffered from earlier utterances.
+            events = events.resubscribe();
+            let spoken = speak_sentence(
+                &engine,
+                &mut queue,
+                &pronouncer,
+                &mut settings,
+                &mut preparing,
+            );
+            if !spoken.await {
+                continue;
+            }
+        }
+
+        let command = if queue.is_playing() {
+            // Biased, so boundaries already reported are delivered before completion.
+            tokio::select! {
+                biased;
+                event = events.recv(), if events_open => {
+                    match event {
+                        Ok(SpeechEvent::Boundary(boundary)) => queue.report(boundary),
+                        Ok(SpeechEvent::Failed(reason)) => {
+                            log::error!("Playback failed: {}", reason);
+                        }
+                        // The end is seen through `wait_for_completion`.
+                        Ok(SpeechEvent::Started | SpeechEvent::Ended) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            log::warn!("Speech queue missed {} engine events.", skipped);
+                        }
+                        Err(RecvError::Closed) => events_open = false,
+                    }
+                    continue;
+                }
+                command = commands.recv() => command,
+                result = engine.wait_for_completion() => {
+                    if let Err(e) = result {
+                        log::error!("TTS failed while waiting for playback: {:?}", e);
+                    }
+                    if queue.next_sentence() {
+                        events = events.resubscribe();
+                        speak_sentence(
+                            &engine,
+                            &mut queue,
+                            &pronouncer,
+                            &mut settings,
+                            &mut preparing,
+                        )
+                        .await;
+                    }
+                    continue;
+                }
+            }
+        } else {
+            commands.recv().await
+        };
+
+        let current_state = *state.borrow();
+        let stop_current = match command {
+            Some(QueueCommand::Enqueue(utterance, ..)) if !current_state.allows(&utterance) => {
+                log::warn!("Not speaking while {:?}: {}", current_state, utterance.text);
+                false
+            }
+            Some(QueueCommand::Enqueue(utterance, done, progress)) => queue.push(Entry {
+                utterance,
+                _done: done,
+                progress,
+                sentences: None,
+            }),
+            Some(QueueCommand::Clear | QueueCommand::Skip) if !current_state.allows_stop() => {
+                log::warn!("Not stopping speech while {:?}", current_state);
+                false
+            }
+            Some(QueueCommand::Clear) => queue.clear(),
+            // The end of the stopped sentence moves on to the next one.
+            Some(QueueCommand::Skip) => queue.is_playing(),
+            Some(QueueCommand::SetProsody(next)) => {
+                settings.prosody = next;
+                false
+            }
+            Some(QueueCommand::SetLanguage(next)) => {
+                settings.language = next;
+                false
+            }
+            Some(QueueCommand::SetLanguageVoices(voices)) => {
+                settings.voices = voices;
+                false
+            }
+            Some(QueueCommand::SetDefaultVoice(voice)) => {
+                settings.default_voice = Some(voice);
+                settings.switched_voice = None;
+                false
+            }
+            Some(QueueCommand::SetState(next, reply)) => {
+                let result = transition(engine.as_ref(), &mut queue, &state, next).await;
+                let stop_current = matches!(result, Ok(true));
+                let _ = reply.send(result.map(|_| ()));
+                stop_current
+            }
+            None => break,
+        };
+        if stop_current {
+            if let Err(e) = engine.stop().await {
+                log::error!("TTS stop failed in speech queue: {:?}", e);
+            }
+        }
+    }
+
+    log::info!("Speech queue closed.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(utterance: Utterance) -> Entry {
+        Entry {
+            utterance,
+            _done: None,
+            progress: None,
+            sentences: None,
+        }
+    }
+
+    fn pending_texts(queue: &SpeechQueue) -> Vec<&str> {
+        queue
+            .pending
+            .iter()
+            .map(|e| e.utterance.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn key_echo_does_not_cut_off_focus() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::focus("OK, button")));
+        queue.start_next();
+
+        assert!(!queue.push(entry(Utterance::key_echo("A"))));
+        assert_eq!(pending_texts(&queue), vec!["A"]);
+    }
+
+    #[test]
+    fn key_echo_replaces_older_key_echo() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::key_echo("A")));
+        queue.start_next();
+
+        assert!(queue.push(entry(Utterance::key_echo("B"))));
+        queue.push(entry(Utterance::key_echo("C")));
+        assert_eq!(pending_texts(&queue), vec!["C"]);
+    }
+
+    #[test]
+    fn focus_replaces_everything() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::key_echo("A")));
+        queue.push(entry(Utterance::notification("Download complete")));
+        queue.start_next();
+
+        assert!(queue.push(entry(Utterance::focus("OK, button"))));
+        assert_eq!(pending_texts(&queue), vec!["OK, button"]);
+    }
+
+    #[test]
+    fn replace_does_not_touch_higher_priorities() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::system("Welcome to Aria.")));
+        queue.start_next();
+
+        let stop = queue.push(entry(Utterance::new(
+            "low",
+            Priority::Normal,
+            Policy::ReplaceAll,
+            Category::Other,
+        )));
+        assert!(!stop);
+        assert!(queue.is_playing());
+    }
+
+    #[test]
+    fn preempted_speech_is_requeued() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::notification("Download complete")));
+        queue.start_next();
+
+        assert!(queue.push(entry(Utterance::key_echo("A"))));
+        assert_eq!(pending_texts(&queue), vec!["A", "Download complete"]);
+    }
+
+    #[test]
+    fn higher_priorities_are_spoken_first() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::notification("later")));
+        queue.push(entry(Utterance::from("normal")));
+        queue.push(entry(Utterance::new(
+            "urgent",
+            Priority::Interrupt,
+            Policy::Append,
+            Category::Other,
+        )));
+        assert_eq!(pending_texts(&queue), vec!["urgent", "normal", "later"]);
+    }
+
+    #[test]
+    fn retain_drops_rejected_speech() {
+        let mut queue = SpeechQueue::default();
+        queue.push(entry(Utterance::from("Hello")));
+        queue.push(entry(Utterance::notification("Download complete")));
+        queue.start_next();
+
+        assert!(queue.retain(|u| u.category == Category::Notification));
+        assert!(!queue.is_playing());
+        assert_eq!(pending_texts(&queue), vec!["Download complete"]);
+    }
+
+    #[test]
+    fn clear_reports_playing_speech() {
+        let mut queue = SpeechQueue::default();
+        assert!(!queue.clear());
+        queue.push(entry(Utterance::from("Hello")));
+        queue.push(entry(Utterance::from("World")));
+        queue.start_next();
+        assert!(queue.clear());
+        assert!(queue.start_next().is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::queue::{Category, Utterance};
+
+/// The lifecycle of the speech controller.
+///
+/// The state is owned by the speech queue task, which checks every utterance and
+/// stop request against it. Observe it with [`crate::tts::TTS::subscribe_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpeechState {
+    /// Aria is starting up. Speech plays, but cannot be stopped, so the welcome
+    /// message is not cut off.
+    Starting,
+    /// Normal operation.
+    Running,
+    /// Only messages about Aria itself are spoken.
+    Muted,
+    /// Aria is shutting down. Only messages about Aria itself are spoken and
+    /// they cannot be stopped.
+    ShuttingDown,
+    /// The engine has been closed. Nothing is spoken anymore.
+    Stopped,
+}
+
+impl SpeechState {
+    /// Whether `utterance` may be spoken in this state.
+    pub fn allows(self, utterance: &Utterance) -> bool {
+        match self {
+            SpeechState::Starting | SpeechState::Running => true,
+            SpeechState::Muted | SpeechState::ShuttingDown => {
+                utterance.category == Category::System
+            }
+            SpeechState::Stopped => false,
+        }
+    }
+
+    /// Whether a stop request cuts off speech in this state.
+    pub fn allows_stop(self) -> bool {
+        matches!(self, SpeechState::Running | SpeechState::Muted)
+    }
+
+    /// Whether the controller may move from this state to `next`. Staying in the
+    /// same state is always allowed.
+    pub fn can_become(self, next: SpeechState) -> bool {
+        use SpeechState::*;
+        self == next
+            || match (self, next) {
+                (Stopped, _) => false,
+                (ShuttingDown, next) => next == Stopped,
+                (_, Starting) => false,
+                _ => true,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SpeechState::*;
+
+    #[test]
+    fn shutting_down_only_leads_to_stopped() {
+        assert!(Running.can_become(ShuttingDown));
+        assert!(ShuttingDown.can_become(Stopped));
+        assert!(!ShuttingDown.can_become(Running));
+        assert!(!Stopped.can_become(Running));
+        assert!(!Running.can_become(Starting));
+        assert!(Running.can_become(Muted) && Muted.can_become(Running));
+    }
+
+    #[test]
+    fn only_system_messages_pass_while_muted() {
+        assert!(Muted.allows(&Utterance::system("Aria shutting down.")));
+        assert!(!Muted.allows(&Utterance::focus("OK, button")));
+        assert!(!Stopped.allows(&Utterance::system("Hello")));
+        assert!(!Starting.allows_stop());
+    }
+}
@@ -1,346 +1,765 @@
-use crate::error::TTSError;
-use aria_utils::config::get_config;
-use aria_utils::error::ConfigError as AriaUtilsConfigError;
-use log::warn;
-use once_cell::sync::Lazy;
-use tokio::sync::{Mutex, OnceCell as TokioOnceCell};
-use windows::{
-    core::HSTRING,
-    Foundation::IAsyncOperation,
-    Media::{
-        Core::MediaSource,
-        Playback::MediaPlayer,
-        SpeechSynthesis::{
-            SpeechAppendedSilence, SpeechPunctuationSilence, SpeechSynthesizer, VoiceInformation,
-        },
-    },
-};
-
-#[derive(Debug, Clone)]
-pub struct VoiceInfo {
-    pub id: String,
-    pub display_name: String,
-    pub language: String,
-    pub gender: String,
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use aria_utils::config::{Language, PunctuationLevel};
+use aria_utils::pronunciation::Pronunciation;
+
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+use crate::audio::AudioBuffer;
+use crate::engine::{Prosody, SpeechEngine, VoiceInfo};
+use crate::error::{Result, TTSError};
+use crate::event::{Boundary, SpeechEvent};
+use crate::language::LanguageVoices;
+use crate::pronunciation::Pronouncer;
+use crate::queue::{spawn_queue, QueueCommand, Utterance};
+use crate::state::SpeechState;
+use crate::voice::VoiceSelector;
+
+/// Speech controller shared by everything in Aria that talks.
+///
+/// `TTS` schedules utterances through a priority queue that feeds a
+/// [`SpeechEngine`]. The task running the queue also owns the [`SpeechState`],
+/// which decides what may be spoken and whether speech can be stopped. Long
+/// utterances are spoken a sentence at a time, with the next sentence synthesized
+/// while the one before plays. Clones share the same engine, queue and state.
+#[derive(Clone)]
+pub struct TTS {
+    engine: Arc<dyn SpeechEngine>,
+    queue: mpsc::Sender<QueueCommand>,
+    state: watch::Receiver<SpeechState>,
+    pronouncer: Arc<Pronouncer>,
 }
 
-type Result<T> = std::result::Result<T, TTSError>;
-
-async fn create_and_configure_synthesizer() -> Result<SpeechSynthesizer> {
-    let synthesizer = SpeechSynthesizer::new().map_err(TTSError::Windows)?;
-    let synthesizer_options = synthesizer.Options().map_err(TTSError::Windows)?;
-    let config = get_config().map_err(|e: AriaUtilsConfigError| match e {
-        AriaUtilsConfigError::Io(io_err) => TTSError::Windows(windows::core::Error::from(io_err)),
-        AriaUtilsConfigError::TomlSer(toml_err) => {
-            TTSError::Synthesis(format!("Config TOML error: {}", toml_err))
-        }
-        AriaUtilsConfigError::Lib(lib_err) => {
-            TTSError::Synthesis(format!("Config lib error: {}", lib_err))
-        }
-        AriaUtilsConfigError::HomeDir => {
-            TTSError::Synthesis("Failed to get home directory for config".to_string())
-        }
-        AriaUtilsConfigError::PathToStr { path } => {
-            TTSError::Synthesis(format!("Config path error for {:?}", path))
-        }
-    })?;
-
-    synthesizer_options
-        .SetSpeakingRate(config.speech_rate)
-        .map_err(TTSError::Windows)?;
-    synthesizer_options
-        .SetAppendedSilence(if config.append_silence {
-            SpeechAppendedSilence::Default
-        } else {
-            SpeechAppendedSilence::Min
-        })
-        .map_err(TTSError::Windows)?;
-    synthesizer_options
-        .SetPunctuationSilence(if config.punctuation_silence {
-            SpeechPunctuationSilence::Default
-        } else {
-            SpeechPunctuationSilence::Min
-        })
-        .map_err(TTSError::Windows)?;
-
-    // Set voice if specified in config
-    if let Some(voice_name) = &config.voice {
-        if let Ok(Some(voice)) = find_voice_by_name(voice_name).await {
-            synthesizer.SetVoice(&voice).map_err(TTSError::Windows)?;
+impl TTS {
+    /// Create a controller for `engine`, in [`SpeechState::Starting`]. Must be
+    /// called from within a Tokio runtime, which runs the speech queue.
+    pub fn new(engine: Arc<dyn SpeechEngine>) -> Self {
+        let pronouncer = Arc::new(Pronouncer::default());
+        let (queue, state) = spawn_queue(engine.clone(), pronouncer.clone());
+        TTS {
+            queue,
+            engine,
+            state,
+            pronouncer,
         }
     }
 
-    Ok(synthesizer)
-}
-
-async fn create_media_player() -> Result<MediaPlayer> {
-    MediaPlayer::new().map_err(TTSError::Windows)
-}
+    /// The engine this controller speaks through.
+    pub fn engine(&self) -> &Arc<dyn SpeechEngine> {
+        &self.engine
+    }
 
-static SYNTHESIZER: TokioOnceCell<SpeechSynthesizer> = TokioOnceCell::const_new();
-static PLAYER: TokioOnceCell<MediaPlayer> = TokioOnceCell::const_new();
+    async fn send(&self, command: QueueCommand) -> Result<()> {
+        self.queue
+            .send(command)
+            .await
+            .map_err(|_| TTSError::NotInitialized)
+    }
 
-static CAN_STOP: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
-static CAN_SPEAK: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(true));
+    /// Queue an utterance according to its priority and policy. It is dropped if
+    /// the current state does not allow it.
+    pub async fn speak<U: Into<Utterance>>(&self, utterance: U) -> Result<()> {
+        let utterance = utterance.into();
+        log::info!("{}", utterance.text);
+        self.send(QueueCommand::Enqueue(utterance, None, None))
+            .await
+    }
 
-async fn get_synthesizer() -> Result<&'static SpeechSynthesizer> {
-    SYNTHESIZER
-        .get_or_try_init(create_and_configure_synthesizer)
-        .await
-}
+    /// Queue an utterance and receive its word and sentence boundaries while it plays.
+    /// The receiver closes once the utterance has been spoken or was dropped from the queue.
+    pub async fn speak_with_progress<U: Into<Utterance>>(
+        &self,
+        utterance: U,
+    ) -> Result<mpsc::UnboundedReceiver<Boundary>> {
+        let utterance = utterance.into();
+        log::info!("{}", utterance.text);
+        let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+        self.send(QueueCommand::Enqueue(utterance, None, Some(progress_tx)))
+            .await?;
+        Ok(progress_rx)
+    }
 
-async fn get_player() -> Result<&'static MediaPlayer> {
-    PLAYER.get_or_try_init(create_media_player).await
-}
+    /// Queue an utterance and wait until it has been spoken or was dropped from the queue.
+    pub async fn speak_and_wait<U: Into<Utterance>>(&self, utterance: U) -> Result<()> {
+        let utterance = utterance.into();
+        log::info!("{}", utterance.text);
+        let (done_tx, done_rx) = oneshot::channel();
+        self.send(QueueCommand::Enqueue(utterance, Some(done_tx), None))
+            .await?;
+        // The queue drops the sender once it is done with the utterance.
+        let _ = done_rx.await;
+        Ok(())
+    }
 
-async fn await_windows_async<T, F>(op_factory: F) -> Result<T>
-where
-    T: windows::core::RuntimeType + Send + 'static,
-    F: FnOnce() -> windows::core::Result<IAsyncOperation<T>> + Send + 'static,
-    IAsyncOperation<T>: Send + Sync,
-{
-    let operation = tokio::task::spawn_blocking(op_factory)
-        .await
-        .map_err(|e| TTSError::Synthesis(format!("Task spawn join error: {}", e)))??;
+    /// Synthesize an utterance to audio instead of speaking it. This bypasses the
+    /// queue, so it neither interrupts nor waits for speech that is playing.
+    pub async fn synthesize<U: Into<Utterance>>(&self, utterance: U) -> Result<AudioBuffer> {
+        let utterance = utterance.into();
+        let text = self.pronouncer.apply(&utterance.text);
+        let prosody = utterance.prosody_over(Prosody::default());
+        self.engine.synthesize(&text, &prosody).await
+    }
 
-    tokio::task::spawn_blocking(move || operation.get())
-        .await
-        .map_err(|e| TTSError::Synthesis(format!("Task spawn for .get() error: {}", e)))?
-        .map_err(TTSError::Windows)
-}
+    /// Get a list of all installed TTS voices
+    pub async fn get_available_voices(&self) -> Result<Vec<VoiceInfo>> {
+        self.engine.voices().await
+    }
 
-async fn get_installed_voices() -> Result<Vec<VoiceInfo>> {
-    tokio::task::spawn_blocking(|| {
-        let voices = SpeechSynthesizer::AllVoices().map_err(TTSError::Windows)?;
-
-        let mut voice_list = Vec::new();
-        let voice_count = voices.Size().map_err(TTSError::Windows)?;
-
-        for i in 0..voice_count {
-            if let Ok(voice) = voices.GetAt(i) {
-                let display_name = voice.DisplayName().map_err(TTSError::Windows)?.to_string();
-                let id = voice.Id().map_err(TTSError::Windows)?.to_string();
-                let language = voice.Language().map_err(TTSError::Windows)?.to_string();
-                let gender = match voice.Gender().map_err(TTSError::Windows)? {
-                    windows::Media::SpeechSynthesis::VoiceGender::Male => "Male".to_string(),
-                    windows::Media::SpeechSynthesis::VoiceGender::Female => "Female".to_string(),
-                    _ => "Unknown".to_string(),
-                };
-
-                voice_list.push(VoiceInfo {
-                    id,
-                    display_name,
-                    language,
-                    gender,
-                });
-            }
-        }
+    /// Select the voice that best matches `selector` (see [`crate::voice`]) and
+    /// return it.
+    pub async fn set_voice(&self, selector: &str) -> Result<VoiceInfo> {
+        let selector: VoiceSelector = selector.parse()?;
+        let voices = self.engine.voices().await?;
+        let voice = selector.select(&voices)?.clone();
+        self.use_voice(&voice).await?;
+        Ok(voice)
+    }
 
-        Ok(voice_list)
-    })
-    .await
-    .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
-}
+    /// Speak with `voice`, one of [`TTS::get_available_voices`], and pronounce
+    /// words with its dictionary.
+    pub async fn use_voice(&self, voice: &VoiceInfo) -> Result<()> {
+        self.engine.set_voice(voice).await?;
+        self.pronouncer.set_voice(voice.clone());
+        self.send(QueueCommand::SetDefaultVoice(voice.clone()))
+            .await
+    }
 
-async fn find_voice_by_name(voice_name: &str) -> Result<Option<VoiceInformation>> {
-    let voice_name = voice_name.to_string();
-    tokio::task::spawn_blocking(move || {
-        let voices = SpeechSynthesizer::AllVoices().map_err(TTSError::Windows)?;
-        let voice_count = voices.Size().map_err(TTSError::Windows)?;
-
-        for i in 0..voice_count {
-            if let Ok(voice) = voices.GetAt(i) {
-                let display_name = voice.DisplayName().map_err(TTSError::Windows)?.to_string();
-                if display_name == voice_name {
-                    return Ok(Some(voice));
+    /// Speak each sentence with the voice for its language, detected or given by
+    /// [`Utterance::language`]. `selectors` maps languages such as `de` to voice
+    /// selectors such as `lang:de-DE`; sentences in other languages keep the
+    /// default voice. Languages or voices that are not found are skipped.
+    pub async fn set_language_voices(&self, selectors: &BTreeMap<String, String>) -> Result<()> {
+        let available = self.engine.voices().await?;
+        let mut voices = LanguageVoices::default();
+        for (language, selector) in selectors {
+            let voice = match selector.parse::<VoiceSelector>() {
+                Ok(selector) => selector.select(&available).cloned(),
+                Err(e) => Err(e),
+            };
+            match voice {
+                Ok(voice) => {
+                    if !voices.add(language, voice) {
+                        log::warn!("Unknown language {:?} in language_voices", language);
+                    }
                 }
+                Err(e) => log::warn!("No voice for {:?}: {}", language, e),
             }
         }
+        self.send(QueueCommand::SetLanguageVoices(voices)).await
+    }
 
-        Ok(None)
-    })
-    .await
-    .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
-}
+    /// Rewrite everything spoken or synthesized from now on with `dictionaries`,
+    /// see [`aria_utils::pronunciation`].
+    pub fn set_pronunciation(&self, dictionaries: Pronunciation) {
+        self.pronouncer.set_dictionaries(dictionaries);
+    }
 
-pub struct TTS;
+    /// Speak the names of the symbols `level` includes from now on, see
+    /// [`aria_utils::symbols`].
+    pub fn set_punctuation(&self, level: PunctuationLevel) {
+        self.pronouncer.set_punctuation(level);
+    }
 
-impl TTS {
-    pub async fn speak(text: &str, ignore_can_speak: bool) -> Result<()> {
-        log::info!("{}", text);
+    /// Pronounce words with the dictionary of `app`, the executable of the
+    /// focused application, from now on.
+    pub fn set_app(&self, app: Option<String>) {
+        self.pronouncer.set_app(app);
+    }
 
-        let can_speak_guard = CAN_SPEAK.lock().await;
-        if !*can_speak_guard && !ignore_can_speak {
-            warn!("Cannot speak: speaking is disabled");
-            return Ok(());
-        }
-        drop(can_speak_guard);
+    /// Speak everything queued from now on with `prosody`, except for values an
+    /// utterance overrides itself. Values left at `None` keep the engine's settings.
+    pub async fn set_prosody(&self, prosody: Prosody) -> Result<()> {
+        self.send(QueueCommand::SetProsody(prosody)).await
+    }
+
+    /// Find the ends of sentences following the conventions of `language` from now
+    /// on, see [`aria_utils::sentences`].
+    pub async fn set_language(&self, language: Language) -> Result<()> {
+        self.send(QueueCommand::SetLanguage(language)).await
+    }
 
-        let synthesizer = get_synthesizer().await?;
-        let player = get_player().await?;
+    /// Get the current default voice information
+    pub async fn get_default_voice(&self) -> Result<VoiceInfo> {
+        self.engine.default_voice().await
+    }
 
-        let text_hstring = HSTRING::from(text);
+    /// Receive the engine's playback events from now on: when speech starts,
+    /// reaches a word or sentence, ends or fails.
+    pub fn subscribe(&self) -> broadcast::Receiver<SpeechEvent> {
+        self.engine.subscribe()
+    }
 
-        let stream =
-            await_windows_async(move || synthesizer.SynthesizeTextToStreamAsync(&text_hstring))
-                .await?;
+    /// The state the controller is in right now.
+    pub fn state(&self) -> SpeechState {
+        *self.state.borrow()
+    }
 
-        let content_type = stream.ContentType().map_err(TTSError::Windows)?;
-        let media_source =
-            MediaSource::CreateFromStream(&stream, &content_type).map_err(TTSError::Windows)?;
+    /// Watch every transition of the controller's state.
+    pub fn subscribe_state(&self) -> watch::Receiver<SpeechState> {
+        self.state.clone()
+    }
 
-        player.SetSource(&media_source).map_err(TTSError::Windows)?;
-        player.Play().map_err(TTSError::Windows)?;
+    /// Move to `state` once everything sent before has been handled. Speech the new
+    /// state does not allow is dropped, and [`SpeechState::Stopped`] closes the
+    /// engine. Fails if the current state cannot become `state`.
+    pub async fn set_state(&self, state: SpeechState) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.send(QueueCommand::SetState(state, reply_tx)).await?;
+        reply_rx.await.map_err(|_| TTSError::NotInitialized)?
+    }
 
-        Ok(())
+    /// Stop the current utterance and drop everything that is queued, unless the
+    /// current state protects speech from being stopped.
+    pub async fn stop(&self) -> Result<()> {
+        self.send(QueueCommand::Clear).await
     }
 
-    /// Speak text and wait for completion
-    pub async fn speak_and_wait(text: &str, ignore_can_speak: bool) -> Result<()> {
-        log::info!("{}", text);
+    /// Cut the sentence that is playing short and go on with the next one, or with
+    /// the next utterance after the last sentence. Like [`TTS::stop`], this does
+    /// nothing while the current state protects speech from being stopped.
+    pub async fn skip(&self) -> Result<()> {
+        self.send(QueueCommand::Skip).await
+    }
+}
 
-        let can_speak_guard = CAN_SPEAK.lock().await;
-        if !*can_speak_guard && !ignore_can_speak {
-            warn!("Cannot speak: speaking is disabled");
-            return Ok(());
-        }
-        drop(can_speak_guard);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::RecordingEngine;
+    use crate::event::BoundaryKind;
+    use std::time::Duration;
+
+    /// Let the queue task catch up. Time is paused, so this returns as soon as
+    /// every task is idle.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
 
-        let synthesizer = get_synthesizer().await?;
-        let player = get_player().await?;
+    fn voice(name: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: name.to_lowercase(),
+            display_name: name.to_string(),
+            language: "en-US".to_string(),
+            gender: "Female".to_string(),
+        }
+    }
 
-        let text_hstring = HSTRING::from(text);
+    #[tokio::test(start_paused = true)]
+    async fn muted_speech_only_lets_system_messages_through() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
 
-        let stream =
-            await_windows_async(move || synthesizer.SynthesizeTextToStreamAsync(&text_hstring))
-                .await?;
+        tts.set_state(SpeechState::Muted).await.unwrap();
+        tts.speak("dropped").await.unwrap();
+        tts.speak_and_wait(Utterance::system("Aria muted."))
+            .await
+            .unwrap();
 
-        let content_type = stream.ContentType().map_err(TTSError::Windows)?;
-        let media_source =
-            MediaSource::CreateFromStream(&stream, &content_type).map_err(TTSError::Windows)?;
+        assert_eq!(engine.spoken_texts(), vec!["Aria muted."]);
+    }
 
-        player.SetSource(&media_source).map_err(TTSError::Windows)?;
-        player.Play().map_err(TTSError::Windows)?;
+    #[tokio::test(start_paused = true)]
+    async fn stop_is_ignored_while_starting() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        tts.speak("Hello").await.unwrap();
+        settle().await;
+
+        tts.stop().await.unwrap();
+        settle().await;
+        assert_eq!(engine.stop_count(), 0);
+
+        tts.set_state(SpeechState::Running).await.unwrap();
+        tts.stop().await.unwrap();
+        settle().await;
+        assert_eq!(engine.stop_count(), 1);
+    }
 
-        // Wait for playback to complete
-        Self::wait_for_playback_completion(player).await?;
+    #[tokio::test(start_paused = true)]
+    async fn state_transitions_are_observable() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        let mut states = tts.subscribe_state();
+        assert_eq!(*states.borrow_and_update(), SpeechState::Starting);
+
+        tts.set_state(SpeechState::Running).await.unwrap();
+        assert!(states.has_changed().unwrap());
+        assert_eq!(*states.borrow_and_update(), SpeechState::Running);
+
+        tts.set_state(SpeechState::ShuttingDown).await.unwrap();
+        assert!(matches!(
+            tts.set_state(SpeechState::Running).await,
+            Err(TTSError::InvalidTransition { .. })
+        ));
+        tts.set_state(SpeechState::Stopped).await.unwrap();
+        assert_eq!(tts.state(), SpeechState::Stopped);
+        assert!(engine.is_closed());
+    }
 
-        Ok(())
+    #[tokio::test(start_paused = true)]
+    async fn normal_priority_replaces_pending_speech() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+
+        tts.speak(Utterance::focus("OK, button")).await.unwrap();
+        tts.speak(Utterance::key_echo("A")).await.unwrap();
+        tts.speak(Utterance::key_echo("B")).await.unwrap();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["OK, button"]);
+        assert_eq!(engine.stop_count(), 0);
+
+        engine.finish_playback();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["OK, button", "B"]);
     }
 
-    /// Wait for media player to finish playing
-    async fn wait_for_playback_completion(player: &MediaPlayer) -> Result<()> {
-        use windows::Media::Playback::MediaPlaybackState;
+    #[tokio::test(start_paused = true)]
+    async fn prosody_reaches_the_engine() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        let raised = Prosody {
+            pitch: Some(1.3),
+            ..Prosody::default()
+        };
 
-        // Poll the player state until it's no longer playing
-        loop {
-            let state = tokio::task::spawn_blocking({
-                let player = player.clone();
-                move || player.PlaybackSession()?.PlaybackState()
-            })
+        tts.speak_and_wait(Utterance::key_echo("A").with_prosody(raised))
             .await
-            .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
-            .map_err(TTSError::Windows)?;
+            .unwrap();
 
-            match state {
-                MediaPlaybackState::Playing => {
-                    // Still playing, wait a bit and check again
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                }
-                MediaPlaybackState::Paused | MediaPlaybackState::None => {
-                    // Playback finished
-                    break;
-                }
-                _ => {
-                    // Other states, wait a bit and check again
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                }
+        assert_eq!(engine.utterances()[0].prosody, raised);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn utterances_override_the_live_prosody() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        let faster = Prosody {
+            rate: Some(1.5),
+            volume: Some(0.5),
+            ..Prosody::default()
+        };
+        let raised = Prosody {
+            pitch: Some(1.3),
+            volume: Some(1.0),
+            ..Prosody::default()
+        };
+
+        tts.set_prosody(faster).await.unwrap();
+        tts.speak_and_wait(Utterance::key_echo("A").with_prosody(raised))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            engine.utterances()[0].prosody,
+            Prosody {
+                rate: Some(1.5),
+                pitch: Some(1.3),
+                volume: Some(1.0),
             }
-        }
+        );
+    }
 
-        Ok(())
+    #[tokio::test(start_paused = true)]
+    async fn pitch_factor_scales_the_live_pitch() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        let low = Prosody {
+            pitch: Some(0.8),
+            ..Prosody::default()
+        };
+
+        tts.speak_and_wait(Utterance::key_echo("A").with_pitch_factor(1.5))
+            .await
+            .unwrap();
+        tts.set_prosody(low).await.unwrap();
+        tts.speak_and_wait(Utterance::key_echo("A").with_pitch_factor(1.5))
+            .await
+            .unwrap();
+
+        let pitches: Vec<_> = engine
+            .utterances()
+            .iter()
+            .map(|u| u.prosody.pitch)
+            .collect();
+        assert_eq!(pitches, vec![Some(1.5), Some(0.8 * 1.5)]);
     }
 
-    /// Get a list of all installed TTS voices
-    pub async fn get_available_voices() -> Result<Vec<VoiceInfo>> {
-        get_installed_voices().await
+    #[tokio::test(start_paused = true)]
+    async fn progress_follows_its_own_utterance() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+
+        tts.speak("first utterance").await.unwrap();
+        let mut progress = tts.speak_with_progress("Hello world").await.unwrap();
+        settle().await;
+        assert!(progress.try_recv().is_err());
+
+        engine.finish_playback();
+        settle().await;
+        engine.finish_playback();
+        let ranges: Vec<_> = std::iter::from_fn(|| progress.try_recv().ok())
+            .map(|b| (b.kind, b.range))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(BoundaryKind::Word, 0..5), (BoundaryKind::Word, 6..11)]
+        );
+
+        settle().await;
+        assert!(progress.recv().await.is_none());
     }
 
-    /// Set the current TTS voice by name
-    pub async fn set_voice(voice_name: &str) -> Result<bool> {
-        let synthesizer = get_synthesizer().await?;
+    #[tokio::test(start_paused = true)]
+    async fn long_text_is_spoken_a_sentence_at_a_time() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        let prepared = || -> Vec<_> { engine.prepared().into_iter().map(|u| u.text).collect() };
 
-        if let Ok(Some(voice)) = find_voice_by_name(voice_name).await {
-            synthesizer.SetVoice(&voice).map_err(TTSError::Windows)?;
-            return Ok(true);
-        }
-        Ok(false)
+        tts.speak("First sentence.  Second one! Third?")
+            .await
+            .unwrap();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["First sentence."]);
+        assert_eq!(prepared(), vec!["Second one!"]);
+
+        engine.finish_playback();
+        settle().await;
+        assert_eq!(prepared(), vec!["Second one!", "Third?"]);
+        engine.finish_playback();
+        settle().await;
+        engine.finish_playback();
+        settle().await;
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["First sentence.", "Second one!", "Third?"]
+        );
+        assert!(!engine.is_playing());
     }
 
-    /// Get the current default voice information
-    pub async fn get_default_voice() -> Result<VoiceInfo> {
-        tokio::task::spawn_blocking(|| {
-            let default_voice = SpeechSynthesizer::DefaultVoice().map_err(TTSError::Windows)?;
-            let display_name = default_voice
-                .DisplayName()
-                .map_err(TTSError::Windows)?
-                .to_string();
-            let id = default_voice.Id().map_err(TTSError::Windows)?.to_string();
-            let language = default_voice
-                .Language()
-                .map_err(TTSError::Windows)?
-                .to_string();
-            let gender = match default_voice.Gender().map_err(TTSError::Windows)? {
-                windows::Media::SpeechSynthesis::VoiceGender::Male => "Male".to_string(),
-                windows::Media::SpeechSynthesis::VoiceGender::Female => "Female".to_string(),
-                _ => "Unknown".to_string(),
-            };
-
-            Ok(VoiceInfo {
-                id,
-                display_name,
-                language,
-                gender,
-            })
-        })
+    #[tokio::test(start_paused = true)]
+    async fn sentences_switch_to_the_voice_of_their_language() {
+        let german = VoiceInfo {
+            language: "de-DE".to_string(),
+            ..voice("Hedda")
+        };
+        let engine = Arc::new(RecordingEngine::with_voices(vec![
+            voice("Zira"),
+            german.clone(),
+        ]));
+        let tts = TTS::new(engine.clone());
+        let selectors = BTreeMap::from([
+            ("de".to_string(), "lang:de-DE".to_string()),
+            ("tlh".to_string(), "lang:tlh".to_string()),
+        ]);
+        tts.set_language_voices(&selectors).await.unwrap();
+        let voices = || -> Vec<_> {
+            engine
+                .utterances()
+                .into_iter()
+                .map(|u| (u.text, u.voice.id))
+                .collect()
+        };
+
+        tts.speak_and_wait(
+            "The file could not be saved. Die Datei konnte nicht gespeichert werden. Try again.",
+        )
         .await
-        .map_err(|e| TTSError::Synthesis(format!("Task spawn error: {}", e)))?
+        .unwrap();
+        assert_eq!(
+            voices(),
+            vec![
+                (
+                    "The file could not be saved.".to_string(),
+                    "zira".to_string()
+                ),
+                (
+                    "Die Datei konnte nicht gespeichert werden.".to_string(),
+                    "hedda".to_string()
+                ),
+                ("Try again.".to_string(), "zira".to_string()),
+            ]
+        );
+
+        tts.speak_and_wait(Utterance::from("OK").with_language("de-AT"))
+            .await
+            .unwrap();
+        assert_eq!(voices()[3], ("OK".to_string(), "hedda".to_string()));
+
+        // The voice the user picks replaces the default the text falls back to.
+        tts.use_voice(&german).await.unwrap();
+        tts.speak_and_wait("The file could not be saved.")
+            .await
+            .unwrap();
+        assert_eq!(voices()[4].1, "hedda");
     }
 
-    pub async fn set_can_stop(can_stop: bool) -> Result<()> {
-        let mut can_stop_lock = CAN_STOP.lock().await;
-        *can_stop_lock = can_stop;
-        Ok(())
+    #[tokio::test(start_paused = true)]
+    async fn skip_moves_on_to_the_next_sentence() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        tts.set_state(SpeechState::Running).await.unwrap();
+
+        tts.speak("One. Two.").await.unwrap();
+        tts.speak("Next").await.unwrap();
+        settle().await;
+        tts.skip().await.unwrap();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["One.", "Two."]);
+
+        tts.skip().await.unwrap();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["One.", "Two.", "Next"]);
+
+        tts.stop().await.unwrap();
+        settle().await;
+        assert_eq!(engine.stop_count(), 3);
+        assert!(!engine.is_playing());
     }
 
-    pub async fn set_can_speak(can_speak: bool) -> Result<()> {
-        let mut can_speak_lock = CAN_SPEAK.lock().await;
-        *can_speak_lock = can_speak;
-        Ok(())
+    #[tokio::test(start_paused = true)]
+    async fn preempted_speech_resumes_at_its_sentence() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+
+        tts.speak(Utterance::notification("One. Two. Three."))
+            .await
+            .unwrap();
+        settle().await;
+        engine.finish_playback();
+        settle().await;
+        tts.speak(Utterance::key_echo("A")).await.unwrap();
+        settle().await;
+        engine.finish_playback();
+        settle().await;
+
+        assert_eq!(engine.spoken_texts(), vec!["One.", "Two.", "A", "Two."]);
     }
 
-    pub async fn stop(ignore_can_stop: bool) -> Result<()> {
-        let can_stop_guard = CAN_STOP.lock().await;
-        if !*can_stop_guard && !ignore_can_stop {
-            warn!("Cannot stop: stopping is disabled");
-            return Ok(());
-        }
-        drop(can_stop_guard);
+    #[tokio::test(start_paused = true)]
+    async fn progress_spans_every_sentence() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
 
-        if let Some(player) = PLAYER.get() {
-            player.Pause().map_err(TTSError::Windows)?;
-        } else {
-            log::info!("Player not initialized, nothing to stop.");
+        let mut progress = tts.speak_with_progress("Hi there. Bye now.").await.unwrap();
+        let mut ranges = Vec::new();
+        while let Some(boundary) = progress.recv().await {
+            ranges.push(boundary.range);
         }
-        Ok(())
+
+        assert_eq!(engine.spoken_texts(), vec!["Hi there.", "Bye now."]);
+        assert_eq!(ranges, vec![0..2, 3..9, 10..13, 14..18]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sentences_end_by_the_rules_of_the_language() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+
+        tts.speak_and_wait("It was 5. Then").await.unwrap();
+        tts.set_language(Language::De).await.unwrap();
+        // Would end after "3." by the English rules.
+        tts.speak_and_wait("Am 3. Mai.").await.unwrap();
+
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["It was 5.", "Then", "Am 3. Mai."]
+        );
+    }
+
+    #[tokio::test]
+    async fn set_voice_accepts_selectors() {
+        let voice = |name: &str, language: &str| VoiceInfo {
+            id: name.to_lowercase(),
+            display_name: format!("Microsoft {} Desktop", name),
+            language: language.to_string(),
+            gender: "Female".to_string(),
+        };
+        let engine = Arc::new(RecordingEngine::with_voices(vec![
+            voice("Zira", "en-US"),
+            voice("Hedda", "de-DE"),
+        ]));
+        let tts = TTS::new(engine.clone());
+
+        assert_eq!(tts.set_voice("lang:de-AT").await.unwrap().id, "hedda");
+        assert_eq!(tts.set_voice("id:zira").await.unwrap().id, "zira");
+        assert!(matches!(
+            tts.set_voice("Cortana").await,
+            Err(TTSError::VoiceNotFound { .. })
+        ));
+
+        tts.speak_and_wait("Hello").await.unwrap();
+        assert_eq!(engine.utterances()[0].voice.id, "zira");
     }
 
-    pub async fn destroy() -> Result<()> {
-        if let Some(player) = PLAYER.get() {
-            player.Close().map_err(TTSError::Windows)?;
+    #[tokio::test]
+    async fn pronunciation_follows_the_voice_and_app() {
+        use aria_utils::pronunciation::{
+            DictionaryScope, PronunciationDictionaries, PronunciationEntry,
+        };
+
+        let mut dictionaries = PronunciationDictionaries::default();
+        let entries = [
+            (DictionaryScope::Global, "Aria", "Arya"),
+            (DictionaryScope::Voice("hedda".to_string()), "Aria", "Ahria"),
+            (
+                DictionaryScope::App("code.exe".to_string()),
+                "fn",
+                "function",
+            ),
+        ];
+        for (scope, pattern, replacement) in entries {
+            let entry = PronunciationEntry::new(pattern, replacement);
+            dictionaries.add(&scope, entry).unwrap();
         }
+        let engine = Arc::new(RecordingEngine::with_voices(vec![
+            voice("Zira"),
+            voice("Hedda"),
+        ]));
+        let tts = TTS::new(engine.clone());
+        tts.set_pronunciation(dictionaries.compile().unwrap());
+
+        tts.speak_and_wait("Aria fn").await.unwrap();
+        tts.set_voice("Hedda").await.unwrap();
+        tts.set_app(Some("Code.exe".to_string()));
+        tts.speak_and_wait("Aria fn").await.unwrap();
+        let audio = tts.synthesize("Aria").await.unwrap();
+
+        assert_eq!(engine.spoken_texts(), vec!["Arya fn", "Ahria function"]);
+        assert_eq!(engine.synthesized()[0].text, "Ahria");
+        assert!(!audio.data.is_empty());
+    }
 
-        log::info!("TTS resources associated with static player are closed (if initialized).");
-        Ok(())
+    #[tokio::test]
+    async fn symbols_are_named_at_the_punctuation_level() {
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+
+        tts.speak_and_wait("a = (b)").await.unwrap();
+        tts.set_punctuation(PunctuationLevel::Most);
+        tts.speak_and_wait("a = (b)").await.unwrap();
+
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["a = (b)", "a equals open paren b close paren"]
+        );
+    }
+
+    #[tokio::test]
+    async fn symbols_are_not_named_in_dictionary_replacements() {
+        use aria_utils::pronunciation::{
+            DictionaryScope, PronunciationDictionaries, PronunciationEntry,
+        };
+
+        let mut dictionaries = PronunciationDictionaries::default();
+        let entry = PronunciationEntry::new("hello", "[[h@'loU]]");
+        dictionaries.add(&DictionaryScope::Global, entry).unwrap();
+        let engine = Arc::new(RecordingEngine::new());
+        let tts = TTS::new(engine.clone());
+        tts.set_pronunciation(dictionaries.compile().unwrap());
+        tts.set_punctuation(PunctuationLevel::Some);
+
+        tts.speak_and_wait("hello @you").await.unwrap();
+        tts.speak_and_wait("#1 hello").await.unwrap();
+
+        assert_eq!(
+            engine.spoken_texts(),
+            vec!["[[h@'loU]] at you", "number 1 [[h@'loU]]"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cues_play_as_their_utterance_starts() {
+        use crate::queue::Cue;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        let played = Arc::new(AtomicUsize::new(0));
+        let cue = {
+            let played = played.clone();
+            Cue::new(move || {
+                played.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        tts.speak("a").await.unwrap();
+        tts.speak(Utterance::from("B").with_cue(cue)).await.unwrap();
+        settle().await;
+        assert_eq!(played.load(Ordering::SeqCst), 0);
+
+        engine.finish_playback();
+        settle().await;
+        assert_eq!(engine.spoken_texts(), vec!["a", "B"]);
+        assert_eq!(played.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn synthesize_does_not_play() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        tts.speak("playing").await.unwrap();
+        settle().await;
+
+        let audio = tts.synthesize("saved").await.unwrap();
+        settle().await;
+
+        assert!(!audio.data.is_empty());
+        assert_eq!(engine.synthesized()[0].text, "saved");
+        assert_eq!(engine.spoken_texts(), vec!["playing"]);
+        assert_eq!(engine.stop_count(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_playback_completes_the_utterance() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+        let mut events = tts.subscribe();
+
+        tts.speak("broken").await.unwrap();
+        tts.speak("next").await.unwrap();
+        settle().await;
+        engine.fail_playback("device lost");
+        settle().await;
+
+        assert_eq!(engine.spoken_texts(), vec!["broken", "next"]);
+        let received: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter(|e| !matches!(e, SpeechEvent::Boundary(_)))
+            .collect();
+        assert_eq!(
+            received,
+            vec![
+                SpeechEvent::Started,
+                SpeechEvent::Failed("device lost".to_string()),
+                SpeechEvent::Started,
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_clears_the_queue() {
+        let engine = Arc::new(RecordingEngine::new());
+        engine.hold_playback(true);
+        let tts = TTS::new(engine.clone());
+
+        tts.set_state(SpeechState::Running).await.unwrap();
+        tts.speak("one").await.unwrap();
+        tts.speak("two").await.unwrap();
+        settle().await;
+        tts.stop().await.unwrap();
+        settle().await;
+
+        assert_eq!(engine.spoken_texts(), vec!["one"]);
+        assert_eq!(engine.stop_count(), 1);
+        assert!(!engine.is_playing());
     }
 }
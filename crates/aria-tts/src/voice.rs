@@ -0,0 +1,338 @@
+//! Choosing a voice from a short selector string.
+//!
+//! Selectors are used by the `voice` key in aria.toml and by `aria speak --voice`.
+//! They are a comma-separated list of criteria that must all match:
+//!
+//! - `id:<id>` matches [`VoiceInfo::id`] exactly.
+//! - `lang:<tag>` matches a BCP-47 language tag, falling back to less specific
+//!   tags, so `lang:de-AT` picks a German voice when there is no Austrian one.
+//! - `gender:<gender>` matches [`VoiceInfo::gender`], ignoring case.
+//! - `name:<name>`, or just `<name>`, matches the display name ignoring case,
+//!   then by substring, then by edit distance to allow typos.
+//!
+//! For example `lang:de-AT, gender:female` or `Microsoft Zira`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::VoiceInfo;
+use crate::error::TTSError;
+
+/// How many close matches a [`TTSError::VoiceNotFound`] lists.
+const MAX_CLOSE_MATCHES: usize = 3;
+
+/// Criteria a voice has to meet. Empty criteria match every voice.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VoiceSelector {
+    pub id: Option<String>,
+    pub language: Option<String>,
+    pub gender: Option<String>,
+    pub name: Option<String>,
+}
+
+impl FromStr for VoiceSelector {
+    type Err = TTSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut selector = VoiceSelector::default();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let criterion = part
+                .split_once(':')
+                .map(|(key, value)| (key.trim().to_lowercase(), value.trim()));
+            let (field, value) = match criterion {
+                Some((key, value)) if key == "id" => (&mut selector.id, value),
+                Some((key, value)) if key == "lang" || key == "language" => {
+                    (&mut selector.language, value)
+                }
+                Some((key, value)) if key == "gender" => (&mut selector.gender, value),
+                Some((key, value)) if key == "name" => (&mut selector.name, value),
+                // Display names may contain colons themselves.
+                _ => (&mut selector.name, part),
+            };
+            if field.is_some() {
+                return Err(TTSError::InvalidVoiceSelector(format!(
+                    "`{}` sets the same criterion twice",
+                    s
+                )));
+            }
+            *field = Some(value.to_string());
+        }
+        Ok(selector)
+    }
+}
+
+impl fmt::Display for VoiceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [
+            ("id", &self.id),
+            ("lang", &self.language),
+            ("gender", &self.gender),
+            ("name", &self.name),
+        ]
+        .iter()
+        .filter_map(|(key, value)| value.as_ref().map(|v| format!("{}:{}", key, v)))
+        .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl VoiceSelector {
+    /// Pick the voice that best matches every criterion.
+    pub fn select<'a>(&self, voices: &'a [VoiceInfo]) -> crate::error::Result<&'a VoiceInfo> {
+        let mut candidates: Vec<&VoiceInfo> = voices.iter().collect();
+
+        if let Some(id) = &self.id {
+            candidates.retain(|v| &v.id == id);
+        }
+        if let Some(gender) = &self.gender {
+            candidates.retain(|v| v.gender.eq_ignore_ascii_case(gender));
+        }
+        if let Some(language) = &self.language {
+            candidates = best_language_matches(&candidates, language);
+        }
+        if let Some(name) = &self.name {
+            candidates = best_name_matches(&candidates, name);
+        }
+
+        candidates
+            .first()
+            .copied()
+            .ok_or_else(|| TTSError::VoiceNotFound {
+                selector: self.to_string(),
+                close_matches: self.close_matches(voices),
+            })
+    }
+
+    /// Display names of the voices closest to this selector, best first.
+    fn close_matches(&self, voices: &[VoiceInfo]) -> Vec<String> {
+        let target = match (&self.name, &self.id, &self.language) {
+            (Some(name), _, _) => name.to_lowercase(),
+            (None, Some(id), _) => id.to_lowercase(),
+            (None, None, Some(language)) => primary_subtag(language).to_lowercase(),
+            (None, None, None) => return Vec::new(),
+        };
+
+        let mut ranked: Vec<(usize, &VoiceInfo)> = voices
+            .iter()
+            .map(|v| {
+                let distance = if self.name.is_some() {
+                    name_distance(&v.display_name.to_lowercase(), &target)
+                } else if self.id.is_some() {
+                    levenshtein(&v.id.to_lowercase(), &target)
+                } else {
+                    levenshtein(primary_subtag(&v.language.to_lowercase()), &target)
+                };
+                (distance, v)
+            })
+            .collect();
+        ranked.sort_by_key(|(distance, _)| *distance);
+        ranked
+            .into_iter()
+            .take(MAX_CLOSE_MATCHES)
+            .map(|(_, v)| format!("{} ({}, {})", v.display_name, v.language, v.gender))
+            .collect()
+    }
+}
+
+fn primary_subtag(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+/// Voices speaking the most specific form of `language` available: `de-AT`, then `de`.
+fn best_language_matches<'a>(voices: &[&'a VoiceInfo], language: &str) -> Vec<&'a VoiceInfo> {
+    let requested = language.replace('_', "-").to_lowercase();
+    let mut tag = requested.as_str();
+    loop {
+        let matches: Vec<&VoiceInfo> = voices
+            .iter()
+            .copied()
+            .filter(|v| {
+                let voice_tag = v.language.replace('_', "-").to_lowercase();
+                voice_tag == tag || voice_tag.starts_with(&format!("{}-", tag))
+            })
+            .collect();
+        if !matches.is_empty() {
+            return matches;
+        }
+        match tag.rsplit_once('-') {
+            Some((shorter, _)) => tag = shorter,
+            None => return Vec::new(),
+        }
+    }
+}
+
+/// Voices whose display name matches `name`, trying ever looser comparisons.
+fn best_name_matches<'a>(voices: &[&'a VoiceInfo], name: &str) -> Vec<&'a VoiceInfo> {
+    let name = name.to_lowercase();
+    let lower = |v: &VoiceInfo| v.display_name.to_lowercase();
+
+    let exact: Vec<_> = voices
+        .iter()
+        .copied()
+        .filter(|v| lower(v) == name)
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    let partial: Vec<_> = voices
+        .iter()
+        .copied()
+        .filter(|v| lower(v).contains(&name))
+        .collect();
+    if !partial.is_empty() {
+        return partial;
+    }
+
+    // Allow roughly one typo per four characters, and swapped letters in short names.
+    let max_distance = (name.chars().count() / 4).max(2);
+    let mut fuzzy: Vec<(usize, &VoiceInfo)> = voices
+        .iter()
+        .map(|v| (name_distance(&lower(v), &name), *v))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    fuzzy.sort_by_key(|(distance, _)| *distance);
+    fuzzy.into_iter().map(|(_, v)| v).collect()
+}
+
+/// Edit distance from `name` to the closest run of words in the display name, so
+/// that "zria" and "zira desktop" are both close to "Microsoft Zira Desktop".
+fn name_distance(display_name: &str, name: &str) -> usize {
+    let words: Vec<&str> = display_name.split_whitespace().collect();
+    let window = name.split_whitespace().count().clamp(1, words.len().max(1));
+    words
+        .windows(window)
+        .map(|run| levenshtein(&run.join(" "), name))
+        .chain(std::iter::once(levenshtein(display_name, name)))
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(id: &str, display_name: &str, language: &str, gender: &str) -> VoiceInfo {
+        VoiceInfo {
+            id: id.to_string(),
+            display_name: display_name.to_string(),
+            language: language.to_string(),
+            gender: gender.to_string(),
+        }
+    }
+
+    fn voices() -> Vec<VoiceInfo> {
+        vec![
+            voice(
+                "MSTTS_V110_enUS_DavidM",
+                "Microsoft David Desktop",
+                "en-US",
+                "Male",
+            ),
+            voice(
+                "MSTTS_V110_enUS_ZiraM",
+                "Microsoft Zira Desktop",
+                "en-US",
+                "Female",
+            ),
+            voice(
+                "MSTTS_V110_deDE_HeddaM",
+                "Microsoft Hedda Desktop",
+                "de-DE",
+                "Female",
+            ),
+            voice(
+                "MSTTS_V110_deDE_StefanM",
+                "Microsoft Stefan",
+                "de-DE",
+                "Male",
+            ),
+        ]
+    }
+
+    fn select(selector: &str) -> crate::error::Result<String> {
+        let voices = voices();
+        let selector: VoiceSelector = selector.parse()?;
+        selector.select(&voices).map(|v| v.display_name.clone())
+    }
+
+    #[test]
+    fn parses_criteria() {
+        assert_eq!(
+            "lang:de-AT, gender:female"
+                .parse::<VoiceSelector>()
+                .unwrap(),
+            VoiceSelector {
+                language: Some("de-AT".to_string()),
+                gender: Some("female".to_string()),
+                ..VoiceSelector::default()
+            }
+        );
+        assert_eq!(
+            "Voice: Special".parse::<VoiceSelector>().unwrap().name,
+            Some("Voice: Special".to_string())
+        );
+        assert!("lang:de, lang:en".parse::<VoiceSelector>().is_err());
+    }
+
+    #[test]
+    fn selects_by_id_and_exact_name() {
+        assert_eq!(
+            select("id:MSTTS_V110_deDE_StefanM").unwrap(),
+            "Microsoft Stefan"
+        );
+        assert_eq!(
+            select("microsoft zira desktop").unwrap(),
+            "Microsoft Zira Desktop"
+        );
+    }
+
+    #[test]
+    fn language_falls_back_to_primary_subtag() {
+        assert_eq!(select("lang:de-AT").unwrap(), "Microsoft Hedda Desktop");
+        assert_eq!(
+            select("lang:de-AT, gender:male").unwrap(),
+            "Microsoft Stefan"
+        );
+        assert_eq!(
+            select("lang:en_us, gender:Female").unwrap(),
+            "Microsoft Zira Desktop"
+        );
+    }
+
+    #[test]
+    fn names_match_by_substring_and_typo() {
+        assert_eq!(select("Hedda").unwrap(), "Microsoft Hedda Desktop");
+        assert_eq!(select("zria").unwrap(), "Microsoft Zira Desktop");
+        assert_eq!(select("Stefen, lang:de").unwrap(), "Microsoft Stefan");
+    }
+
+    #[test]
+    fn unmatched_selector_lists_close_matches() {
+        match select("Davide Desktop, lang:fr") {
+            Err(TTSError::VoiceNotFound {
+                selector,
+                close_matches,
+            }) => {
+                assert_eq!(selector, "lang:fr, name:Davide Desktop");
+                assert_eq!(close_matches[0], "Microsoft David Desktop (en-US, Male)");
+                assert_eq!(close_matches.len(), MAX_CLOSE_MATCHES);
+            }
+            other => panic!("expected VoiceNotFound, got {:?}", other),
+        }
+    }
+}
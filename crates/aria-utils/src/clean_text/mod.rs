@@ -1,11 +1,15 @@
+use crate::emoji::emoji_name;
 use crate::wide_string::*;
 use regex::*;
 use std::borrow::Cow;
+use std::ops::Range;
 use unicode_segmentation::*;
 
 mod regex_cleaner_pair;
 pub use self::regex_cleaner_pair::*;
 
+pub mod rules;
+
 // // un comment and add #![feature(test)] to main to benchmark
 // #[cfg(test)]
 // mod bench;
@@ -13,7 +17,85 @@ pub use self::regex_cleaner_pair::*;
 #[cfg(test)]
 mod test;
 
-type Pair<'a> = (&'a str, Option<Cow<'a, str>>);
+/// A piece of the text being cleaned and what is spoken for it, or `None` to
+/// speak it as it is.
+pub type Pair<'a> = (&'a str, Option<Cow<'a, str>>);
+
+/// A step of the cleaning pipeline.
+///
+/// Stages run in order. Each one is given the pieces of the text that no stage
+/// before it has replaced, one at a time, and splits a piece into pairs whose
+/// originals, joined, are the piece again. That keeps the index maps of
+/// [`Cleaner`] exact whatever the stages do. Replaced pieces are passed on as they
+/// are, so a later stage never rewrites what an earlier one said.
+///
+/// Regex rules and [`Words`] are stages, and so are closures that return the byte
+/// ranges of a piece to replace, in order, each with what to say instead.
+pub trait CleanerStage {
+    fn clean<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a>;
+}
+
+impl<F> CleanerStage for F
+where
+    F: Fn(&str) -> Vec<(Range<usize>, String)>,
+{
+    fn clean<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+        let replacements = self(text)
+            .into_iter()
+            .map(|(range, replacement)| (range, replacement.into()));
+        Box::new(replace_ranges(text, replacements).into_iter())
+    }
+}
+
+/// Split `text` into pairs, replacing the given ranges and keeping the rest.
+/// Ranges out of order, overlapping one before them, reversed or not on character
+/// boundaries of `text` are logged and skipped.
+fn replace_ranges<'a>(
+    text: &'a str,
+    replacements: impl IntoIterator<Item = (Range<usize>, Cow<'a, str>)>,
+) -> Vec<Pair<'a>> {
+    let mut pairs = Vec::new();
+    let mut kept = 0;
+    for (range, replacement) in replacements {
+        let valid = kept <= range.start
+            && range.start <= range.end
+            && text.is_char_boundary(range.start)
+            && text.is_char_boundary(range.end);
+        if !valid {
+            log::error!(
+                "Skipping replacement of {:?} in {:?}, after {} bytes were handled",
+                range,
+                text,
+                kept
+            );
+            continue;
+        }
+        if kept < range.start {
+            pairs.push((&text[kept..range.start], None));
+        }
+        kept = range.end;
+        pairs.push((&text[range], Some(replacement)));
+    }
+    if kept < text.len() {
+        pairs.push((&text[kept..], None));
+    }
+    pairs
+}
+
+/// A stage that rewrites single words, e.g. by looking them up in a dictionary.
+/// Words the function returns `None` for are kept.
+pub struct Words<F>(pub F);
+
+impl<F: Fn(&str) -> Option<String>> CleanerStage for Words<F> {
+    fn clean<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+        // Kept words are passed on together, so later stages see runs of emoji whole.
+        let replacements = text.split_word_bound_indices().filter_map(|(start, word)| {
+            let replacement = (self.0)(word)?;
+            Some((start..start + word.len(), replacement.into()))
+        });
+        Box::new(replace_ranges(text, replacements).into_iter())
+    }
+}
 
 struct RegexReplace<'r, 'a> {
     text: &'a str,
@@ -135,77 +217,199 @@ where
     }
 }
 
-fn regex_replace<'r, 'a, I>(
-    raw: I,
-    reg: &'a RegexCleanerPair,
-) -> Box<dyn Iterator<Item = Pair<'a>> + 'a>
-where
-    I: 'a + Iterator<Item = Pair<'a>>,
-{
-    let (reg, mut r) = reg.to_parts();
-    if r.no_expansion().is_some() {
-        FlatPair::new_box(raw, move |orig| RegexSubstitute {
-            text: orig,
-            last_match: 0,
-            captures_iter: reg.find_iter(orig),
-            cap: None,
-            rep: r,
-        })
-    } else {
-        FlatPair::new_box(raw, move |orig| RegexReplace {
-            text: orig,
-            last_match: 0,
-            captures_iter: reg.captures_iter(orig),
-            cap: None,
-            rep: r,
-        })
+impl CleanerStage for RegexCleanerPair {
+    fn clean<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+        let (reg, mut r) = self.to_parts();
+        if r.no_expansion().is_some() {
+            Box::new(RegexSubstitute {
+                text,
+                last_match: 0,
+                captures_iter: reg.find_iter(text),
+                cap: None,
+                rep: r,
+            })
+        } else {
+            Box::new(RegexReplace {
+                text,
+                last_match: 0,
+                captures_iter: reg.captures_iter(text),
+                cap: None,
+                rep: r,
+            })
+        }
     }
 }
 
-fn running_count<'a>(st: &mut (&'a str, usize), ch: &'a str) -> Option<Pair<'a>> {
-    if st.0 != ch {
-        st.1 = 0;
-        st.0 = ch;
-    }
-    st.1 += 1;
-    Some((
-        ch,
-        if st.1 < 4 || ch.chars().all(|x| x.is_numeric()) {
-            None
+/// Name the emoji in `orig` and drop repeats of a grapheme beyond the third,
+/// unless it is a digit. A name is set off from the words around it by spaces.
+///
+/// With `collapse_emoji`, a run of the same emoji is named once with its count,
+/// e.g. "thumbs up times 5". That needs the whole run, so the output for a prefix
+/// of `orig` is no longer a prefix of the output for `orig`.
+fn name_graphemes(orig: &str, collapse_emoji: bool) -> Vec<Pair<'_>> {
+    let mut pairs = Vec::new();
+    // Whether the text so far ends in a letter or digit, and whether in a name.
+    let mut after_word = false;
+    let mut after_name = false;
+    let mut graphemes = orig.graphemes(true).peekable();
+    while let Some(ch) = graphemes.next() {
+        let mut run = 1;
+        while graphemes.next_if_eq(&ch).is_some() {
+            run += 1;
+        }
+        let kept = if ch.chars().all(|x| x.is_numeric()) {
+            run
         } else {
-            Some("".into())
-        },
-    ))
+            run.min(3)
+        };
+        match emoji_name(ch) {
+            Some(name) => {
+                let (name, spoken) = if collapse_emoji && run > 1 {
+                    (format!("{} times {}", name, run).into(), 1)
+                } else {
+                    (name, kept)
+                };
+                for i in 0..run {
+                    if i < spoken {
+                        let space = if after_word { " " } else { "" };
+                        pairs.push((ch, Some(format!("{}{}", space, name).into())));
+                        after_word = true;
+                        after_name = true;
+                    } else {
+                        pairs.push((ch, Some("".into())));
+                    }
+                }
+            }
+            None => {
+                let word = ch.chars().any(char::is_alphanumeric);
+                for i in 0..run {
+                    if i >= kept {
+                        pairs.push((ch, Some("".into())));
+                        continue;
+                    }
+                    if after_name && word {
+                        pairs.push((ch, Some(format!(" {}", ch).into())));
+                    } else {
+                        pairs.push((ch, None));
+                    }
+                    after_word = word;
+                    after_name = false;
+                }
+            }
+        }
+    }
+    pairs
 }
 
-fn graphemes_pair<'a, I: 'a + Iterator<Item = Pair<'a>>>(
-    i: I,
+fn trivial_pair<'a>(text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+    Box::new(Some((text, None)).into_iter())
+}
+
+/// The output of a pipeline, with the UTF-16 index maps back to its input.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rewritten {
+    pub text: String,
+    u16idx_in: Vec<usize>,
+    u16idx_out: Vec<usize>,
+}
+
+impl Rewritten {
+    pub fn from_pairs<'a>(pairs: impl IntoIterator<Item = Pair<'a>>) -> Self {
+        let mut rewritten = Rewritten {
+            text: String::new(),
+            u16idx_in: vec![0],
+            u16idx_out: vec![0],
+        };
+        let (mut u16idx_in, mut u16idx_out) = (0, 0);
+        let mut push = |orig: &str, out: &str| {
+            u16idx_in += orig.len_utf16();
+            u16idx_out += out.len_utf16();
+            rewritten.text.push_str(out);
+            rewritten.u16idx_in.push(u16idx_in);
+            rewritten.u16idx_out.push(u16idx_out);
+        };
+        for (orig, replacement) in pairs {
+            match replacement {
+                Some(out) => push(orig, &out),
+                // Kept text maps back character by character.
+                None => {
+                    for (i, c) in orig.char_indices() {
+                        let c = &orig[i..i + c.len_utf8()];
+                        push(c, c);
+                    }
+                }
+            }
+        }
+        rewritten
+    }
+
+    /// Map a UTF-16 range of the output to the UTF-16 range of the input it was
+    /// made from. A range that starts or ends inside a replacement takes in the
+    /// whole replaced input.
+    pub fn input_u16range(&self, range: &Range<usize>) -> Range<usize> {
+        let out = &self.u16idx_out;
+        let last = out.len() - 1;
+        let start = out.partition_point(|&x| x <= range.start) - 1;
+        let end = out.partition_point(|&x| x < range.end).min(last);
+        let (start, end) = (self.u16idx_in[start], self.u16idx_in[end]);
+        start.min(end)..end.max(start)
+    }
+}
+
+/// Run `raw` through `stages` alone, without the emoji naming of [`clean_text`].
+pub fn run_stages<'r: 'a, 'a>(
+    raw: &'a str,
+    stages: impl IntoIterator<Item = &'r dyn CleanerStage>,
 ) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
-    FlatPair::new_box(i, move |orig: &'a str| {
-        orig.graphemes(true).scan(("", 0), running_count)
-    })
+    let mut out = trivial_pair(raw);
+    for stage in stages {
+        out = FlatPair::new_box(out, move |orig| stage.clean(orig));
+    }
+    out
 }
 
-fn trivial_pair<'a>(text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
-    Box::new(Some((text, None)).into_iter())
+/// Run `raw` through `stages`, then through [`name_graphemes`].
+fn pipeline<'r: 'a, 'a>(
+    raw: &'a str,
+    stages: impl Iterator<Item = &'r dyn CleanerStage>,
+    collapse_emoji: bool,
+) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+    FlatPair::new_box(run_stages(raw, stages), move |orig| {
+        name_graphemes(orig, collapse_emoji).into_iter()
+    })
 }
 
 fn clean_iter<'r: 'a, 'a>(
     raw: &'a str,
     list: &'r [RegexCleanerPair],
+    collapse_emoji: bool,
 ) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
-    let mut out = trivial_pair(raw);
-    for reg in list.iter() {
-        out = regex_replace(out, reg);
-    }
-    Box::new(graphemes_pair(out))
+    pipeline(
+        raw,
+        list.iter().map(|reg| reg as &dyn CleanerStage),
+        collapse_emoji,
+    )
 }
 
 pub fn clean_text<'r: 'a, 'a, O>(raw: &'a str, list: &'r [RegexCleanerPair]) -> O
 where
     O: ::std::iter::FromIterator<Cow<'a, str>>,
 {
-    clean_iter(raw, list)
+    clean_text_with(raw, list, false)
+}
+
+/// [`clean_text`], optionally reading a run of the same emoji once with its count.
+/// The index maps below always name every emoji, so only use this for text whose
+/// read position is not followed.
+pub fn clean_text_with<'r: 'a, 'a, O>(
+    raw: &'a str,
+    list: &'r [RegexCleanerPair],
+    collapse_emoji: bool,
+) -> O
+where
+    O: ::std::iter::FromIterator<Cow<'a, str>>,
+{
+    clean_iter(raw, list, collapse_emoji)
         .map(|(o, r)| r.unwrap_or_else(|| o.into()))
         .collect()
 }
@@ -215,13 +419,24 @@ fn clean_text_idx<'r: 'a, 'a, F>(
     len: F,
     list: &'r [RegexCleanerPair],
 ) -> Box<dyn Iterator<Item = (usize, usize)> + 'a>
+where
+    F: 'a + Fn(&str) -> usize,
+{
+    pairs_idx(clean_iter(raw, list, false), len)
+}
+
+/// Where each pair starts and ends in the input and in the output, measured by `len`.
+fn pairs_idx<'a, F>(
+    pairs: Box<dyn Iterator<Item = Pair<'a>> + 'a>,
+    len: F,
+) -> Box<dyn Iterator<Item = (usize, usize)> + 'a>
 where
     F: 'a + Fn(&str) -> usize,
 {
     Box::new(
         (0..1).map(|x| (x, x)).chain(
-            clean_iter(raw, list)
-                .map(move |(o, r)| (len(o), len(&*r.unwrap_or_else(|| o.into()))))
+            pairs
+                .map(move |(o, r)| (len(o), len(&r.unwrap_or_else(|| o.into()))))
                 .scan((0, 0), move |st, x| {
                     st.0 += x.0;
                     st.1 += x.1;
@@ -231,30 +446,107 @@ where
     )
 }
 
-#[allow(dead_code)]
 pub fn clean_text_u8idx_in<T: AsRef<str>>(raw: T, list: &[RegexCleanerPair]) -> Vec<usize> {
     clean_text_idx(raw.as_ref(), LenUtf::len_utf8, list)
         .map(|(s, _)| s)
         .collect()
 }
 
-#[allow(dead_code)]
 pub fn clean_text_u16idx_in<T: AsRef<str>>(raw: T, list: &[RegexCleanerPair]) -> Vec<usize> {
     clean_text_idx(raw.as_ref(), LenUtf::len_utf16, list)
         .map(|(s, _)| s)
         .collect()
 }
 
-#[allow(dead_code)]
 pub fn clean_text_u8idx_out<T: AsRef<str>>(raw: T, list: &[RegexCleanerPair]) -> Vec<usize> {
     clean_text_idx(raw.as_ref(), LenUtf::len_utf8, list)
         .map(|(_, s)| s)
         .collect()
 }
 
-#[allow(dead_code)]
 pub fn clean_text_u16idx_out<T: AsRef<str>>(raw: T, list: &[RegexCleanerPair]) -> Vec<usize> {
     clean_text_idx(raw.as_ref(), LenUtf::len_utf16, list)
         .map(|(_, s)| s)
         .collect()
 }
+
+/// A cleaning pipeline of any [`CleanerStage`]s, ending like [`clean_text`] in
+/// naming emoji and dropping repeated characters.
+#[derive(Default)]
+pub struct Cleaner {
+    stages: Vec<Box<dyn CleanerStage + Send + Sync>>,
+    collapse_emoji: bool,
+}
+
+impl Cleaner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pipeline of [`clean_text`]: the rules of `list`, in order.
+    pub fn from_list(list: &[RegexCleanerPair]) -> Self {
+        list.iter()
+            .cloned()
+            .fold(Self::new(), |cleaner, reg| cleaner.stage(reg))
+    }
+
+    /// Add `stage` after the stages so far.
+    pub fn stage<S: CleanerStage + Send + Sync + 'static>(mut self, stage: S) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Read a run of the same emoji once with its count, as in [`clean_text_with`].
+    /// The index maps follow, mapping the name to the whole run.
+    pub fn collapse_emoji(mut self, collapse_emoji: bool) -> Self {
+        self.collapse_emoji = collapse_emoji;
+        self
+    }
+
+    fn pairs<'a>(&'a self, raw: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+        pipeline(
+            raw,
+            self.stages
+                .iter()
+                .map(|stage| stage.as_ref() as &dyn CleanerStage),
+            self.collapse_emoji,
+        )
+    }
+
+    pub fn clean<'a, O>(&'a self, raw: &'a str) -> O
+    where
+        O: ::std::iter::FromIterator<Cow<'a, str>>,
+    {
+        self.pairs(raw)
+            .map(|(o, r)| r.unwrap_or_else(|| o.into()))
+            .collect()
+    }
+
+    /// Like [`clean_text_u8idx_in`].
+    pub fn u8idx_in(&self, raw: &str) -> Vec<usize> {
+        pairs_idx(self.pairs(raw), LenUtf::len_utf8)
+            .map(|(s, _)| s)
+            .collect()
+    }
+
+    /// Like [`clean_text_u16idx_in`].
+    pub fn u16idx_in(&self, raw: &str) -> Vec<usize> {
+        pairs_idx(self.pairs(raw), LenUtf::len_utf16)
+            .map(|(s, _)| s)
+            .collect()
+    }
+
+    /// Like [`clean_text_u8idx_out`].
+    pub fn u8idx_out(&self, raw: &str) -> Vec<usize> {
+        pairs_idx(self.pairs(raw), LenUtf::len_utf8)
+            .map(|(_, s)| s)
+            .collect()
+    }
+
+    /// Like [`clean_text_u16idx_out`].
+    pub fn u16idx_out(&self, raw: &str) -> Vec<usize> {
+        pairs_idx(self.pairs(raw), LenUtf::len_utf16)
+            .map(|(_, s)| s)
+            .collect()
+    }
+}
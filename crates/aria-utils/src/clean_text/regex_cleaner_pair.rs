@@ -22,10 +22,7 @@ impl RegexCleanerPair {
             .collect()
     }
     pub fn to_parts(&self) -> (&Regex, &str) {
-        let &RegexCleanerPair {
-            regex: ref reg,
-            rep: ref r,
-        } = self;
+        let RegexCleanerPair { regex: reg, rep: r } = self;
         (reg, r)
     }
 }
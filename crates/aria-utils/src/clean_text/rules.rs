@@ -0,0 +1,192 @@
+//! Cleaning rules as written in aria.toml.
+//!
+//! Rules apply in order to focus text before it is spoken. The built-in rules come
+//! first; a rule in aria.toml with the name of a built-in one replaces it in place,
+//! and `enabled = false` turns it off. Other rules follow the built-in ones:
+//!
+//! ```toml
+//! [[cleaning]]
+//! name = "hashes"
+//! enabled = false
+//!
+//! [[cleaning]]
+//! name = "ticket numbers"
+//! pattern = 'JIRA-(\d+)'
+//! replacement = "ticket $1"
+//! comment = "Our tracker prefixes every ticket"
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use super::RegexCleanerPair;
+use crate::error::{ConfigError, Result};
+
+/// One rule of `cleaning` in aria.toml.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CleaningRule {
+    /// Names the rule in errors, and lets it replace the built-in rule of that name.
+    pub name: String,
+    /// A regular expression. May be left out to only turn a built-in rule on or off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Spoken instead of each match. Can refer to groups as `$1` or `$name`.
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default = "CleaningRule::default_enabled")]
+    pub enabled: bool,
+    /// What the rule is for. Not used by Aria.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl CleaningRule {
+    pub fn new<N: Into<String>, P: Into<String>, R: Into<String>>(
+        name: N,
+        pattern: P,
+        replacement: R,
+    ) -> Self {
+        CleaningRule {
+            name: name.into(),
+            pattern: Some(pattern.into()),
+            replacement: replacement.into(),
+            enabled: true,
+            comment: None,
+        }
+    }
+
+    pub fn with_comment<C: Into<String>>(mut self, comment: C) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    fn default_enabled() -> bool {
+        true
+    }
+
+    /// Compile the rule, naming it in the error if its pattern is invalid.
+    pub fn to_pair(&self) -> Result<RegexCleanerPair> {
+        let pattern = self
+            .pattern
+            .as_deref()
+            .ok_or_else(|| ConfigError::CleaningRulePattern {
+                name: self.name.clone(),
+            })?;
+        RegexCleanerPair::new(pattern, self.replacement.clone()).map_err(|source| {
+            ConfigError::CleaningRule {
+                name: self.name.clone(),
+                source,
+            }
+        })
+    }
+}
+
+/// The rules Aria cleans focus text with unless aria.toml says otherwise.
+pub fn default_rules() -> Vec<CleaningRule> {
+    vec![
+        CleaningRule::new("whitespace", r"\s+", " ")
+            .with_comment("Read any run of spaces, tabs and line breaks as one space"),
+        CleaningRule::new(
+            "hashes",
+            r"(?P<s>[0-9a-f]{6})([0-9]+[a-f]|[a-f]+[0-9])[0-9a-f]*",
+            "hash $s",
+        )
+        .with_comment("Shorten commit hashes and similar hex strings to their first six digits"),
+    ]
+}
+
+/// `rules` merged into `defaults`: a rule replaces the default of the same name,
+/// keeping its place, unless it only turns it on or off. The rest are appended.
+pub fn merge_rules(defaults: Vec<CleaningRule>, rules: &[CleaningRule]) -> Vec<CleaningRule> {
+    let mut merged = defaults;
+    for rule in rules {
+        match merged.iter_mut().find(|other| other.name == rule.name) {
+            Some(other) if rule.pattern.is_none() => other.enabled = rule.enabled,
+            Some(other) => *other = rule.clone(),
+            None => merged.push(rule.clone()),
+        }
+    }
+    merged
+}
+
+/// Compile the enabled rules of `rules` merged into the built-in ones, failing on
+/// the first invalid pattern.
+pub fn compile_rules(rules: &[CleaningRule]) -> Result<Vec<RegexCleanerPair>> {
+    merge_rules(default_rules(), rules)
+        .iter()
+        .filter(|rule| rule.enabled)
+        .map(CleaningRule::to_pair)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clean_text::clean_text;
+
+    #[derive(Deserialize)]
+    struct Config {
+        cleaning: Vec<CleaningRule>,
+    }
+
+    fn rules(toml: &str) -> Vec<CleaningRule> {
+        toml::from_str::<Config>(toml).unwrap().cleaning
+    }
+
+    #[test]
+    fn rules_merge_with_the_built_in_ones() {
+        let rules = rules(
+            r#"
+            [[cleaning]]
+            name = "tickets"
+            pattern = 'JIRA-(\d+)'
+            replacement = "ticket $1"
+            comment = "Our tracker"
+
+            [[cleaning]]
+            name = "hashes"
+            enabled = false
+
+            [[cleaning]]
+            name = "whitespace"
+            pattern = '[ \t]+'
+            replacement = " "
+            "#,
+        );
+        let merged = merge_rules(default_rules(), &rules);
+        let names: Vec<_> = merged
+            .iter()
+            .map(|rule| (rule.name.as_str(), rule.enabled))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("whitespace", true), ("hashes", false), ("tickets", true)]
+        );
+        assert_eq!(merged[0].pattern.as_deref(), Some(r"[ \t]+"));
+
+        let list = compile_rules(&rules).unwrap();
+        let cleaned: String = clean_text("Fix  JIRA-42 in\n1a2b3c4d5e6f", &list);
+        assert_eq!(cleaned, "Fix ticket 42 in\n1a2b3c4d5e6f");
+
+        let cleaned: String = clean_text("see 1a2b3c4d5e6f", &compile_rules(&[]).unwrap());
+        assert_eq!(cleaned, "see hash 1a2b3c");
+    }
+
+    #[test]
+    fn errors_name_the_bad_rule() {
+        let bad = [CleaningRule::new("broken", r"(unclosed", "")];
+        let error = compile_rules(&bad).unwrap_err().to_string();
+        assert!(error.contains("cleaning rule `broken`"), "{}", error);
+        assert!(error.contains("(unclosed"), "{}", error);
+
+        let disabled = [CleaningRule {
+            enabled: false,
+            ..bad[0].clone()
+        }];
+        assert!(compile_rules(&disabled).is_ok());
+
+        let error = compile_rules(&rules("[[cleaning]]\nname = \"nothing\""))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("`nothing` has no pattern"), "{}", error);
+    }
+}
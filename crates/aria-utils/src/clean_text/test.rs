@@ -44,6 +44,7 @@ fn one_word_u8idx() {
 }
 
 #[test]
+#[allow(clippy::unnecessary_to_owned)] // deliberately passes an owned `String`
 fn in_string() {
     assert_eq!(clean_text_string("Hello".to_string(), &RE_LIST), "Hello");
 }
@@ -311,7 +312,7 @@ fn quickcheck_clean_text_u8idx() {
 
 fn test_does_not_lose_segments<T: AsRef<str>>(text: T) -> bool {
     let text = text.as_ref();
-    let left_out: String = clean_iter(text, &RE_LIST).map(|(o, _)| o).collect();
+    let left_out: String = clean_iter(text, &RE_LIST, false).map(|(o, _)| o).collect();
     text == left_out
 }
 
@@ -319,3 +320,123 @@ fn test_does_not_lose_segments<T: AsRef<str>>(text: T) -> bool {
 fn quickcheck_does_not_lose_segments() {
     quickcheck(test_does_not_lose_segments as fn(String) -> bool);
 }
+
+#[test]
+fn emoji_are_named() {
+    assert_eq!(
+        clean_text_string("Great job👍! 😂 🇩🇪", &RE_LIST),
+        "Great job thumbs up! face with tears of joy flag D E"
+    );
+    assert_eq!(
+        clean_text_string("ok 👍👍👍👍👍", &RE_LIST),
+        "ok thumbs up thumbs up thumbs up"
+    );
+    assert!(test_clean_text_u8idx("Great job👍🏽! 👨‍👩‍👧 1️⃣"));
+}
+
+#[test]
+fn emoji_runs_collapse() {
+    let collapsed: String = clean_text_with("ok 👍👍👍👍👍 😂😂, 🎉 (🎉)", &RE_LIST, true);
+    assert_eq!(
+        collapsed,
+        "ok thumbs up times 5 face with tears of joy times 2, party popper (party popper)"
+    );
+}
+
+#[test]
+fn custom_stages_join_the_pipeline() {
+    use crate::config::{Language, NumberMode};
+    use crate::normalize::Normalizer;
+
+    let normalizer = Normalizer::new(Language::En, NumberMode::Whole);
+    let cleaner = Cleaner::from_list(&RE_LIST)
+        .stage(Words(|word: &str| {
+            (word == "btn").then(|| "button".to_string())
+        }))
+        // Split camelCase words before each inner capital, e.g. "saveFile" as "save File".
+        .stage(|text: &str| {
+            let chars: Vec<_> = text.char_indices().collect();
+            chars
+                .windows(2)
+                .filter(|pair| pair[0].1.is_lowercase() && pair[1].1.is_uppercase())
+                .map(|pair| {
+                    let (i, c) = pair[1];
+                    (i..i + c.len_utf8(), format!(" {}", c))
+                })
+                .collect()
+        })
+        .stage(Words(move |word: &str| {
+            word.chars()
+                .all(|c| c.is_ascii_digit())
+                .then(|| normalizer.normalize(word).into_owned())
+        }));
+
+    let text = "saveFile  btn 42 👍👍";
+    let cleaned: String = cleaner.clean(text);
+    assert_eq!(cleaned, "save File button forty-two thumbs up thumbs up");
+
+    // Stages run in order and never rewrite what a stage before them said.
+    let cleaned: String = Cleaner::new()
+        .stage(Words(|word: &str| (word == "a").then(|| "b".to_string())))
+        .stage(Words(|word: &str| (word == "b").then(|| "c".to_string())))
+        .clean("a b");
+    assert_eq!(cleaned, "b c");
+
+    let u8idx_in = cleaner.u8idx_in(text);
+    let u8idx_out = cleaner.u8idx_out(text);
+    assert_eq!(u8idx_in.last(), Some(&text.len()));
+    assert_eq!(u8idx_out.last(), Some(&cleaned_len(&cleaner, text)));
+    let at = |input: usize| u8idx_out[u8idx_in.iter().position(|&i| i == input).unwrap()];
+    // "File" starts after "save", "button" after the collapsed spaces.
+    assert_eq!(at(4), 4);
+    assert_eq!(at(10), 10);
+    assert_eq!(at(14), 17);
+    assert_eq!(cleaner.u16idx_in(text).last(), Some(&text.len_utf16()));
+}
+
+#[test]
+fn invalid_ranges_of_a_stage_are_skipped() {
+    let cleaner = Cleaner::new().stage(|_: &str| {
+        vec![
+            (3..5, "e".to_string()),
+            // Before the last range, reversed, inside "ñ" and past the end.
+            (0..1, "x".to_string()),
+            (Range { start: 8, end: 7 }, "x".to_string()),
+            (9..10, "x".to_string()),
+            (11..20, "x".to_string()),
+            (8..10, "n".to_string()),
+        ]
+    });
+    let cleaned: String = cleaner.clean("caf\u{e9} ni\u{f1}o");
+    assert_eq!(cleaned, "cafe nino");
+}
+
+fn cleaned_len(cleaner: &Cleaner, text: &str) -> usize {
+    cleaner.clean::<String>(text).len()
+}
+
+#[test]
+fn collapsed_emoji_keep_the_index_maps_exact() {
+    let cleaner = Cleaner::from_list(&RE_LIST).collapse_emoji(true);
+    let cleaned: String = cleaner.clean("ok 👍👍👍");
+    assert_eq!(cleaned, "ok thumbs up times 3");
+    // The name stands for the whole run.
+    assert_eq!(cleaner.u8idx_in("ok 👍👍👍"), vec![0, 1, 2, 3, 7, 11, 15]);
+    assert_eq!(cleaner.u8idx_out("ok 👍👍👍"), vec![0, 1, 2, 3, 20, 20, 20]);
+    assert_eq!(
+        *cleaner.u16idx_out("ok 👍👍👍").last().unwrap(),
+        cleaned.len_utf16()
+    );
+}
+
+#[test]
+fn rewritten_ranges_map_to_the_input() {
+    let stages: [&dyn CleanerStage; 1] = [&RE_LIST[0]];
+    let rewritten = Rewritten::from_pairs(run_stages("a \t\u{1d565}  b", stages));
+    assert_eq!(rewritten.text, "a \u{1d565} b");
+    assert_eq!(rewritten.input_u16range(&(2..4)), 3..5);
+    assert_eq!(rewritten.input_u16range(&(5..6)), 7..8);
+    // The replaced whitespace, and past the end.
+    assert_eq!(rewritten.input_u16range(&(1..2)), 1..3);
+    assert_eq!(rewritten.input_u16range(&(6..9)), 8..8);
+}
@@ -1,18 +1,270 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use config::Config as ConfigLib;
 use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item, Value};
 
+use crate::clean_text::rules::{compile_rules, CleaningRule};
+use crate::clean_text::RegexCleanerPair;
 use crate::error::{ConfigError, Result};
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+/// Which speech engine Aria talks through.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineKind {
+    /// The synthesizer built into the platform.
+    #[default]
+    Builtin,
+    /// An external program configured in the `[command]` table.
+    Command,
+    /// A local TTS server configured in the `[http]` table.
+    Http,
+}
+
+/// How much punctuation is read out.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PunctuationLevel {
+    None,
+    /// Only symbols that change the meaning of the text, such as `@` or `#`.
+    #[default]
+    Some,
+    Most,
+    All,
+}
+
+/// Language whose conventions are used to read numbers, dates and units and to
+/// find the ends of sentences.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+}
+
+/// How numbers that are not part of a date, time, amount or measurement are read.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberMode {
+    /// As whole numbers, e.g. 1234 as "one thousand two hundred thirty-four".
+    #[default]
+    Whole,
+    /// Digit by digit, e.g. "one two three four".
+    Digits,
+    /// In groups of two digits, e.g. "twelve thirty-four".
+    Groups,
+}
+
+/// A way to set a capital letter apart when it is read on its own. Any
+/// combination can be listed in `capital_indication`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CapitalIndication {
+    /// Say "cap" before the letter.
+    Say,
+    /// Raise the pitch by `capital_pitch_percent`.
+    Pitch,
+    /// Play a short beep.
+    Beep,
+}
+
+/// Encoding of the audio an external TTS program writes to stdout.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// A WAV file, which describes its own sample format.
+    #[default]
+    Wav,
+    /// Headerless little-endian PCM as described by the `sample_rate`,
+    /// `channels` and `bits_per_sample` keys.
+    Raw,
+}
+
+/// A voice offered by an external TTS program.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct CommandVoiceConfig {
+    /// Value substituted for `{voice}` in `voice_args`.
+    pub id: String,
+    pub name: Option<String>,
+    pub language: Option<String>,
+    pub gender: Option<String>,
+}
+
+/// The `[command]` table: an external program that turns text into audio.
+///
+/// `args` and `voice_args` may contain the placeholders `{text}`, `{voice}`,
+/// `{rate}`, `{pitch}` and `{volume}`, and `{rate_percent}`, `{pitch_percent}`
+/// and `{volume_percent}` for the same values multiplied by 100. Unless `{text}`
+/// appears, the text is written to the program's stdin.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct CommandEngineConfig {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Arguments appended after `args` when a voice is selected, e.g. `["-v", "{voice}"]`.
+    #[serde(default)]
+    pub voice_args: Vec<String>,
+    #[serde(default)]
+    pub format: AudioFormat,
+    #[serde(default = "CommandEngineConfig::default_sample_rate")]
+    pub sample_rate: u32,
+    #[serde(default = "CommandEngineConfig::default_channels")]
+    pub channels: u16,
+    #[serde(default = "CommandEngineConfig::default_bits_per_sample")]
+    pub bits_per_sample: u16,
+    #[serde(default)]
+    pub voices: Vec<CommandVoiceConfig>,
+}
+
+impl CommandEngineConfig {
+    /// A program with no arguments that writes WAV to stdout.
+    pub fn new<P: Into<String>>(program: P) -> Self {
+        CommandEngineConfig {
+            program: program.into(),
+            args: Vec::new(),
+            voice_args: Vec::new(),
+            format: AudioFormat::default(),
+            sample_rate: Self::default_sample_rate(),
+            channels: Self::default_channels(),
+            bits_per_sample: Self::default_bits_per_sample(),
+            voices: Vec::new(),
+        }
+    }
+
+    fn default_sample_rate() -> u32 {
+        22_050
+    }
+
+    fn default_channels() -> u16 {
+        1
+    }
+
+    fn default_bits_per_sample() -> u16 {
+        16
+    }
+}
+
+/// The `[http]` table: a TTS server with an OpenTTS-style HTTP API.
+///
+/// Text is POSTed to `url` with `voice`, `rate`, `pitch` and `volume` as query
+/// parameters, and the server answers with a WAV file.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct HttpEngineConfig {
+    /// Synthesis endpoint, e.g. `http://localhost:5500/api/tts`.
+    pub url: String,
+    /// Endpoint listing the server's voices, e.g. `http://localhost:5500/api/voices`.
+    pub voices_url: Option<String>,
+    /// How long to wait for the server before giving up on an utterance.
+    #[serde(default = "HttpEngineConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Speak through the built-in engine when the server does not answer in time.
+    #[serde(default = "HttpEngineConfig::default_fallback")]
+    pub fallback: bool,
+}
+
+impl HttpEngineConfig {
+    pub fn new<U: Into<String>>(url: U) -> Self {
+        HttpEngineConfig {
+            url: url.into(),
+            voices_url: None,
+            timeout_ms: Self::default_timeout_ms(),
+            fallback: Self::default_fallback(),
+        }
+    }
+
+    fn default_timeout_ms() -> u64 {
+        3_000
+    }
+
+    fn default_fallback() -> bool {
+        true
+    }
+}
+
+/// A table under `[profiles]`: settings that apply while a matching application
+/// has focus, in place of the ones at the top of aria.toml.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct AppProfile {
+    /// Executables the profile is for, e.g. `code.exe`, matched ignoring case.
+    #[serde(default)]
+    pub processes: Vec<String>,
+    /// Classes of foreground windows the profile is for, e.g. `ConsoleWindowClass`.
+    #[serde(default)]
+    pub window_classes: Vec<String>,
+    /// Cleaning rules merged over the global ones, like those over the built-in ones.
+    #[serde(default)]
+    pub cleaning: Vec<CleaningRule>,
+    pub speech_rate: Option<f64>,
+    /// Voice selector, e.g. `Zira` or `lang:de-AT, gender:female`.
+    pub voice: Option<String>,
+    pub punctuation: Option<PunctuationLevel>,
+    pub key_echo: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct AriaConfig {
     pub speech_rate: f64,
     pub pitch: f64,
+    pub volume: f64,
     pub append_silence: bool,
     pub punctuation_silence: bool,
     pub startup_shutdown_sounds: bool,
+    /// Voice selector, e.g. `Zira` or `lang:de-AT, gender:female`.
     pub voice: Option<String>,
+    pub punctuation: PunctuationLevel,
+    pub language: Language,
+    pub number_mode: NumberMode,
+    /// Read a run of the same emoji once with its count, e.g. "thumbs up times 5".
+    pub collapse_emoji: bool,
+    /// Say the keys typed into input fields.
+    pub key_echo: bool,
+    /// How capital letters are set apart in key echo and when spelling.
+    pub capital_indication: Vec<CapitalIndication>,
+    /// How much higher capital letters are said with [`CapitalIndication::Pitch`].
+    pub capital_pitch_percent: f64,
+    pub engine: EngineKind,
+    /// Memory for recently synthesized utterances, in megabytes. 0 turns the cache off.
+    pub cache_size_mb: u64,
+    /// Only utterances up to this many characters are cached.
+    pub cache_max_chars: u64,
+    /// How many utterances the speech history keeps for review.
+    pub history_size: u64,
+    /// Rules that clean focus text before it is spoken, merged with the built-in
+    /// ones, see [`crate::clean_text::rules`].
+    #[serde(default)]
+    pub cleaning: Vec<CleaningRule>,
+    /// Voice selector for each language of text, e.g. `de = "lang:de-DE"`. Every
+    /// sentence is spoken with the voice for its language, or the default voice.
+    #[serde(default)]
+    pub language_voices: BTreeMap<String, String>,
+    /// Say the name of the profile whenever focus moves to an application with
+    /// another profile.
+    #[serde(default)]
+    pub announce_profile: bool,
+    /// Settings for particular applications, by profile name.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, AppProfile>,
+    /// Settings of the `command` engine.
+    pub command: Option<CommandEngineConfig>,
+    /// Settings of the `http` engine.
+    pub http: Option<HttpEngineConfig>,
+}
+
+impl AriaConfig {
+    /// The cleaning rules in effect, compiled in the order they apply.
+    pub fn clean_list(&self) -> Result<Vec<RegexCleanerPair>> {
+        compile_rules(&self.cleaning)
+    }
+
+    /// The cleaning rules in effect while `profile` is active.
+    pub fn profile_clean_list(&self, profile: &AppProfile) -> Result<Vec<RegexCleanerPair>> {
+        compile_rules(&[self.cleaning.as_slice(), &profile.cleaning].concat())
+    }
 }
 
 impl Default for AriaConfig {
@@ -20,10 +272,28 @@ impl Default for AriaConfig {
         AriaConfig {
             speech_rate: 1.0,
             pitch: 1.0,
+            volume: 1.0,
             append_silence: true,
             punctuation_silence: true,
             startup_shutdown_sounds: true,
             voice: None,
+            punctuation: PunctuationLevel::default(),
+            language: Language::default(),
+            number_mode: NumberMode::default(),
+            collapse_emoji: true,
+            key_echo: true,
+            capital_indication: vec![CapitalIndication::Pitch],
+            capital_pitch_percent: 30.0,
+            engine: EngineKind::default(),
+            cache_size_mb: 16,
+            cache_max_chars: 200,
+            history_size: 500,
+            cleaning: Vec::new(),
+            language_voices: BTreeMap::new(),
+            announce_profile: false,
+            profiles: BTreeMap::new(),
+            command: None,
+            http: None,
         }
     }
 }
@@ -42,16 +312,60 @@ pub fn get_config_path() -> Result<PathBuf> {
 }
 
 pub fn create_default_config(path: &PathBuf) -> Result<()> {
+    write_config(path, &AriaConfig::default())
+}
+
+/// Write `config` to `path`, replacing whatever the file contained.
+pub fn write_config(path: &PathBuf, config: &AriaConfig) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    let default_config = AriaConfig::default();
-    let toml_string = toml::to_string(&default_config)?;
+    let toml_string = toml::to_string(config)?;
 
     fs::write(path, toml_string)?;
     Ok(())
 }
 
+/// Write the speech rate, pitch, volume, punctuation level and voice of `config`
+/// to the file at `path`, keeping everything else in it as it is, comments
+/// included. Without a voice, the voice in the file is kept too.
+pub fn write_speech_settings(path: &Path, config: &AriaConfig) -> Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut document: DocumentMut = text.parse()?;
+    set_value(&mut document, "speech_rate", config.speech_rate.into());
+    set_value(&mut document, "pitch", config.pitch.into());
+    set_value(&mut document, "volume", config.volume.into());
+    if let toml::Value::String(punctuation) = toml::Value::try_from(config.punctuation)? {
+        set_value(&mut document, "punctuation", punctuation.into());
+    }
+    if let Some(voice) = &config.voice {
+        set_value(&mut document, "voice", voice.into());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Set the top-level `key` of `document`, keeping the comment after its old value.
+fn set_value(document: &mut DocumentMut, key: &str, mut value: Value) {
+    if let Some(old) = document.get(key).and_then(Item::as_value) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    document[key] = Item::Value(value);
+}
+
+/// Save the settings changed at runtime to aria.toml, as [`write_speech_settings`].
+pub fn save_speech_settings(config: &AriaConfig) -> Result<()> {
+    write_speech_settings(&get_config_path()?, config)
+}
+
 pub fn get_config() -> Result<AriaConfig> {
     let config_path = get_config_path()?;
 
@@ -68,6 +382,7 @@ pub fn get_config() -> Result<AriaConfig> {
         .add_source(config::Environment::with_prefix("ARIA"))
         .set_default("speech_rate", AriaConfig::default().speech_rate)?
         .set_default("pitch", AriaConfig::default().pitch)?
+        .set_default("volume", AriaConfig::default().volume)?
         .set_default("append_silence", AriaConfig::default().append_silence)?
         .set_default(
             "punctuation_silence",
@@ -78,7 +393,68 @@ pub fn get_config() -> Result<AriaConfig> {
             AriaConfig::default().startup_shutdown_sounds,
         )?
         .set_default("voice", AriaConfig::default().voice)?
+        .set_default("punctuation", "some")?
+        .set_default("language", "en")?
+        .set_default("number_mode", "whole")?
+        .set_default("collapse_emoji", AriaConfig::default().collapse_emoji)?
+        .set_default("key_echo", AriaConfig::default().key_echo)?
+        .set_default("capital_indication", vec!["pitch"])?
+        .set_default(
+            "capital_pitch_percent",
+            AriaConfig::default().capital_pitch_percent,
+        )?
+        .set_default("engine", "builtin")?
+        .set_default("cache_size_mb", AriaConfig::default().cache_size_mb)?
+        .set_default("cache_max_chars", AriaConfig::default().cache_max_chars)?
+        .set_default("history_size", AriaConfig::default().history_size)?
         .build()?;
 
-    Ok(settings.try_deserialize::<AriaConfig>()?)
+    let config = settings.try_deserialize::<AriaConfig>()?;
+    config.clean_list()?;
+    for profile in config.profiles.values() {
+        config.profile_clean_list(profile)?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speech_settings_keep_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aria.toml");
+        let original = r#"# Faster than the default
+speech_rate = 1.5 # words per minute would be nicer
+voice = "Zira"
+
+[http]
+url = "http://localhost:5002/api/tts"
+"#;
+        fs::write(&path, original).unwrap();
+
+        let config = AriaConfig {
+            speech_rate: 2.0,
+            punctuation: PunctuationLevel::Most,
+            ..AriaConfig::default()
+        };
+        write_speech_settings(&path, &config).unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(
+            written.starts_with("# Faster than the default\n"),
+            "{}",
+            written
+        );
+        assert!(
+            written.contains("speech_rate = 2.0 # words per minute would be nicer\n"),
+            "{}",
+            written
+        );
+        assert!(written.contains("voice = \"Zira\""));
+        assert!(written.contains("punctuation = \"most\""));
+        assert!(written.contains("[http]\nurl = \"http://localhost:5002/api/tts\""));
+        assert!(written.contains("volume = 1.0\n"));
+    }
 }
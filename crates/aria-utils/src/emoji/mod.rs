@@ -0,0 +1,93 @@
+//! Spoken names of emoji and pictographic symbols.
+//!
+//! Names come from the CLDR annotations embedded in `annotations.txt`. Sequences
+//! without an entry of their own are named from their parts, so a thumbs up with a
+//! skin tone is "thumbs up medium skin tone", a keycap is "keycap 1" and a pair of
+//! regional indicators is "flag U S". The colons and commas CLDR puts in sequence
+//! names are left out, since they would be spoken at the higher punctuation levels.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+#[cfg(test)]
+mod test;
+
+const ZWJ: char = '\u{200D}';
+const KEYCAP: &str = "\u{20E3}";
+const SKIN_TONES: std::ops::RangeInclusive<char> = '\u{1F3FB}'..='\u{1F3FF}';
+const REGIONAL_INDICATORS: std::ops::RangeInclusive<char> = '\u{1F1E6}'..='\u{1F1FF}';
+
+static ANNOTATIONS: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    include_str!("annotations.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (code_points, name) = line.split_once(';')?;
+            let key = code_points
+                .split_whitespace()
+                .map(|hex| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32))
+                .collect::<Option<String>>()?;
+            Some((key, name.trim().replace([':', ','], "")))
+        })
+        .collect()
+});
+
+/// Whether `c` only selects how the character before it is drawn.
+fn is_variation_selector(c: char) -> bool {
+    matches!(c, '\u{FE0E}' | '\u{FE0F}')
+}
+
+/// The spoken name of `grapheme` if it is an emoji or a symbol in the table.
+pub fn emoji_name(grapheme: &str) -> Option<Cow<'static, str>> {
+    let key: String = grapheme
+        .chars()
+        .filter(|c| !is_variation_selector(*c))
+        .collect();
+    if let Some(name) = ANNOTATIONS.get(&key) {
+        return Some(Cow::Borrowed(name.as_str()));
+    }
+    compose(&key).map(Cow::Owned)
+}
+
+/// Name a sequence without an entry of its own from its parts.
+fn compose(key: &str) -> Option<String> {
+    let mut chars = key.chars();
+    let first = chars.next()?;
+    if REGIONAL_INDICATORS.contains(&first) {
+        let letters = key
+            .chars()
+            .map(|c| {
+                REGIONAL_INDICATORS
+                    .contains(&c)
+                    .then(|| char::from(b'A' + (c as u32 - 0x1F1E6) as u8))
+            })
+            .collect::<Option<Vec<char>>>()?;
+        return match letters[..] {
+            [a, b] => Some(format!("flag {} {}", a, b)),
+            _ => None,
+        };
+    }
+    if chars.as_str() == KEYCAP {
+        return Some(format!("keycap {}", first));
+    }
+    if !key.contains(ZWJ) && !key.chars().any(|c| SKIN_TONES.contains(&c)) {
+        return None;
+    }
+
+    // Skin tones follow the name of the whole sequence, which is either in the
+    // table or made of the names of the parts joined by ZWJ.
+    let base: String = key.chars().filter(|c| !SKIN_TONES.contains(c)).collect();
+    let mut words = match ANNOTATIONS.get(&base) {
+        Some(name) => vec![name.as_str()],
+        None => base
+            .split(ZWJ)
+            .map(|part| ANNOTATIONS.get(part).map(String::as_str))
+            .collect::<Option<Vec<_>>>()?,
+    };
+    for tone in key.chars().filter(|c| SKIN_TONES.contains(c)) {
+        words.push(ANNOTATIONS.get(&tone.to_string())?.as_str());
+    }
+    Some(words.join(" "))
+}
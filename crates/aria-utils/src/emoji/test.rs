@@ -0,0 +1,69 @@
+use super::*;
+
+fn name(grapheme: &str) -> Option<String> {
+    emoji_name(grapheme).map(Cow::into_owned)
+}
+
+#[test]
+fn single_emoji_and_symbols() {
+    assert_eq!(name("👍").as_deref(), Some("thumbs up"));
+    assert_eq!(name("😂").as_deref(), Some("face with tears of joy"));
+    assert_eq!(name("→").as_deref(), Some("rightwards arrow"));
+    assert_eq!(name("≠").as_deref(), Some("not equal to"));
+    assert_eq!(name("a"), None);
+    assert_eq!(name("1"), None);
+}
+
+#[test]
+fn variation_selectors_are_ignored() {
+    assert_eq!(name("❤\u{FE0F}").as_deref(), Some("red heart"));
+    assert_eq!(name("❤").as_deref(), Some("red heart"));
+    assert_eq!(name("☺\u{FE0E}").as_deref(), Some("smiling face"));
+}
+
+#[test]
+fn skin_tones_follow_the_name() {
+    assert_eq!(name("👍🏽").as_deref(), Some("thumbs up medium skin tone"));
+    assert_eq!(
+        name("👩🏿‍💻").as_deref(),
+        Some("woman technologist dark skin tone")
+    );
+    assert_eq!(name("🏻").as_deref(), Some("light skin tone"));
+}
+
+#[test]
+fn zwj_sequences() {
+    assert_eq!(name("👨‍👩‍👧").as_deref(), Some("family man woman girl"));
+    assert_eq!(name("🏳️‍🌈").as_deref(), Some("rainbow flag"));
+    assert_eq!(name("👩‍🦰").as_deref(), Some("woman red hair"));
+    // Not in the table, so named from its parts.
+    assert_eq!(name("🐻‍❄️").as_deref(), Some("bear snowflake"));
+    assert_eq!(name("👩‍🪿"), None);
+}
+
+#[test]
+fn keycaps_and_flags() {
+    assert_eq!(name("1\u{FE0F}\u{20E3}").as_deref(), Some("keycap 1"));
+    assert_eq!(name("🇩🇪").as_deref(), Some("flag D E"));
+    assert_eq!(name("🏴󠁧󠁢󠁳󠁣󠁴󠁿").as_deref(), Some("flag Scotland"));
+}
+
+#[test]
+fn every_line_of_the_table_is_read() {
+    let lines = include_str!("annotations.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .count();
+    assert_eq!(ANNOTATIONS.len(), lines);
+    for (key, name) in ANNOTATIONS.iter() {
+        assert!(!name.is_empty(), "{:?}", key);
+        assert!(!key.chars().any(is_variation_selector), "{:?}", name);
+        assert!(
+            crate::symbols::SYMBOLS
+                .iter()
+                .all(|s| key != &s.symbol.to_string()),
+            "{} is named by the punctuation level",
+            name
+        );
+    }
+}
@@ -10,6 +10,21 @@ pub enum ConfigError {
     #[error("TOML serialization error: {0}")]
     TomlSer(#[from] toml::ser::Error),
 
+    #[error("TOML parse error: {0}")]
+    TomlDe(#[from] toml::de::Error),
+
+    #[error("TOML parse error: {0}")]
+    TomlEdit(#[from] toml_edit::TomlError),
+
+    #[error("Invalid pattern: {0}")]
+    Regex(#[from] regex::Error),
+
+    #[error("Invalid pattern in cleaning rule `{name}`: {source}")]
+    CleaningRule { name: String, source: regex::Error },
+
+    #[error("Cleaning rule `{name}` has no pattern and replaces no built-in rule")]
+    CleaningRulePattern { name: String },
+
     #[error("Config library error: {0}")]
     Lib(#[from] LibConfigError),
 
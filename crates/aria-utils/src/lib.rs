@@ -1,4 +1,10 @@
 pub mod clean_text;
 pub mod config;
+pub mod emoji;
 pub mod error;
+pub mod normalize;
+pub mod pronunciation;
+pub mod sentences;
+pub mod spell;
+pub mod symbols;
 pub mod wide_string;
@@ -0,0 +1,293 @@
+use super::{Amount, Currency, Locale, Unit};
+
+const ONES: [&str; 20] = [
+    "null",
+    "eins",
+    "zwei",
+    "drei",
+    "vier",
+    "fünf",
+    "sechs",
+    "sieben",
+    "acht",
+    "neun",
+    "zehn",
+    "elf",
+    "zwölf",
+    "dreizehn",
+    "vierzehn",
+    "fünfzehn",
+    "sechzehn",
+    "siebzehn",
+    "achtzehn",
+    "neunzehn",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "zwanzig", "dreißig", "vierzig", "fünfzig", "sechzig", "siebzig", "achtzig", "neunzig",
+];
+
+/// Scales above a thousand, which are nouns with a singular and a plural.
+const SCALES: [(u64, &str, &str); 5] = [
+    (1_000_000_000_000_000_000, "Trillion", "Trillionen"),
+    (1_000_000_000_000_000, "Billiarde", "Billiarden"),
+    (1_000_000_000_000, "Billion", "Billionen"),
+    (1_000_000_000, "Milliarde", "Milliarden"),
+    (1_000_000, "Million", "Millionen"),
+];
+
+/// `n` below a thousand. A trailing one is "ein" where a word follows, as in
+/// "einundzwanzig" or "eintausend", and "eins" at the end.
+fn below_thousand(n: u64, compound: bool) -> String {
+    let one = if compound { "ein" } else { "eins" };
+    let mut words = String::new();
+    if n >= 100 {
+        match n / 100 {
+            1 => words.push_str("ein"),
+            hundreds => words.push_str(ONES[hundreds as usize]),
+        }
+        words.push_str("hundert");
+    }
+    match n % 100 {
+        0 if n == 0 => words.push_str(ONES[0]),
+        0 => {}
+        1 => words.push_str(one),
+        rest @ 2..=19 => words.push_str(ONES[rest as usize]),
+        rest => {
+            match rest % 10 {
+                0 => {}
+                1 => words.push_str("einund"),
+                ones => {
+                    words.push_str(ONES[ones as usize]);
+                    words.push_str("und");
+                }
+            }
+            words.push_str(TENS[(rest / 10) as usize]);
+        }
+    }
+    words
+}
+
+/// Years from 1100 to 1999 are read in hundreds, e.g. 1989 as
+/// "neunzehnhundertneunundachtzig".
+fn year(year: u64) -> String {
+    match year {
+        1100..=1999 => format!(
+            "{}hundert{}",
+            German.cardinal(year / 100),
+            match year % 100 {
+                0 => String::new(),
+                rest => German.cardinal(rest),
+            }
+        ),
+        _ => German.cardinal(year),
+    }
+}
+
+/// The singular and plural name of a unit, and whether it is feminine.
+fn unit_names(unit: Unit) -> (&'static str, &'static str, bool) {
+    match unit {
+        Unit::KilometersPerHour => ("Kilometer pro Stunde", "Kilometer pro Stunde", false),
+        Unit::MilesPerHour => ("Meile pro Stunde", "Meilen pro Stunde", true),
+        Unit::Kilometers => ("Kilometer", "Kilometer", false),
+        Unit::Centimeters => ("Zentimeter", "Zentimeter", false),
+        Unit::Millimeters => ("Millimeter", "Millimeter", false),
+        Unit::Meters => ("Meter", "Meter", false),
+        Unit::Kilograms => ("Kilogramm", "Kilogramm", false),
+        Unit::Milligrams => ("Milligramm", "Milligramm", false),
+        Unit::Grams => ("Gramm", "Gramm", false),
+        Unit::Milliliters => ("Milliliter", "Milliliter", false),
+        Unit::Liters => ("Liter", "Liter", false),
+        Unit::Celsius => ("Grad Celsius", "Grad Celsius", false),
+        Unit::Fahrenheit => ("Grad Fahrenheit", "Grad Fahrenheit", false),
+        Unit::Kilobytes => ("Kilobyte", "Kilobyte", false),
+        Unit::Megabytes => ("Megabyte", "Megabyte", false),
+        Unit::Gigabytes => ("Gigabyte", "Gigabyte", false),
+        Unit::Terabytes => ("Terabyte", "Terabyte", false),
+        Unit::Gigahertz => ("Gigahertz", "Gigahertz", false),
+        Unit::Megahertz => ("Megahertz", "Megahertz", false),
+        Unit::Kilohertz => ("Kilohertz", "Kilohertz", false),
+        Unit::Hertz => ("Hertz", "Hertz", false),
+        Unit::Milliseconds => ("Millisekunde", "Millisekunden", true),
+        Unit::Seconds => ("Sekunde", "Sekunden", true),
+        Unit::Minutes => ("Minute", "Minuten", true),
+        Unit::Hours => ("Stunde", "Stunden", true),
+        Unit::Percent => ("Prozent", "Prozent", false),
+    }
+}
+
+pub(crate) struct German;
+
+impl German {
+    /// `amount` followed by a noun, so a single one agrees with it.
+    fn count(&self, amount: &Amount, noun: &str, feminine: bool) -> String {
+        match amount.is_one() {
+            true if feminine => format!("eine {}", noun),
+            true => format!("ein {}", noun),
+            false => format!("{} {}", self.amount(amount), noun),
+        }
+    }
+}
+
+impl Locale for German {
+    fn thousands_separator(&self) -> char {
+        '.'
+    }
+
+    fn decimal_separator(&self) -> char {
+        ','
+    }
+
+    fn months(&self) -> [&'static str; 12] {
+        [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ]
+    }
+
+    fn cardinal(&self, n: u64) -> String {
+        let mut words = Vec::new();
+        let mut rest = n;
+        for (scale, one, many) in SCALES {
+            if rest >= scale {
+                let count = rest / scale;
+                words.push(match count {
+                    1 => format!("eine {}", one),
+                    count => format!("{} {}", below_thousand(count, false), many),
+                });
+                rest %= scale;
+            }
+        }
+        // Thousands and what follows them are written as one word.
+        let mut word = String::new();
+        if rest >= 1000 {
+            word.push_str(&below_thousand(rest / 1000, true));
+            word.push_str("tausend");
+            rest %= 1000;
+        }
+        if rest > 0 || (word.is_empty() && words.is_empty()) {
+            word.push_str(&below_thousand(rest, false));
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+        words.join(" ")
+    }
+
+    fn ordinal(&self, n: u64) -> String {
+        let rest = n % 100;
+        if !(1..=19).contains(&rest) {
+            return format!("{}ste", self.cardinal(n));
+        }
+        let start = match n - rest {
+            0 => String::new(),
+            hundreds => self.cardinal(hundreds),
+        };
+        let end = match rest {
+            1 => "erste".to_string(),
+            3 => "dritte".to_string(),
+            7 => "siebte".to_string(),
+            8 => "achte".to_string(),
+            rest => format!("{}te", ONES[rest as usize]),
+        };
+        start + &end
+    }
+
+    fn minus(&self) -> &'static str {
+        "minus"
+    }
+
+    fn point(&self) -> &'static str {
+        "Komma"
+    }
+
+    fn date(&self, year: Option<u64>, month: u64, day: u64) -> String {
+        let month = self.months()[month as usize - 1];
+        match year {
+            Some(year) => format!("{}r {} {}", self.ordinal(day), month, self::year(year)),
+            None => format!("{}r {}", self.ordinal(day), month),
+        }
+    }
+
+    fn time(&self, hour: u64, minute: u64, second: Option<u64>, pm: Option<bool>) -> String {
+        let mut words = vec![match hour {
+            1 => "ein Uhr".to_string(),
+            hour => format!("{} Uhr", self.cardinal(hour)),
+        }];
+        if minute > 0 {
+            words.push(self.cardinal(minute));
+        }
+        if let Some(second) = second {
+            let seconds = Amount {
+                negative: false,
+                integer: second.to_string(),
+                fraction: None,
+            };
+            let unit = if second == 1 { "Sekunde" } else { "Sekunden" };
+            words.push(format!("und {}", self.count(&seconds, unit, true)));
+        }
+        match pm {
+            Some(true) => words.push("nachmittags".to_string()),
+            Some(false) => words.push("vormittags".to_string()),
+            None => {}
+        }
+        words.join(" ")
+    }
+
+    fn money(&self, amount: &Amount, currency: Currency) -> String {
+        let (unit, cent, cents) = match currency {
+            Currency::Dollar => ("Dollar", "Cent", "Cent"),
+            Currency::Euro => ("Euro", "Cent", "Cent"),
+            Currency::Pound => ("Pfund", "Penny", "Pence"),
+            Currency::Yen => ("Yen", "Sen", "Sen"),
+        };
+        let whole = Amount {
+            fraction: None,
+            ..amount.clone()
+        };
+        // Only amounts with one or two decimals are read as cents.
+        let hundredths = match &amount.fraction {
+            None => None,
+            Some(fraction) if fraction.len() <= 2 => Some(format!("{:0<2}", fraction)),
+            Some(_) => return format!("{} {}", self.amount(amount), unit),
+        };
+        match hundredths.filter(|cents| cents != "00") {
+            None => self.count(&whole, unit, false),
+            Some(hundredths) => {
+                let hundredths = Amount {
+                    negative: false,
+                    integer: hundredths,
+                    fraction: None,
+                };
+                if whole.value() == Some(0) {
+                    let sign = if amount.negative { "minus " } else { "" };
+                    let name = if hundredths.is_one() { cent } else { cents };
+                    format!("{}{}", sign, self.count(&hundredths, name, false))
+                } else {
+                    // "fünf Euro fünfzig", without naming the cents.
+                    format!(
+                        "{} {}",
+                        self.count(&whole, unit, false),
+                        self.amount(&hundredths)
+                    )
+                }
+            }
+        }
+    }
+
+    fn measure(&self, amount: &Amount, unit: Unit) -> String {
+        let (one, many, feminine) = unit_names(unit);
+        let name = if amount.is_one() { one } else { many };
+        self.count(amount, name, feminine)
+    }
+}
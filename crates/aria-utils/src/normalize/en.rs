@@ -0,0 +1,259 @@
+use super::{Amount, Currency, Locale, Unit};
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const SCALES: [(u64, &str); 6] = [
+    (1_000_000_000_000_000_000, "quintillion"),
+    (1_000_000_000_000_000, "quadrillion"),
+    (1_000_000_000_000, "trillion"),
+    (1_000_000_000, "billion"),
+    (1_000_000, "million"),
+    (1_000, "thousand"),
+];
+
+fn below_thousand(n: u64) -> String {
+    let mut words = Vec::new();
+    if n >= 100 {
+        words.push(format!("{} hundred", ONES[(n / 100) as usize]));
+    }
+    let rest = n % 100;
+    if rest >= 20 {
+        match rest % 10 {
+            0 => words.push(TENS[(rest / 10) as usize].to_string()),
+            ones => words.push(format!(
+                "{}-{}",
+                TENS[(rest / 10) as usize],
+                ONES[ones as usize]
+            )),
+        }
+    } else if rest > 0 || n == 0 {
+        words.push(ONES[rest as usize].to_string());
+    }
+    words.join(" ")
+}
+
+/// Read years in pairs, e.g. 1984 as "nineteen eighty-four".
+fn year(year: u64) -> String {
+    match year {
+        2000..=2009 => English.cardinal(year),
+        1000..=9999 if year.is_multiple_of(100) => {
+            format!("{} hundred", English.cardinal(year / 100))
+        }
+        1000..=9999 if year % 100 < 10 => format!(
+            "{} oh {}",
+            English.cardinal(year / 100),
+            ONES[(year % 100) as usize]
+        ),
+        1000..=9999 => format!(
+            "{} {}",
+            English.cardinal(year / 100),
+            English.cardinal(year % 100)
+        ),
+        _ => English.cardinal(year),
+    }
+}
+
+/// The singular and plural name of a unit.
+fn unit_names(unit: Unit) -> (&'static str, &'static str) {
+    match unit {
+        Unit::KilometersPerHour => ("kilometer per hour", "kilometers per hour"),
+        Unit::MilesPerHour => ("mile per hour", "miles per hour"),
+        Unit::Kilometers => ("kilometer", "kilometers"),
+        Unit::Centimeters => ("centimeter", "centimeters"),
+        Unit::Millimeters => ("millimeter", "millimeters"),
+        Unit::Meters => ("meter", "meters"),
+        Unit::Kilograms => ("kilogram", "kilograms"),
+        Unit::Milligrams => ("milligram", "milligrams"),
+        Unit::Grams => ("gram", "grams"),
+        Unit::Milliliters => ("milliliter", "milliliters"),
+        Unit::Liters => ("liter", "liters"),
+        Unit::Celsius => ("degree Celsius", "degrees Celsius"),
+        Unit::Fahrenheit => ("degree Fahrenheit", "degrees Fahrenheit"),
+        Unit::Kilobytes => ("kilobyte", "kilobytes"),
+        Unit::Megabytes => ("megabyte", "megabytes"),
+        Unit::Gigabytes => ("gigabyte", "gigabytes"),
+        Unit::Terabytes => ("terabyte", "terabytes"),
+        Unit::Gigahertz => ("gigahertz", "gigahertz"),
+        Unit::Megahertz => ("megahertz", "megahertz"),
+        Unit::Kilohertz => ("kilohertz", "kilohertz"),
+        Unit::Hertz => ("hertz", "hertz"),
+        Unit::Milliseconds => ("millisecond", "milliseconds"),
+        Unit::Seconds => ("second", "seconds"),
+        Unit::Minutes => ("minute", "minutes"),
+        Unit::Hours => ("hour", "hours"),
+        Unit::Percent => ("percent", "percent"),
+    }
+}
+
+pub(crate) struct English;
+
+impl Locale for English {
+    fn thousands_separator(&self) -> char {
+        ','
+    }
+
+    fn decimal_separator(&self) -> char {
+        '.'
+    }
+
+    fn months(&self) -> [&'static str; 12] {
+        [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ]
+    }
+
+    fn cardinal(&self, n: u64) -> String {
+        let mut words = Vec::new();
+        let mut rest = n;
+        for (scale, name) in SCALES {
+            if rest >= scale {
+                words.push(format!("{} {}", below_thousand(rest / scale), name));
+                rest %= scale;
+            }
+        }
+        if rest > 0 || words.is_empty() {
+            words.push(below_thousand(rest));
+        }
+        words.join(" ")
+    }
+
+    fn ordinal(&self, n: u64) -> String {
+        let cardinal = self.cardinal(n);
+        // Only the last word changes, e.g. "twenty-one" to "twenty-first".
+        let split = cardinal.rfind([' ', '-']).map_or(0, |i| i + 1);
+        let (start, last) = cardinal.split_at(split);
+        let last = match last {
+            "one" => "first".to_string(),
+            "two" => "second".to_string(),
+            "three" => "third".to_string(),
+            "five" => "fifth".to_string(),
+            "eight" => "eighth".to_string(),
+            "nine" => "ninth".to_string(),
+            "twelve" => "twelfth".to_string(),
+            last if last.ends_with('y') => format!("{}ieth", &last[..last.len() - 1]),
+            last => format!("{}th", last),
+        };
+        format!("{}{}", start, last)
+    }
+
+    fn minus(&self) -> &'static str {
+        "minus"
+    }
+
+    fn point(&self) -> &'static str {
+        "point"
+    }
+
+    fn date(&self, year: Option<u64>, month: u64, day: u64) -> String {
+        let month = self.months()[month as usize - 1];
+        match year {
+            Some(year) => format!("{} {}, {}", month, self.ordinal(day), self::year(year)),
+            None => format!("{} {}", month, self.ordinal(day)),
+        }
+    }
+
+    fn time(&self, hour: u64, minute: u64, second: Option<u64>, pm: Option<bool>) -> String {
+        let mut words = vec![self.cardinal(hour)];
+        match minute {
+            0 if pm.is_none() && second.is_none() => words.push("o'clock".to_string()),
+            0 => {}
+            1..=9 => words.push(format!("oh {}", self.cardinal(minute))),
+            _ => words.push(self.cardinal(minute)),
+        }
+        if let Some(second) = second {
+            let unit = if second == 1 { "second" } else { "seconds" };
+            words.push(format!("and {} {}", self.cardinal(second), unit));
+        }
+        match pm {
+            Some(true) => words.push("p m".to_string()),
+            Some(false) => words.push("a m".to_string()),
+            None => {}
+        }
+        words.join(" ")
+    }
+
+    fn money(&self, amount: &Amount, currency: Currency) -> String {
+        let (unit, units, cent, cents) = match currency {
+            Currency::Dollar => ("dollar", "dollars", "cent", "cents"),
+            Currency::Euro => ("euro", "euros", "cent", "cents"),
+            Currency::Pound => ("pound", "pounds", "penny", "pence"),
+            Currency::Yen => ("yen", "yen", "sen", "sen"),
+        };
+        let whole = Amount {
+            fraction: None,
+            ..amount.clone()
+        };
+        let name = |amount: &Amount, one: &str, many: &str| match amount.is_one() {
+            true => format!("{} {}", self.amount(amount), one),
+            false => format!("{} {}", self.amount(amount), many),
+        };
+        // Only amounts with one or two decimals are read as cents.
+        let hundredths = match &amount.fraction {
+            None => None,
+            Some(fraction) if fraction.len() <= 2 => Some(format!("{:0<2}", fraction)),
+            Some(_) => return name(amount, units, units),
+        };
+        match hundredths.filter(|cents| cents != "00") {
+            None => name(&whole, unit, units),
+            Some(hundredths) => {
+                let hundredths = Amount {
+                    negative: false,
+                    integer: hundredths,
+                    fraction: None,
+                };
+                if whole.value() == Some(0) {
+                    let sign = if amount.negative { "minus " } else { "" };
+                    format!("{}{}", sign, name(&hundredths, cent, cents))
+                } else {
+                    format!(
+                        "{} and {}",
+                        name(&whole, unit, units),
+                        name(&hundredths, cent, cents)
+                    )
+                }
+            }
+        }
+    }
+
+    fn measure(&self, amount: &Amount, unit: Unit) -> String {
+        let (one, many) = unit_names(unit);
+        let name = if amount.is_one() { one } else { many };
+        format!("{} {}", self.amount(amount), name)
+    }
+}
@@ -0,0 +1,507 @@
+//! Reading numbers, dates, times, amounts of money and measurements as words.
+//!
+//! [`Normalizer::normalize`] runs before the `clean_text` regex list, so the
+//! synthesizer gets "May first, twenty twenty-four" instead of guessing how to read
+//! `05/01/2024`. The conventions of each [`Language`] live in their own module.
+//!
+//! Passes run from the most to the least specific pattern: dates, times, money,
+//! percentages and units, ordinals, and finally plain numbers. Only plain numbers
+//! follow the [`NumberMode`]; everything else is read as whole numbers. A pass
+//! leaves alone what the passes before it spelled out, so [`Normalizer::rewrite`]
+//! can map every word back to the number it was read for.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::clean_text::{Pair, Rewritten};
+use crate::config::{AriaConfig, Language, NumberMode};
+
+mod de;
+mod en;
+
+#[cfg(test)]
+mod test;
+
+/// A unit of measurement Aria reads after a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Unit {
+    KilometersPerHour,
+    MilesPerHour,
+    Kilometers,
+    Centimeters,
+    Millimeters,
+    Meters,
+    Kilograms,
+    Milligrams,
+    Grams,
+    Milliliters,
+    Liters,
+    Celsius,
+    Fahrenheit,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+    Terabytes,
+    Gigahertz,
+    Megahertz,
+    Kilohertz,
+    Hertz,
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+    Percent,
+}
+
+impl Unit {
+    /// Units written after a number, longest symbol first so the regex prefers it.
+    const WRITTEN: [(&'static str, Unit); 26] = [
+        ("km/h", Unit::KilometersPerHour),
+        ("mph", Unit::MilesPerHour),
+        ("km", Unit::Kilometers),
+        ("cm", Unit::Centimeters),
+        ("mm", Unit::Millimeters),
+        ("m", Unit::Meters),
+        ("kg", Unit::Kilograms),
+        ("mg", Unit::Milligrams),
+        ("g", Unit::Grams),
+        ("ml", Unit::Milliliters),
+        ("l", Unit::Liters),
+        ("°C", Unit::Celsius),
+        ("°F", Unit::Fahrenheit),
+        ("KB", Unit::Kilobytes),
+        ("MB", Unit::Megabytes),
+        ("GB", Unit::Gigabytes),
+        ("TB", Unit::Terabytes),
+        ("GHz", Unit::Gigahertz),
+        ("MHz", Unit::Megahertz),
+        ("kHz", Unit::Kilohertz),
+        ("Hz", Unit::Hertz),
+        ("ms", Unit::Milliseconds),
+        ("s", Unit::Seconds),
+        ("min", Unit::Minutes),
+        ("h", Unit::Hours),
+        ("%", Unit::Percent),
+    ];
+
+    fn from_symbol(symbol: &str) -> Option<Unit> {
+        Self::WRITTEN
+            .iter()
+            .find(|(written, _)| *written == symbol)
+            .map(|(_, unit)| *unit)
+    }
+}
+
+/// A currency Aria reads after an amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Currency {
+    Dollar,
+    Euro,
+    Pound,
+    Yen,
+}
+
+impl Currency {
+    fn from_symbol(symbol: &str) -> Option<Currency> {
+        match symbol {
+            "$" | "USD" => Some(Currency::Dollar),
+            "€" | "EUR" => Some(Currency::Euro),
+            "£" | "GBP" => Some(Currency::Pound),
+            "¥" | "JPY" => Some(Currency::Yen),
+            _ => None,
+        }
+    }
+}
+
+/// A number as written, split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Amount {
+    pub negative: bool,
+    /// Digits before the decimal separator, without thousands separators.
+    pub integer: String,
+    /// Digits after the decimal separator.
+    pub fraction: Option<String>,
+}
+
+impl Amount {
+    /// Whether a noun after the amount is singular.
+    pub fn is_one(&self) -> bool {
+        !self.negative && self.integer.trim_start_matches('0') == "1" && self.fraction.is_none()
+    }
+
+    pub fn value(&self) -> Option<u64> {
+        self.integer.parse().ok()
+    }
+}
+
+/// The words and conventions of one language.
+pub(crate) trait Locale: Sync {
+    fn thousands_separator(&self) -> char;
+    fn decimal_separator(&self) -> char;
+    fn months(&self) -> [&'static str; 12];
+
+    fn cardinal(&self, n: u64) -> String;
+    fn ordinal(&self, n: u64) -> String;
+    fn minus(&self) -> &'static str;
+    fn point(&self) -> &'static str;
+
+    /// A date; `month` and `day` start at 1.
+    fn date(&self, year: Option<u64>, month: u64, day: u64) -> String;
+    /// A time of day, with `pm` set for "p.m." and unset for "a.m.".
+    fn time(&self, hour: u64, minute: u64, second: Option<u64>, pm: Option<bool>) -> String;
+    fn money(&self, amount: &Amount, currency: Currency) -> String;
+    fn measure(&self, amount: &Amount, unit: Unit) -> String;
+
+    fn digits(&self, digits: &str) -> String {
+        digits
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .map(|d| self.cardinal(d as u64))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// An amount as a whole number, with the fraction read digit by digit.
+    fn amount(&self, amount: &Amount) -> String {
+        let mut words = Vec::new();
+        if amount.negative {
+            words.push(self.minus().to_string());
+        }
+        words.push(match amount.value() {
+            Some(n) => self.cardinal(n),
+            None => self.digits(&amount.integer),
+        });
+        if let Some(fraction) = &amount.fraction {
+            words.push(self.point().to_string());
+            words.push(self.digits(fraction));
+        }
+        words.join(" ")
+    }
+}
+
+fn locale(language: Language) -> &'static dyn Locale {
+    match language {
+        Language::En => &en::English,
+        Language::De => &de::German,
+    }
+}
+
+/// The regexes of one language, built around its way of writing numbers.
+struct Patterns {
+    iso_date: Regex,
+    numeric_date: Regex,
+    named_date: Regex,
+    time: Regex,
+    money_before: Regex,
+    money_after: Regex,
+    unit: Regex,
+    ordinal: Regex,
+    number: Regex,
+}
+
+impl Patterns {
+    fn new(language: Language) -> Self {
+        let locale = locale(language);
+        let thousands = regex::escape(&locale.thousands_separator().to_string());
+        let decimal = regex::escape(&locale.decimal_separator().to_string());
+        let number = format!(
+            r"\d{{1,3}}(?:{t}\d{{3}})+(?:{d}\d+)?|\d+(?:{d}\d+)?",
+            t = thousands,
+            d = decimal
+        );
+        // A number must not continue a word or another number.
+        let start = r"(^|[^\w.,])";
+        let currency = r"([$€£¥]|\b(?:USD|EUR|GBP|JPY)\b)";
+        // Units written with letters must end a word, so "5 meters" is not "5 m".
+        let units = Unit::WRITTEN
+            .iter()
+            .map(
+                |(written, _)| match written.ends_with(char::is_alphabetic) {
+                    true => format!(r"{}\b", regex::escape(written)),
+                    false => regex::escape(written),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("|");
+        let months = locale.months().join("|");
+        let regex = |pattern: String| Regex::new(&pattern).expect("normalizer pattern");
+
+        let (numeric_date, named_date) = match language {
+            Language::En => (
+                format!(r"{}(\d{{1,2}})/(\d{{1,2}})/(\d{{4}})\b", start),
+                format!(
+                    r"\b({})\s+(\d{{1,2}})(?:st|nd|rd|th)?\b(?:,?\s+(\d{{4}})\b)?",
+                    months
+                ),
+            ),
+            Language::De => (
+                format!(r"{}(\d{{1,2}})\.(\d{{1,2}})\.(\d{{4}})\b", start),
+                format!(r"\b(\d{{1,2}})\.\s*({})\b(?:\s+(\d{{4}})\b)?", months),
+            ),
+        };
+
+        Patterns {
+            iso_date: regex(format!(r"{}(\d{{4}})-(\d{{2}})-(\d{{2}})\b", start)),
+            numeric_date: regex(numeric_date),
+            named_date: regex(named_date),
+            time: regex(format!(
+                r"{}(\d{{1,2}}):(\d{{2}})(?::(\d{{2}}))?(?:\s?([aApP])\.?[mM](?:\.|\b))?",
+                start
+            )),
+            money_before: regex(format!(r"(^|\W){}\s?(-?)({})", currency, number)),
+            money_after: regex(format!(r"{}(-?)({})\s?{}", start, number, currency)),
+            unit: regex(format!(r"{}(-?)({})\s?({})", start, number, units)),
+            ordinal: regex(r"\b(\d+)(?:st|nd|rd|th)\b".to_string()),
+            number: regex(format!(r"{}(-?)({})", start, number)),
+        }
+    }
+}
+
+static ENGLISH: Lazy<Patterns> = Lazy::new(|| Patterns::new(Language::En));
+static GERMAN: Lazy<Patterns> = Lazy::new(|| Patterns::new(Language::De));
+
+/// Rewrites numbers and the things written with them as words. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Normalizer {
+    pub language: Language,
+    pub numbers: NumberMode,
+}
+
+impl Normalizer {
+    pub fn new(language: Language, numbers: NumberMode) -> Self {
+        Normalizer { language, numbers }
+    }
+
+    pub fn from_config(config: &AriaConfig) -> Self {
+        Self::new(config.language, config.number_mode)
+    }
+
+    fn locale(&self) -> &'static dyn Locale {
+        locale(self.language)
+    }
+
+    fn patterns(&self) -> &'static Patterns {
+        match self.language {
+            Language::En => &ENGLISH,
+            Language::De => &GERMAN,
+        }
+    }
+
+    /// Split a number written in this language into its parts.
+    fn parse(&self, sign: &str, number: &str) -> Amount {
+        let locale = self.locale();
+        let number: String = number
+            .chars()
+            .filter(|c| *c != locale.thousands_separator())
+            .collect();
+        let (integer, fraction) = match number.split_once(locale.decimal_separator()) {
+            Some((integer, fraction)) => (integer.to_string(), Some(fraction.to_string())),
+            None => (number, None),
+        };
+        Amount {
+            negative: sign == "-",
+            integer,
+            fraction,
+        }
+    }
+
+    /// Read `text` with numbers, dates, times, money and units spelled out.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !text.chars().any(|c| c.is_ascii_digit()) {
+            return Cow::Borrowed(text);
+        }
+        self.pairs(text)
+            .into_iter()
+            .map(|(o, r)| r.unwrap_or_else(|| o.into()))
+            .collect::<String>()
+            .into()
+    }
+
+    /// [`Normalizer::normalize`] `text`, keeping the index maps back to it, e.g.
+    /// to follow the read position.
+    pub fn rewrite(&self, text: &str) -> Rewritten {
+        Rewritten::from_pairs(self.pairs(text))
+    }
+
+    /// `text` split into what the passes replaced and what they kept. Each pass
+    /// only sees the pieces the passes before it kept.
+    fn pairs<'a>(&self, text: &'a str) -> Vec<Pair<'a>> {
+        let locale = self.locale();
+        let patterns = self.patterns();
+        // `None` for more digits than a `u64` holds, which leaves dates and times as they are.
+        let number =
+            |caps: &Captures, i: usize| -> Option<u64> { caps.get(i)?.as_str().parse().ok() };
+        let month_index = |name: &str| {
+            locale
+                .months()
+                .iter()
+                .position(|month| *month == name)
+                .map(|i| i as u64 + 1)
+                .unwrap_or(0)
+        };
+
+        let text = vec![(text, None)];
+        let text = replace(&patterns.iso_date, text, |caps| {
+            let (year, month, day) = (number(caps, 2)?, number(caps, 3)?, number(caps, 4)?);
+            valid_date(month, day).then(|| locale.date(Some(year), month, day))
+        });
+        let text = replace(&patterns.numeric_date, text, |caps| {
+            let (first, second, year) = (number(caps, 2)?, number(caps, 3)?, number(caps, 4)?);
+            let (month, day) = match self.language {
+                Language::En => (first, second),
+                Language::De => (second, first),
+            };
+            valid_date(month, day).then(|| locale.date(Some(year), month, day))
+        });
+        let text = replace_all(&patterns.named_date, text, |caps| {
+            let (month, day) = match self.language {
+                Language::En => (month_index(&caps[1]), number(caps, 2)?),
+                Language::De => (month_index(&caps[2]), number(caps, 1)?),
+            };
+            let year = match caps.get(3) {
+                Some(_) => Some(number(caps, 3)?),
+                None => None,
+            };
+            valid_date(month, day).then(|| locale.date(year, month, day))
+        });
+        let text = replace(&patterns.time, text, |caps| {
+            let (hour, minute) = (number(caps, 2)?, number(caps, 3)?);
+            let second = match caps.get(4) {
+                Some(_) => Some(number(caps, 4)?),
+                None => None,
+            };
+            let pm = caps.get(5).map(|m| m.as_str().eq_ignore_ascii_case("p"));
+            let valid = match pm {
+                Some(_) => (1..=12).contains(&hour),
+                None => hour < 24,
+            } && minute < 60
+                && second.unwrap_or(0) < 60;
+            valid.then(|| locale.time(hour, minute, second, pm))
+        });
+        let text = replace(&patterns.money_before, text, |caps| {
+            let currency = Currency::from_symbol(&caps[2])?;
+            Some(locale.money(&self.parse(&caps[3], &caps[4]), currency))
+        });
+        let text = replace(&patterns.money_after, text, |caps| {
+            let currency = Currency::from_symbol(&caps[4])?;
+            Some(locale.money(&self.parse(&caps[2], &caps[3]), currency))
+        });
+        let text = replace(&patterns.unit, text, |caps| {
+            let unit = Unit::from_symbol(&caps[4])?;
+            Some(locale.measure(&self.parse(&caps[2], &caps[3]), unit))
+        });
+        let text = replace_all(&patterns.ordinal, text, |caps| {
+            Some(match number(caps, 1) {
+                Some(n) => locale.ordinal(n),
+                None => format!("{} {}", locale.digits(&caps[1]), &caps[0][caps[1].len()..]),
+            })
+        });
+        replace(&patterns.number, text, |caps| {
+            Some(self.number(&self.parse(&caps[2], &caps[3])))
+        })
+    }
+
+    /// A plain number, read according to the [`NumberMode`].
+    fn number(&self, amount: &Amount) -> String {
+        let locale = self.locale();
+        let integer = match self.numbers {
+            NumberMode::Whole => return locale.amount(amount),
+            NumberMode::Digits => locale.digits(&amount.integer),
+            NumberMode::Groups => amount
+                .integer
+                .as_bytes()
+                .chunks(2)
+                .map(|pair| {
+                    let pair = std::str::from_utf8(pair).unwrap_or_default();
+                    match pair.parse::<u64>() {
+                        // "05" is read digit by digit, "5" and "50" as a number.
+                        Ok(n) if !pair.starts_with('0') || pair.len() == 1 => locale.cardinal(n),
+                        _ => locale.digits(pair),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        let mut words = Vec::new();
+        if amount.negative {
+            words.push(locale.minus().to_string());
+        }
+        words.push(integer);
+        if let Some(fraction) = &amount.fraction {
+            words.push(locale.point().to_string());
+            words.push(locale.digits(fraction));
+        }
+        words.join(" ")
+    }
+}
+
+fn valid_date(month: u64, day: u64) -> bool {
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// Replace matches of a pattern starting with the `(^|[^\w.,])` group, keeping
+/// that group. Matches `read` returns `None` for are left as they are.
+fn replace<'a>(
+    regex: &Regex,
+    pairs: Vec<Pair<'a>>,
+    read: impl Fn(&Captures) -> Option<String>,
+) -> Vec<Pair<'a>> {
+    replace_pieces(
+        regex,
+        pairs,
+        |caps| caps.get(1).map_or(0, |m| m.end()),
+        read,
+    )
+}
+
+/// Replace whole matches of a pattern without a leading group.
+fn replace_all<'a>(
+    regex: &Regex,
+    pairs: Vec<Pair<'a>>,
+    read: impl Fn(&Captures) -> Option<String>,
+) -> Vec<Pair<'a>> {
+    replace_pieces(
+        regex,
+        pairs,
+        |caps| caps.get(0).map_or(0, |m| m.start()),
+        read,
+    )
+}
+
+/// Replace matches of a pattern in the pieces of `pairs` kept so far, from where
+/// `start` says to the end of the match.
+fn replace_pieces<'a>(
+    regex: &Regex,
+    pairs: Vec<Pair<'a>>,
+    start: impl Fn(&Captures) -> usize,
+    read: impl Fn(&Captures) -> Option<String>,
+) -> Vec<Pair<'a>> {
+    let mut replaced = Vec::with_capacity(pairs.len());
+    for (piece, replacement) in pairs {
+        if replacement.is_some() {
+            replaced.push((piece, replacement));
+            continue;
+        }
+        let mut kept = 0;
+        for caps in regex.captures_iter(piece) {
+            let Some(words) = read(&caps) else {
+                continue;
+            };
+            // unwrap on 0 is OK because captures only reports matches
+            let whole = caps.get(0).unwrap();
+            let from = start(&caps);
+            if kept < from {
+                replaced.push((&piece[kept..from], None));
+            }
+            replaced.push((&piece[from..whole.end()], Some(words.into())));
+            kept = whole.end();
+        }
+        if kept < piece.len() {
+            replaced.push((&piece[kept..], None));
+        }
+    }
+    replaced
+}
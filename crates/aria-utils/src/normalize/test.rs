@@ -0,0 +1,190 @@
+use super::*;
+
+fn english(text: &str) -> String {
+    Normalizer::new(Language::En, NumberMode::Whole)
+        .normalize(text)
+        .into_owned()
+}
+
+fn german(text: &str) -> String {
+    Normalizer::new(Language::De, NumberMode::Whole)
+        .normalize(text)
+        .into_owned()
+}
+
+#[test]
+fn text_without_digits_is_borrowed() {
+    let normalizer = Normalizer::default();
+    assert!(matches!(
+        normalizer.normalize("OK, button"),
+        Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn english_cardinals() {
+    let en = en::English;
+    assert_eq!(en.cardinal(0), "zero");
+    assert_eq!(en.cardinal(15), "fifteen");
+    assert_eq!(en.cardinal(40), "forty");
+    assert_eq!(en.cardinal(101), "one hundred one");
+    assert_eq!(en.cardinal(1234), "one thousand two hundred thirty-four");
+    assert_eq!(en.cardinal(2_000_005), "two million five");
+    assert_eq!(en.cardinal(u64::MAX).split(' ').next(), Some("eighteen"));
+}
+
+#[test]
+fn german_cardinals() {
+    let de = de::German;
+    assert_eq!(de.cardinal(0), "null");
+    assert_eq!(de.cardinal(1), "eins");
+    assert_eq!(de.cardinal(21), "einundzwanzig");
+    assert_eq!(de.cardinal(101), "einhunderteins");
+    assert_eq!(de.cardinal(1234), "eintausendzweihundertvierunddreißig");
+    assert_eq!(de.cardinal(21_000), "einundzwanzigtausend");
+    assert_eq!(de.cardinal(1_000_001), "eine Million eins");
+    assert_eq!(de.cardinal(3_500_000), "drei Millionen fünfhunderttausend");
+}
+
+#[test]
+fn ordinals() {
+    assert_eq!(
+        english("the 1st, 2nd, 3rd and 21st"),
+        "the first, second, third and twenty-first"
+    );
+    assert_eq!(english("40th"), "fortieth");
+    assert_eq!(english("112th"), "one hundred twelfth");
+    // Too long to read as a number, rather than "zeroth".
+    assert_eq!(
+        english("the 18446744073709551616th"),
+        "the one eight four four six seven four four zero seven three seven zero nine five five \
+         one six one six th"
+    );
+
+    let de = de::German;
+    assert_eq!(de.ordinal(1), "erste");
+    assert_eq!(de.ordinal(7), "siebte");
+    assert_eq!(de.ordinal(20), "zwanzigste");
+    assert_eq!(de.ordinal(103), "einhundertdritte");
+}
+
+#[test]
+fn numbers_follow_the_locale() {
+    assert_eq!(
+        english("1,234.5 items"),
+        "one thousand two hundred thirty-four point five items"
+    );
+    assert_eq!(english("-3 and 007"), "minus three and seven");
+    assert_eq!(
+        german("1.234,5 Stück"),
+        "eintausendzweihundertvierunddreißig Komma fünf Stück"
+    );
+    assert_eq!(german("Seite 3, Zeile 4"), "Seite drei, Zeile vier");
+}
+
+#[test]
+fn number_modes() {
+    let digits = Normalizer::new(Language::En, NumberMode::Digits);
+    assert_eq!(digits.normalize("call 911"), "call nine one one");
+    assert_eq!(digits.normalize("3.5"), "three point five");
+
+    let groups = Normalizer::new(Language::En, NumberMode::Groups);
+    assert_eq!(
+        groups.normalize("1234 567"),
+        "twelve thirty-four fifty-six seven"
+    );
+    assert_eq!(groups.normalize("0105"), "zero one zero five");
+
+    let german = Normalizer::new(Language::De, NumberMode::Groups);
+    assert_eq!(german.normalize("4711"), "siebenundvierzig elf");
+}
+
+#[test]
+fn words_next_to_numbers_are_left_alone() {
+    assert_eq!(english("mp3 and H2O"), "mp3 and H2O");
+    assert_eq!(english("v1.2.3"), "v1.2.3");
+}
+
+#[test]
+fn dates() {
+    assert_eq!(english("on 2024-05-01"), "on May first, twenty twenty-four");
+    assert_eq!(
+        english("due 12/25/1999"),
+        "due December twenty-fifth, nineteen ninety-nine"
+    );
+    assert_eq!(english("March 3, 2005"), "March third, two thousand five");
+    assert_eq!(english("July 4th"), "July fourth");
+    assert_eq!(
+        english("2024-13-01"),
+        "two thousand twenty-four-thirteen-one"
+    );
+
+    assert_eq!(
+        german("am 01.05.2024"),
+        "am erster Mai zweitausendvierundzwanzig"
+    );
+    assert_eq!(
+        german("3. Oktober 1990"),
+        "dritter Oktober neunzehnhundertneunzig"
+    );
+    assert_eq!(german("2024-05-01"), "erster Mai zweitausendvierundzwanzig");
+}
+
+#[test]
+fn times() {
+    assert_eq!(english("at 9:00"), "at nine o'clock");
+    assert_eq!(english("at 9:05 pm"), "at nine oh five p m");
+    assert_eq!(english("14:30:15"), "fourteen thirty and fifteen seconds");
+    assert_eq!(english("25:00"), "twenty-five:zero");
+
+    assert_eq!(german("um 14:30"), "um vierzehn Uhr dreißig");
+    assert_eq!(german("1:00"), "ein Uhr");
+}
+
+#[test]
+fn money() {
+    assert_eq!(english("$5"), "five dollars");
+    assert_eq!(english("costs $1.50"), "costs one dollar and fifty cents");
+    assert_eq!(english("$0.05"), "five cents");
+    assert_eq!(english("€1,000"), "one thousand euros");
+    assert_eq!(english("£2.5"), "two pounds and fifty pence");
+    assert_eq!(english("20 USD"), "twenty dollars");
+
+    assert_eq!(german("5,50 €"), "fünf Euro fünfzig");
+    assert_eq!(german("1 €"), "ein Euro");
+    assert_eq!(german("$0,01"), "ein Cent");
+    assert_eq!(german("1.000 EUR"), "eintausend Euro");
+}
+
+#[test]
+fn percentages_and_units() {
+    assert_eq!(english("50% off"), "fifty percent off");
+    assert_eq!(english("5% 6%"), "five percent six percent");
+    assert_eq!(
+        english("1 km and 2.5 km"),
+        "one kilometer and two point five kilometers"
+    );
+    assert_eq!(english("120km/h"), "one hundred twenty kilometers per hour");
+    assert_eq!(english("-5 °C"), "minus five degrees Celsius");
+    assert_eq!(english("16 GB"), "sixteen gigabytes");
+    assert_eq!(english("5 meters"), "five meters");
+
+    assert_eq!(german("1 h und 30 min"), "eine Stunde und dreißig Minuten");
+    assert_eq!(german("1 min"), "eine Minute");
+    assert_eq!(german("2,5 kg"), "zwei Komma fünf Kilogramm");
+    assert_eq!(german("99 %"), "neunundneunzig Prozent");
+}
+
+#[test]
+fn rewritten_numbers_map_to_what_they_were_read_for() {
+    let normalizer = Normalizer::new(Language::En, NumberMode::Whole);
+    let rewritten = normalizer.rewrite("Pay $5 by 05/01/2024");
+    assert_eq!(
+        rewritten.text,
+        "Pay five dollars by May first, twenty twenty-four"
+    );
+    // "dollars" and "twenty-four" are part of what "$5" and the date were read as.
+    assert_eq!(rewritten.input_u16range(&(9..16)), 4..6);
+    assert_eq!(rewritten.input_u16range(&(37..48)), 10..20);
+    assert_eq!(rewritten.input_u16range(&(0..3)), 0..3);
+}
@@ -0,0 +1,436 @@
+//! User pronunciation dictionaries, stored in pronunciation.toml next to aria.toml.
+//!
+//! Each entry rewrites matches of a pattern just before the text is synthesized.
+//! Entries live in a global dictionary, in one dictionary per voice or in one
+//! dictionary per application:
+//!
+//! ```toml
+//! [[global]]
+//! pattern = "GIF"
+//! replacement = "jif"
+//! case_sensitive = true
+//!
+//! [[voices."Microsoft Hedda"]]
+//! pattern = "Aria"
+//! replacement = "Ahria"
+//!
+//! [[apps."code.exe"]]
+//! pattern = 'fn\s+(\w+)'
+//! replacement = "function $1"
+//! kind = "regex"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clean_text::{run_stages, CleanerStage, RegexCleanerPair};
+use crate::config::get_config_path;
+use crate::error::Result;
+
+/// How the pattern of a [`PronunciationEntry`] is matched.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchKind {
+    /// The pattern anywhere in the text, also inside words.
+    Literal,
+    /// The pattern as a whole word.
+    #[default]
+    WholeWord,
+    /// A regular expression. The replacement can refer to groups as `$1` or `$name`.
+    Regex,
+}
+
+impl fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MatchKind::Literal => "literal",
+            MatchKind::WholeWord => "whole-word",
+            MatchKind::Regex => "regex",
+        })
+    }
+}
+
+impl FromStr for MatchKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "literal" => Ok(MatchKind::Literal),
+            "whole-word" => Ok(MatchKind::WholeWord),
+            "regex" => Ok(MatchKind::Regex),
+            _ => Err(format!(
+                "unknown match kind `{}`, expected literal, whole-word or regex",
+                s
+            )),
+        }
+    }
+}
+
+/// One rule of a pronunciation dictionary.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PronunciationEntry {
+    pub pattern: String,
+    /// Text or phoneme string spoken instead of the match. Phoneme strings are
+    /// passed to the engine unchanged, so they have to use the notation of the
+    /// engine in use, e.g. `[[h@'loU]]` for eSpeak.
+    pub replacement: String,
+    #[serde(default)]
+    pub kind: MatchKind,
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+impl PronunciationEntry {
+    pub fn new<P: Into<String>, R: Into<String>>(pattern: P, replacement: R) -> Self {
+        PronunciationEntry {
+            pattern: pattern.into(),
+            replacement: replacement.into(),
+            kind: MatchKind::default(),
+            case_sensitive: false,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: MatchKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Compile the entry into a regex and a replacement for [`regex::Regex::replace_all`].
+    pub fn to_pair(&self) -> std::result::Result<RegexCleanerPair, regex::Error> {
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let (pattern, replacement) = match self.kind {
+            MatchKind::Regex => (self.pattern.clone(), self.replacement.clone()),
+            MatchKind::Literal => (regex::escape(&self.pattern), literal(&self.replacement)),
+            MatchKind::WholeWord => {
+                // Only anchor at word characters, so `C#` still matches before a space.
+                let start = if is_word(self.pattern.chars().next()) {
+                    r"\b"
+                } else {
+                    ""
+                };
+                let end = if is_word(self.pattern.chars().last()) {
+                    r"\b"
+                } else {
+                    ""
+                };
+                (
+                    format!("{}{}{}", start, regex::escape(&self.pattern), end),
+                    literal(&self.replacement),
+                )
+            }
+        };
+        let flags = if self.case_sensitive { "" } else { "(?i)" };
+        RegexCleanerPair::new(format!("{}{}", flags, pattern), replacement)
+    }
+}
+
+/// Escape `$` so a replacement is used as is.
+fn literal(replacement: &str) -> String {
+    replacement.replace('$', "$$")
+}
+
+/// Which dictionary an entry belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DictionaryScope {
+    Global,
+    /// The dictionary of a voice, by [display name or id](Pronunciation::apply).
+    Voice(String),
+    /// The dictionary of an application, by executable name, e.g. `notepad.exe`.
+    App(String),
+}
+
+impl fmt::Display for DictionaryScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DictionaryScope::Global => f.write_str("global"),
+            DictionaryScope::Voice(voice) => write!(f, "voice {}", voice),
+            DictionaryScope::App(app) => write!(f, "app {}", app),
+        }
+    }
+}
+
+/// The contents of pronunciation.toml.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PronunciationDictionaries {
+    #[serde(default)]
+    pub global: Vec<PronunciationEntry>,
+    #[serde(default)]
+    pub voices: BTreeMap<String, Vec<PronunciationEntry>>,
+    #[serde(default)]
+    pub apps: BTreeMap<String, Vec<PronunciationEntry>>,
+}
+
+impl PronunciationDictionaries {
+    /// The entries of one dictionary, in the order they are applied.
+    pub fn entries(&self, scope: &DictionaryScope) -> &[PronunciationEntry] {
+        let entries = match scope {
+            DictionaryScope::Global => Some(&self.global),
+            DictionaryScope::Voice(voice) => self.voices.get(voice),
+            DictionaryScope::App(app) => self.apps.get(app),
+        };
+        entries.map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn entries_mut(&mut self, scope: &DictionaryScope) -> &mut Vec<PronunciationEntry> {
+        match scope {
+            DictionaryScope::Global => &mut self.global,
+            DictionaryScope::Voice(voice) => self.voices.entry(voice.clone()).or_default(),
+            DictionaryScope::App(app) => self.apps.entry(app.clone()).or_default(),
+        }
+    }
+
+    /// Every dictionary that has entries, global first.
+    pub fn scopes(&self) -> Vec<DictionaryScope> {
+        let mut scopes = Vec::new();
+        if !self.global.is_empty() {
+            scopes.push(DictionaryScope::Global);
+        }
+        scopes.extend(self.voices.keys().cloned().map(DictionaryScope::Voice));
+        scopes.extend(self.apps.keys().cloned().map(DictionaryScope::App));
+        scopes
+    }
+
+    /// Add `entry` to the end of a dictionary, after checking that it compiles.
+    pub fn add(&mut self, scope: &DictionaryScope, entry: PronunciationEntry) -> Result<()> {
+        entry.to_pair()?;
+        self.entries_mut(scope).push(entry);
+        Ok(())
+    }
+
+    /// Remove the entry at `index` of a dictionary, dropping the dictionary once
+    /// it is empty.
+    pub fn remove(&mut self, scope: &DictionaryScope, index: usize) -> Option<PronunciationEntry> {
+        let entries = self.entries_mut(scope);
+        let removed = (index < entries.len()).then(|| entries.remove(index));
+        self.voices.retain(|_, entries| !entries.is_empty());
+        self.apps.retain(|_, entries| !entries.is_empty());
+        removed
+    }
+
+    /// Compile every entry, failing on the first invalid pattern.
+    pub fn compile(&self) -> Result<Pronunciation> {
+        fn compile_all(entries: &[PronunciationEntry]) -> Result<Vec<RegexCleanerPair>> {
+            Ok(entries
+                .iter()
+                .map(PronunciationEntry::to_pair)
+                .collect::<std::result::Result<_, _>>()?)
+        }
+        fn compile_map(
+            map: &BTreeMap<String, Vec<PronunciationEntry>>,
+        ) -> Result<Vec<(String, Vec<RegexCleanerPair>)>> {
+            map.iter()
+                .map(|(key, entries)| Ok((key.to_lowercase(), compile_all(entries)?)))
+                .collect()
+        }
+
+        Ok(Pronunciation {
+            global: compile_all(&self.global)?,
+            voices: compile_map(&self.voices)?,
+            apps: compile_map(&self.apps)?,
+        })
+    }
+
+    /// Read the dictionaries from `path`. A missing file holds no entries.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Write the dictionaries to `path`, replacing whatever the file contained.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Compiled dictionaries, ready to be applied to text.
+#[derive(Debug, Clone, Default)]
+pub struct Pronunciation {
+    global: Vec<RegexCleanerPair>,
+    voices: Vec<(String, Vec<RegexCleanerPair>)>,
+    apps: Vec<(String, Vec<RegexCleanerPair>)>,
+}
+
+impl Pronunciation {
+    pub fn is_empty(&self) -> bool {
+        self.global.is_empty() && self.voices.is_empty() && self.apps.is_empty()
+    }
+
+    /// Rewrite `text` with the dictionary of `app`, then the dictionary of the
+    /// voice, then the global dictionary. The more specific dictionaries come
+    /// first, so their entries win over global ones for the same word. What an
+    /// entry replaced is not rewritten by the entries after it.
+    ///
+    /// `voice` lists the names the voice is known by, usually its display name
+    /// and id. Keys of voice and app dictionaries are matched ignoring case.
+    pub fn apply(&self, text: &str, voice: &[&str], app: Option<&str>) -> String {
+        run_stages(text, self.stages(voice, app))
+            .map(|(o, r)| r.unwrap_or_else(|| o.into()))
+            .collect()
+    }
+
+    /// The entries [`Pronunciation::apply`] rewrites with, in order, as stages of
+    /// a cleaning pipeline.
+    pub fn stages<'a>(
+        &'a self,
+        voice: &[&str],
+        app: Option<&str>,
+    ) -> impl Iterator<Item = &'a dyn CleanerStage> {
+        let mut dictionaries = Vec::new();
+        if let Some(app) = app {
+            dictionaries.push(find(&self.apps, app));
+        }
+        if let Some(entries) = voice
+            .iter()
+            .map(|name| find(&self.voices, name))
+            .find(|entries| !entries.is_empty())
+        {
+            dictionaries.push(entries);
+        }
+        dictionaries.push(&self.global);
+        dictionaries
+            .into_iter()
+            .flatten()
+            .map(|pair| pair as &dyn CleanerStage)
+    }
+}
+
+/// The entries stored under `name`, ignoring case.
+fn find<'a>(
+    dictionaries: &'a [(String, Vec<RegexCleanerPair>)],
+    name: &str,
+) -> &'a [RegexCleanerPair] {
+    let name = name.to_lowercase();
+    dictionaries
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, entries)| entries.as_slice())
+        .unwrap_or_default()
+}
+
+/// pronunciation.toml, next to aria.toml.
+pub fn get_pronunciation_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("pronunciation.toml"))
+}
+
+/// Read the pronunciation dictionaries of the current user.
+pub fn load_pronunciation() -> Result<PronunciationDictionaries> {
+    PronunciationDictionaries::load(&get_pronunciation_path()?)
+}
+
+/// Save the pronunciation dictionaries of the current user.
+pub fn save_pronunciation(dictionaries: &PronunciationDictionaries) -> Result<()> {
+    dictionaries.save(&get_pronunciation_path()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(entry: PronunciationEntry, text: &str) -> String {
+        let mut dictionaries = PronunciationDictionaries::default();
+        dictionaries.add(&DictionaryScope::Global, entry).unwrap();
+        dictionaries.compile().unwrap().apply(text, &[], None)
+    }
+
+    #[test]
+    fn whole_words_ignore_case_by_default() {
+        let entry = PronunciationEntry::new("sql", "sequel");
+        assert_eq!(apply(entry, "SQL and mysql"), "sequel and mysql");
+
+        let entry = PronunciationEntry::new("C#", "C sharp");
+        assert_eq!(apply(entry, "Learn C# today"), "Learn C sharp today");
+    }
+
+    #[test]
+    fn literal_and_case_sensitive_entries() {
+        let entry = PronunciationEntry::new("sql", "sequel").with_kind(MatchKind::Literal);
+        assert_eq!(apply(entry, "mysql"), "mysequel");
+
+        let entry = PronunciationEntry::new("GIF", "jif $1").case_sensitive(true);
+        assert_eq!(apply(entry, "GIF gif"), "jif $1 gif");
+    }
+
+    #[test]
+    fn regex_entries_expand_groups() {
+        let entry = PronunciationEntry::new(r"(\d+)px", "$1 pixels").with_kind(MatchKind::Regex);
+        assert_eq!(apply(entry, "12px wide"), "12 pixels wide");
+
+        let invalid = PronunciationEntry::new("(", "").with_kind(MatchKind::Regex);
+        assert!(PronunciationDictionaries::default()
+            .add(&DictionaryScope::Global, invalid)
+            .is_err());
+    }
+
+    #[test]
+    fn specific_dictionaries_come_first() {
+        let mut dictionaries = PronunciationDictionaries::default();
+        let voice = DictionaryScope::Voice("Microsoft Hedda".to_string());
+        let app = DictionaryScope::App("code.exe".to_string());
+        dictionaries
+            .add(
+                &DictionaryScope::Global,
+                PronunciationEntry::new("Aria", "Arya"),
+            )
+            .unwrap();
+        dictionaries
+            .add(&voice, PronunciationEntry::new("Aria", "Ahria"))
+            .unwrap();
+        dictionaries
+            .add(&app, PronunciationEntry::new("fn", "function"))
+            .unwrap();
+        let pronunciation = dictionaries.compile().unwrap();
+
+        assert_eq!(pronunciation.apply("Aria fn", &[], None), "Arya fn");
+        assert_eq!(
+            pronunciation.apply(
+                "Aria fn",
+                &["hedda-id", "microsoft hedda"],
+                Some("Code.exe")
+            ),
+            "Ahria function"
+        );
+
+        assert!(dictionaries.remove(&app, 1).is_none());
+        assert!(dictionaries.remove(&app, 0).is_some());
+        assert_eq!(dictionaries.scopes(), vec![DictionaryScope::Global, voice]);
+    }
+
+    #[test]
+    fn dictionaries_round_trip_through_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pronunciation.toml");
+        assert_eq!(
+            PronunciationDictionaries::load(&path).unwrap(),
+            PronunciationDictionaries::default()
+        );
+
+        let mut dictionaries = PronunciationDictionaries::default();
+        dictionaries
+            .add(
+                &DictionaryScope::App("code.exe".to_string()),
+                PronunciationEntry::new(r"fn\s+(\w+)", "function $1").with_kind(MatchKind::Regex),
+            )
+            .unwrap();
+        dictionaries.save(&path).unwrap();
+        assert_eq!(
+            PronunciationDictionaries::load(&path).unwrap(),
+            dictionaries
+        );
+    }
+}
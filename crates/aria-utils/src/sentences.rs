@@ -0,0 +1,203 @@
+//! Splitting text into sentences, so long text can be synthesized a sentence at a
+//! time.
+//!
+//! A sentence ends at a line break or at a terminator followed by whitespace.
+//! Closing quotes and brackets after the terminator belong to the sentence. East
+//! Asian full stops end a sentence without a space after them. A period does not
+//! end a sentence after an abbreviation of the [`Language`], after initials such
+//! as "J." or "e.g.", or when the next word starts with a lowercase letter.
+
+use std::ops::Range;
+
+use crate::config::Language;
+
+/// Characters that end a sentence when followed by whitespace.
+const TERMINATORS: &[char] = &[
+    '.', '!', '?', '…', '‼', '⁇', '⁈', '⁉', '।', '॥', '؟', '۔', '。', '！', '？', '｡',
+];
+
+/// Terminators of scripts written without spaces between sentences.
+const FULL_WIDTH_TERMINATORS: &[char] = &['。', '！', '？', '｡'];
+
+/// Characters after a terminator that still belong to its sentence.
+const CLOSING: &[char] = &[
+    '"', '\'', '”', '’', '“', '»', '«', ')', ']', '}', '」', '』', '）',
+];
+
+const ENGLISH_ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "approx", "dept", "est", "inc",
+    "ltd", "co", "corp", "mt", "ave", "blvd", "gen", "gov", "sgt", "capt", "lt", "col",
+];
+
+/// Abbreviations that are only ever followed by a number, such as "No. 5".
+const ENGLISH_BEFORE_NUMBERS: &[&str] = &[
+    "no", "nos", "p", "pp", "vol", "fig", "ch", "sec", "art", "jan", "feb", "mar", "apr", "jun",
+    "jul", "aug", "sep", "sept", "oct", "nov", "dec",
+];
+
+const GERMAN_ABBREVIATIONS: &[&str] = &[
+    "bzw", "usw", "ca", "evtl", "ggf", "vgl", "sog", "inkl", "zzgl", "bspw", "etc", "hr", "fr",
+    "dr", "prof", "str", "mio", "mrd", "jh", "dipl", "ing", "geb", "gest", "tsd",
+];
+
+const GERMAN_BEFORE_NUMBERS: &[&str] = &["nr", "s", "abs", "art", "bd", "tel", "kap"];
+
+impl Language {
+    fn abbreviations(self) -> (&'static [&'static str], &'static [&'static str]) {
+        match self {
+            Language::En => (ENGLISH_ABBREVIATIONS, ENGLISH_BEFORE_NUMBERS),
+            Language::De => (GERMAN_ABBREVIATIONS, GERMAN_BEFORE_NUMBERS),
+        }
+    }
+}
+
+/// Byte ranges of the sentences in `text`, without the whitespace between them.
+pub fn split_sentences(text: &str, language: Language) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let end = if c == '\n' || c == '\r' {
+            i
+        } else if TERMINATORS.contains(&c) {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, next)) = chars.peek() {
+                if !TERMINATORS.contains(&next) && !CLOSING.contains(&next) {
+                    break;
+                }
+                end = j + next.len_utf8();
+                chars.next();
+            }
+            let rest = &text[end..];
+            let spaced = rest.is_empty() || rest.starts_with(char::is_whitespace);
+            if !spaced && !text[i..end].contains(FULL_WIDTH_TERMINATORS) {
+                continue;
+            }
+            if matches!(c, '.' | '…') && !ends_sentence(&text[start..i], rest, language) {
+                continue;
+            }
+            end
+        } else {
+            continue;
+        };
+
+        push_trimmed(&mut sentences, text, start..end);
+        start = end;
+    }
+    push_trimmed(&mut sentences, text, start..text.len());
+    sentences
+}
+
+/// Whether a period between `before` and `after` ends a sentence.
+fn ends_sentence(before: &str, after: &str, language: Language) -> bool {
+    let next = after.trim_start().chars().next();
+    if next.is_some_and(char::is_lowercase) {
+        return false;
+    }
+    let word = before
+        .rsplit(|c: char| c.is_whitespace() || "([{\"'“„»«".contains(c))
+        .next()
+        .unwrap_or_default();
+    if word.is_empty() {
+        return true;
+    }
+
+    // Initials and dotted abbreviations: "J.", "U.S.", "e.g.", "z.B."
+    if word
+        .split('.')
+        .all(|part| part.chars().count() == 1 && part.chars().all(char::is_alphabetic))
+    {
+        return false;
+    }
+
+    let word = word.to_lowercase();
+    let (abbreviations, before_numbers) = language.abbreviations();
+    if abbreviations.contains(&word.as_str()) {
+        return false;
+    }
+    let number_follows = next.is_some_and(|c| c.is_ascii_digit());
+    if number_follows && before_numbers.contains(&word.as_str()) {
+        return false;
+    }
+    // German ordinals: "am 3. Oktober".
+    !(language == Language::De && word.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn push_trimmed(sentences: &mut Vec<Range<usize>>, text: &str, range: Range<usize>) {
+    let sentence = &text[range.clone()];
+    let trimmed = sentence.trim_start();
+    let start = range.start + sentence.len() - trimmed.len();
+    let end = start + trimmed.trim_end().len();
+    if start < end {
+        sentences.push(start..end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences(text: &str, language: Language) -> Vec<&str> {
+        split_sentences(text, language)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn terminators_and_line_breaks_end_sentences() {
+        assert_eq!(
+            sentences("Hello there.  How are you?! Fine…\nThanks", Language::En),
+            vec!["Hello there.", "How are you?!", "Fine…", "Thanks"]
+        );
+        assert_eq!(
+            sentences("He said \"Stop.\" Then he left.", Language::En),
+            vec!["He said \"Stop.\"", "Then he left."]
+        );
+        assert_eq!(
+            sentences("Version 1.2.3 is out. Update now", Language::En),
+            vec!["Version 1.2.3 is out.", "Update now"]
+        );
+        assert_eq!(
+            sentences("今日は晴れです。明日は雨です。", Language::En),
+            vec!["今日は晴れです。", "明日は雨です。"]
+        );
+        assert!(sentences(" \n ", Language::En).is_empty());
+    }
+
+    #[test]
+    fn abbreviations_do_not_end_sentences() {
+        assert_eq!(
+            sentences(
+                "Mr. Smith met Dr. J. R. Doe, e.g. at No. 5. Then etc. ok.",
+                Language::En
+            ),
+            vec![
+                "Mr. Smith met Dr. J. R. Doe, e.g. at No. 5.",
+                "Then etc. ok."
+            ]
+        );
+        assert_eq!(
+            sentences("Wait... what? No. That is all.", Language::En),
+            vec!["Wait... what?", "No.", "That is all."]
+        );
+    }
+
+    #[test]
+    fn german_rules() {
+        assert_eq!(
+            sentences(
+                "Am 3. Oktober kam Hr. Meier, z. B. mit Nr. 7 an. Danach ging er.",
+                Language::De
+            ),
+            vec![
+                "Am 3. Oktober kam Hr. Meier, z. B. mit Nr. 7 an.",
+                "Danach ging er."
+            ]
+        );
+        // A number before a period is an ordinal in German only.
+        assert_eq!(sentences("Es war 5. Platz", Language::De).len(), 1);
+        assert_eq!(sentences("It was 5. Then", Language::En).len(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! Spelling text one character at a time.
+//!
+//! A character is a grapheme cluster, as in the last stage of `clean_text`, so an
+//! "e" with a combining accent or an emoji with a skin tone is one unit, and so is
+//! a character outside the Basic Multilingual Plane, which takes a surrogate pair
+//! in UTF-16. Each [`SpellLevel`] says more about a character than the one before.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use once_cell::sync::Lazy;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::config::PunctuationLevel;
+use crate::emoji::emoji_name;
+use crate::symbols::symbol_name;
+use crate::wide_string::LenUtf;
+
+mod phonetic;
+
+#[cfg(test)]
+mod test;
+
+static UNICODE_NAMES: Lazy<HashMap<char, &'static str>> = Lazy::new(|| {
+    include_str!("unicode_names.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (code_point, name) = line.split_once(';')?;
+            let c = char::from_u32(u32::from_str_radix(code_point, 16).ok()?)?;
+            Some((c, name))
+        })
+        .collect()
+});
+
+/// How much spelling says about each character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpellLevel {
+    /// The character itself, with spaces and symbols named.
+    #[default]
+    Characters,
+    /// A word from a spelling alphabet, e.g. "Bravo" for "b".
+    Phonetic,
+    /// The Unicode name and code point of every code point.
+    Unicode,
+}
+
+impl SpellLevel {
+    /// The level after this one, wrapping around to [`SpellLevel::Characters`].
+    pub fn next(self) -> Self {
+        match self {
+            SpellLevel::Characters => SpellLevel::Phonetic,
+            SpellLevel::Phonetic => SpellLevel::Unicode,
+            SpellLevel::Unicode => SpellLevel::Characters,
+        }
+    }
+}
+
+impl fmt::Display for SpellLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SpellLevel::Characters => "characters",
+            SpellLevel::Phonetic => "phonetic",
+            SpellLevel::Unicode => "unicode",
+        })
+    }
+}
+
+/// One character of spelled text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spelled<'a> {
+    pub grapheme: &'a str,
+    /// Where the character is in the spelled text, in UTF-16 code units.
+    pub u16range: Range<usize>,
+    pub description: String,
+}
+
+/// Spell `text` one character at a time at `level`.
+pub fn spell(text: &str, level: SpellLevel) -> Vec<Spelled<'_>> {
+    let mut start = 0;
+    text.graphemes(true)
+        .map(|grapheme| {
+            let end = start + grapheme.len_utf16();
+            let spelled = Spelled {
+                grapheme,
+                u16range: start..end,
+                description: describe(grapheme, level),
+            };
+            start = end;
+            spelled
+        })
+        .collect()
+}
+
+/// Describe a single grapheme at `level`. Levels that have nothing to say about a
+/// character fall back to the level before.
+pub fn describe(grapheme: &str, level: SpellLevel) -> String {
+    match level {
+        SpellLevel::Characters => character(grapheme),
+        SpellLevel::Phonetic => phonetic(grapheme).unwrap_or_else(|| character(grapheme)),
+        SpellLevel::Unicode => grapheme.chars().map(unicode).collect::<Vec<_>>().join(", "),
+    }
+}
+
+fn character(grapheme: &str) -> String {
+    match grapheme {
+        " " | "\u{a0}" => return "space".to_string(),
+        "\t" => return "tab".to_string(),
+        "\n" | "\r\n" | "\r" => return "new line".to_string(),
+        _ => {}
+    }
+    let mut chars = grapheme.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if let Some(name) = symbol_name(c, PunctuationLevel::All) {
+            return name.to_string();
+        }
+    }
+    match emoji_name(grapheme) {
+        Some(name) => name.into_owned(),
+        None => grapheme.to_string(),
+    }
+}
+
+/// The spelling-alphabet word of a letter, followed by the names of its
+/// diacritics, whether precomposed or combining, e.g. "Echo with acute".
+fn phonetic(grapheme: &str) -> Option<String> {
+    let mut chars = grapheme.chars();
+    let first = chars.next()?;
+    let (word, mut marks) = match phonetic::phonetic_word(first) {
+        Some(word) => (word, Vec::new()),
+        None => {
+            // A precomposed letter is named after its base letter, e.g. LATIN SMALL
+            // LETTER E WITH ACUTE.
+            let name = UNICODE_NAMES.get(&first)?;
+            let (letter, marks) = name.split_once(" WITH ")?;
+            let base = letter.rsplit(' ').next()?.chars().next()?;
+            (phonetic::phonetic_word(base)?, vec![marks.to_lowercase()])
+        }
+    };
+    for mark in chars {
+        let name = UNICODE_NAMES.get(&mark)?;
+        marks.push(name.trim_start_matches("COMBINING ").to_lowercase());
+    }
+    if marks.is_empty() {
+        Some(word.to_string())
+    } else {
+        Some(format!("{} with {}", word, marks.join(" and ")))
+    }
+}
+
+/// The Unicode name of `c` and its code point, e.g. "latin small letter a, U+0061".
+fn unicode(c: char) -> String {
+    let code_point = format!("U+{:04X}", c as u32);
+    match unicode_name(c) {
+        Some(name) => format!("{}, {}", name.to_lowercase(), code_point),
+        None => code_point,
+    }
+}
+
+/// The Unicode name of `c`, for the blocks in the embedded table and the
+/// ideographs, whose names are their code points.
+pub fn unicode_name(c: char) -> Option<String> {
+    if let Some(name) = UNICODE_NAMES.get(&c) {
+        return Some(name.to_string());
+    }
+    match c as u32 {
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0x20000..=0x2A6DF => {
+            Some(format!("CJK UNIFIED IDEOGRAPH-{:04X}", c as u32))
+        }
+        _ => None,
+    }
+}
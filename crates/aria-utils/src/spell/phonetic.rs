@@ -0,0 +1,88 @@
+//! Spelling alphabets, one word per letter.
+//!
+//! Latin letters use the NATO alphabet. Other scripts use the alphabet of the
+//! language they are usually written in, so Cyrillic is spelled with the Russian
+//! one and needs a voice that speaks Russian to be understood.
+
+const NATO: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+    "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+    "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+/// The Greek letters from alpha to omega, as their names are said in English.
+const GREEK: [&str; 25] = [
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "iota",
+    "kappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "omicron",
+    "pi",
+    "rho",
+    "final sigma",
+    "sigma",
+    "tau",
+    "upsilon",
+    "phi",
+    "chi",
+    "psi",
+    "omega",
+];
+
+/// The Russian spelling alphabet, from А to Я.
+const RUSSIAN: [&str; 32] = [
+    "Анна",
+    "Борис",
+    "Василий",
+    "Григорий",
+    "Дмитрий",
+    "Елена",
+    "Женя",
+    "Зинаида",
+    "Иван",
+    "Иван краткий",
+    "Константин",
+    "Леонид",
+    "Михаил",
+    "Николай",
+    "Ольга",
+    "Павел",
+    "Роман",
+    "Семён",
+    "Татьяна",
+    "Ульяна",
+    "Фёдор",
+    "Харитон",
+    "Цапля",
+    "Человек",
+    "Шура",
+    "Щука",
+    "твёрдый знак",
+    "Еры",
+    "мягкий знак",
+    "Эхо",
+    "Юрий",
+    "Яков",
+];
+
+/// The spelling-alphabet word for a single letter of either case.
+pub(crate) fn phonetic_word(c: char) -> Option<&'static str> {
+    let lower = c.to_lowercase().next()?;
+    match lower {
+        'a'..='z' => Some(NATO[lower as usize - 'a' as usize]),
+        'α'..='ω' => Some(GREEK[lower as usize - 'α' as usize]),
+        'а'..='я' => Some(RUSSIAN[lower as usize - 'а' as usize]),
+        'ё' => Some("Ёжик"),
+        _ => None,
+    }
+}
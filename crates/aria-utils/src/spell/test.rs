@@ -0,0 +1,90 @@
+use super::*;
+
+fn descriptions(text: &str, level: SpellLevel) -> Vec<String> {
+    spell(text, level)
+        .into_iter()
+        .map(|spelled| spelled.description)
+        .collect()
+}
+
+#[test]
+fn characters_name_spaces_and_symbols() {
+    assert_eq!(
+        descriptions("Hi, 👍!", SpellLevel::Characters),
+        vec!["H", "i", "comma", "space", "thumbs up", "exclamation mark"]
+    );
+    assert_eq!(
+        descriptions("a\tb\r\n", SpellLevel::Characters),
+        vec!["a", "tab", "b", "new line"]
+    );
+}
+
+#[test]
+fn phonetic_alphabets() {
+    assert_eq!(
+        descriptions("Ab1", SpellLevel::Phonetic),
+        vec!["Alfa", "Bravo", "1"]
+    );
+    assert_eq!(
+        descriptions("Σω", SpellLevel::Phonetic),
+        vec!["sigma", "omega"]
+    );
+    assert_eq!(
+        descriptions("Дё", SpellLevel::Phonetic),
+        vec!["Дмитрий", "Ёжик"]
+    );
+}
+
+#[test]
+fn phonetic_names_diacritics() {
+    assert_eq!(
+        descriptions("é", SpellLevel::Phonetic),
+        vec!["Echo with acute"]
+    );
+    assert_eq!(
+        descriptions("e\u{301}", SpellLevel::Phonetic),
+        vec!["Echo with acute accent"]
+    );
+    assert_eq!(
+        descriptions("Ü", SpellLevel::Phonetic),
+        vec!["Uniform with diaeresis"]
+    );
+}
+
+#[test]
+fn unicode_names_and_code_points() {
+    assert_eq!(
+        descriptions("a€", SpellLevel::Unicode),
+        vec!["latin small letter a, U+0061", "euro sign, U+20AC"]
+    );
+    assert_eq!(
+        descriptions("e\u{301}", SpellLevel::Unicode),
+        vec!["latin small letter e, U+0065, combining acute accent, U+0301"]
+    );
+    assert_eq!(
+        descriptions("中\u{10FFFD}", SpellLevel::Unicode),
+        vec!["cjk unified ideograph-4e2d, U+4E2D", "U+10FFFD"]
+    );
+}
+
+#[test]
+fn graphemes_are_one_unit() {
+    let spelled = spell("x𝕥e\u{301}👍🏽", SpellLevel::Characters);
+    let units: Vec<_> = spelled
+        .iter()
+        .map(|s| (s.grapheme, s.u16range.clone()))
+        .collect();
+    assert_eq!(
+        units,
+        vec![("x", 0..1), ("𝕥", 1..3), ("e\u{301}", 3..5), ("👍🏽", 5..9)]
+    );
+    assert_eq!(spelled[3].description, "thumbs up medium skin tone");
+}
+
+#[test]
+fn levels_cycle() {
+    let level = SpellLevel::default();
+    assert_eq!(level, SpellLevel::Characters);
+    assert_eq!(level.next().next(), SpellLevel::Unicode);
+    assert_eq!(level.next().next().next(), SpellLevel::Characters);
+}
@@ -0,0 +1,213 @@
+//! Spoken names of punctuation and symbols.
+//!
+//! Every symbol in [`SYMBOLS`] has a minimum [`PunctuationLevel`]. At that level
+//! and above it is spoken by name, e.g. `foo(bar)` is read as "foo open paren bar
+//! close paren" from [`PunctuationLevel::Most`] on. Below it the symbol is left to
+//! the synthesizer, which usually pauses at it or skips it.
+
+use std::borrow::Cow;
+
+use crate::clean_text::{CleanerStage, Pair};
+use crate::config::PunctuationLevel;
+
+/// A symbol, its spoken name and the level from which the name is spoken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolName {
+    pub symbol: char,
+    pub name: &'static str,
+    pub level: PunctuationLevel,
+}
+
+const fn symbol(symbol: char, name: &'static str, level: PunctuationLevel) -> SymbolName {
+    SymbolName {
+        symbol,
+        name,
+        level,
+    }
+}
+
+/// Every symbol Aria can name. Sentence punctuation only comes at
+/// [`PunctuationLevel::All`], since the synthesizer already conveys it by pausing.
+pub static SYMBOLS: &[SymbolName] = &[
+    // Symbols that change the meaning of the text.
+    symbol('#', "number", PunctuationLevel::Some),
+    symbol('$', "dollar", PunctuationLevel::Some),
+    symbol('%', "percent", PunctuationLevel::Some),
+    symbol('&', "and", PunctuationLevel::Some),
+    symbol('*', "star", PunctuationLevel::Some),
+    symbol('+', "plus", PunctuationLevel::Some),
+    symbol('/', "slash", PunctuationLevel::Some),
+    symbol('\\', "backslash", PunctuationLevel::Some),
+    symbol('<', "less than", PunctuationLevel::Some),
+    symbol('=', "equals", PunctuationLevel::Some),
+    symbol('>', "greater than", PunctuationLevel::Some),
+    symbol('@', "at", PunctuationLevel::Some),
+    symbol('^', "caret", PunctuationLevel::Some),
+    symbol('_', "underline", PunctuationLevel::Some),
+    symbol('|', "bar", PunctuationLevel::Some),
+    symbol('~', "tilde", PunctuationLevel::Some),
+    symbol('€', "euro", PunctuationLevel::Some),
+    symbol('£', "pound", PunctuationLevel::Some),
+    symbol('¥', "yen", PunctuationLevel::Some),
+    symbol('¢', "cents", PunctuationLevel::Some),
+    symbol('§', "section", PunctuationLevel::Some),
+    symbol('¶', "pilcrow", PunctuationLevel::Some),
+    symbol('©', "copyright", PunctuationLevel::Some),
+    symbol('®', "registered", PunctuationLevel::Some),
+    symbol('™', "trademark", PunctuationLevel::Some),
+    symbol('°', "degrees", PunctuationLevel::Some),
+    symbol('±', "plus or minus", PunctuationLevel::Some),
+    symbol('×', "times", PunctuationLevel::Some),
+    symbol('÷', "divided by", PunctuationLevel::Some),
+    symbol('•', "bullet", PunctuationLevel::Some),
+    // Brackets, quotes and separators.
+    symbol('(', "open paren", PunctuationLevel::Most),
+    symbol(')', "close paren", PunctuationLevel::Most),
+    symbol('[', "open bracket", PunctuationLevel::Most),
+    symbol(']', "close bracket", PunctuationLevel::Most),
+    symbol('{', "open brace", PunctuationLevel::Most),
+    symbol('}', "close brace", PunctuationLevel::Most),
+    symbol('"', "quote", PunctuationLevel::Most),
+    symbol('“', "open quote", PunctuationLevel::Most),
+    symbol('”', "close quote", PunctuationLevel::Most),
+    symbol('«', "open angle quote", PunctuationLevel::Most),
+    symbol('»', "close angle quote", PunctuationLevel::Most),
+    symbol('`', "backtick", PunctuationLevel::Most),
+    symbol('-', "dash", PunctuationLevel::Most),
+    symbol('–', "en dash", PunctuationLevel::Most),
+    symbol('—', "em dash", PunctuationLevel::Most),
+    symbol(':', "colon", PunctuationLevel::Most),
+    symbol(';', "semicolon", PunctuationLevel::Most),
+    symbol('…', "ellipsis", PunctuationLevel::Most),
+    // Sentence punctuation and apostrophes.
+    symbol('.', "dot", PunctuationLevel::All),
+    symbol(',', "comma", PunctuationLevel::All),
+    symbol('?', "question mark", PunctuationLevel::All),
+    symbol('!', "exclamation mark", PunctuationLevel::All),
+    symbol('¿', "inverted question mark", PunctuationLevel::All),
+    symbol('¡', "inverted exclamation mark", PunctuationLevel::All),
+    symbol('\'', "apostrophe", PunctuationLevel::All),
+    symbol('‘', "open single quote", PunctuationLevel::All),
+    symbol('’', "close single quote", PunctuationLevel::All),
+];
+
+/// The name of `c` if it is spoken at `level`.
+pub fn symbol_name(c: char, level: PunctuationLevel) -> Option<&'static str> {
+    SYMBOLS
+        .iter()
+        .find(|s| s.symbol == c)
+        .filter(|s| s.level <= level)
+        .map(|s| s.name)
+}
+
+/// Replace every symbol spoken at `level` by its name, separated from the
+/// surrounding text by spaces.
+pub fn speak_symbols(text: &str, level: PunctuationLevel) -> Cow<'_, str> {
+    if !text.chars().any(|c| symbol_name(c, level).is_some()) {
+        return Cow::Borrowed(text);
+    }
+    name_symbols(text, level, false)
+        .into_iter()
+        .map(|(o, r)| r.unwrap_or_else(|| o.into()))
+        .collect::<String>()
+        .into()
+}
+
+/// The symbols of `text` spoken at `level`, each paired with its name. A name is
+/// set off by spaces from the text around it, and with `edges` also from whatever
+/// comes before and after `text`.
+fn name_symbols(text: &str, level: PunctuationLevel, edges: bool) -> Vec<Pair<'_>> {
+    let mut pairs = Vec::new();
+    let mut kept = 0;
+    let mut previous = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if let Some(name) = symbol_name(c, level) {
+            // A symbol right after this one adds the space between their names.
+            let before = previous.map_or(edges, |p: char| !p.is_whitespace());
+            let after = chars.peek().map_or(edges, |&(_, next)| {
+                !next.is_whitespace() && symbol_name(next, level).is_none()
+            });
+            if kept < i {
+                pairs.push((&text[kept..i], None));
+            }
+            kept = i + c.len_utf8();
+            let space = |yes| if yes { " " } else { "" };
+            let spoken = format!("{}{}{}", space(before), name, space(after));
+            pairs.push((&text[i..kept], Some(spoken.into())));
+        }
+        previous = Some(c);
+    }
+    if kept < text.len() {
+        pairs.push((&text[kept..], None));
+    }
+    pairs
+}
+
+/// A [`CleanerStage`] that names the symbols spoken at its level, like
+/// [`speak_symbols`]. Pieces left by earlier stages may continue text they
+/// replaced, so names at the start or end of a piece are set off by a space too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolNames(pub PunctuationLevel);
+
+impl CleanerStage for SymbolNames {
+    fn clean<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = Pair<'a>> + 'a> {
+        Box::new(name_symbols(text, self.0, true).into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_add_more_symbols() {
+        let code = "if (a >= b) { c(); }";
+        assert_eq!(speak_symbols(code, PunctuationLevel::None), code);
+        assert_eq!(
+            speak_symbols(code, PunctuationLevel::Some),
+            "if (a greater than equals b) { c(); }"
+        );
+        assert_eq!(
+            speak_symbols(code, PunctuationLevel::Most),
+            "if open paren a greater than equals b close paren open brace c open paren close paren semicolon close brace"
+        );
+        assert_eq!(
+            speak_symbols("Hi, it's me.", PunctuationLevel::All),
+            "Hi comma it apostrophe s me dot"
+        );
+    }
+
+    #[test]
+    fn text_without_symbols_is_borrowed() {
+        assert!(matches!(
+            speak_symbols("Hello, world.", PunctuationLevel::Most),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn the_stage_skips_replaced_text() {
+        use crate::clean_text::{run_stages, RegexCleanerPair};
+
+        let phonemes = RegexCleanerPair::new("hi", "[[h@I]]".to_string()).unwrap();
+        let symbols = SymbolNames(PunctuationLevel::Some);
+        let stages: [&dyn CleanerStage; 2] = [&phonemes, &symbols];
+        let spoken: String = run_stages("hi@home", stages)
+            .map(|(o, r)| r.unwrap_or_else(|| o.into()))
+            .collect();
+        assert_eq!(spoken, "[[h@I]] at home");
+    }
+
+    #[test]
+    fn every_symbol_is_listed_once() {
+        for (i, s) in SYMBOLS.iter().enumerate() {
+            assert!(s.level > PunctuationLevel::None, "{:?}", s);
+            assert!(
+                SYMBOLS[..i].iter().all(|other| other.symbol != s.symbol),
+                "{:?}",
+                s
+            );
+        }
+    }
+}
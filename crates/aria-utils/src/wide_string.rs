@@ -1,10 +1,17 @@
-use std::ffi::OsStr;
 use std::ops::Range;
-use std::os::windows::ffi::OsStrExt;
 
+/// A NUL-terminated UTF-16 string, as expected by Win32 and WinRT APIs.
+///
+/// Encoding goes through `str::encode_utf16`, so this works on every platform.
 #[derive(Debug, PartialEq)]
 pub struct WideString(Vec<u16>);
 
+impl Default for WideString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WideString {
     pub fn new() -> WideString {
         WideString(vec![0])
@@ -16,6 +23,10 @@ impl WideString {
     pub fn len(&self) -> usize {
         self.0.len()
     }
+    /// Whether the string holds nothing but its NUL terminator.
+    pub fn is_empty(&self) -> bool {
+        self.0.len() <= 1
+    }
 
     /// Returns a raw pointer to the slice's buffer.
     ///
@@ -37,8 +48,7 @@ impl WideString {
 
 impl<T: ::std::borrow::Borrow<str>> From<T> for WideString {
     fn from(instring: T) -> Self {
-        let osstr: &OsStr = instring.borrow().as_ref();
-        let mut out: Vec<u16> = osstr.encode_wide().collect();
+        let mut out: Vec<u16> = instring.borrow().encode_utf16().collect();
         out.push(0);
         WideString::from_raw(out)
     }
@@ -48,8 +58,7 @@ impl<T: ::std::borrow::Borrow<str>> ::std::iter::FromIterator<T> for WideString
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         let mut out: Vec<u16> = Vec::new();
         for i in iter {
-            let instr: &OsStr = i.borrow().as_ref();
-            out.extend(instr.encode_wide());
+            out.extend(i.borrow().encode_utf16());
         }
         out.push(0);
         WideString::from_raw(out)
@@ -104,7 +113,6 @@ impl IndicesUtf for str {
     }
 }
 
-#[allow(dead_code)]
 pub fn convert_range<T>(v: &[T], r: &Range<T>) -> Range<usize>
 where
     T: Ord,
@@ -114,7 +122,6 @@ where
     lo..(hi - 1)
 }
 
-#[allow(dead_code)]
 pub fn lookup_range<T>(v: &[T], r: &Range<usize>) -> Range<T>
 where
     T: Clone,
@@ -122,7 +129,6 @@ where
     v[r.start].clone()..v[r.end].clone()
 }
 
-#[allow(dead_code)]
 pub fn invert_idx<I, O>(i: &[I], o: &[O], r: &Range<O>) -> Range<I>
 where
     O: Ord,
@@ -137,14 +143,12 @@ pub fn str_from_str_u16idx<'a>(s: &'a str, idx: &Range<usize>) -> &'a str {
     &s[u8idx_from_u16idx(s, idx)]
 }
 
-#[allow(dead_code)]
 pub fn u8idx_from_u16idx(s: &str, idx: &Range<usize>) -> Range<usize> {
     let mut u16idx = 0;
     let mut out = 0..0;
     for c in s.chars() {
         if u16idx <= idx.start {
             out.start = out.end;
-            println!("{:?}", out.start);
         }
         out.end += c.len_utf8();
         u16idx += c.len_utf16();
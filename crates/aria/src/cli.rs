@@ -1,7 +1,15 @@
-use tokio::sync::mpsc;
+use std::path::PathBuf;
 
-use aria_core::driver::WindowsDriver;
+use aria_tts::engine::{configured_engine, Prosody};
+use aria_tts::error::TTSError;
+use aria_tts::event::SpeechEvent;
+use aria_tts::queue::Utterance;
+use aria_tts::state::SpeechState;
 use aria_tts::tts::TTS;
+use aria_utils::config::get_config;
+use aria_utils::pronunciation::{
+    load_pronunciation, save_pronunciation, DictionaryScope, MatchKind, PronunciationEntry,
+};
 use clap::Parser;
 
 /// CLI usage for Aria
@@ -20,37 +28,142 @@ pub enum Command {
     /// List all available TTS voices on the system.
     Voices,
     /// Speak text using TTS with optional voice selection.
-    Speak {
-        /// Text to speak for testing.
-        #[clap(default_value = "Hello, this is a test of the Aria TTS system.")]
-        text: String,
-        /// Voice to use for this test (by display name).
+    Speak(SpeakArgs),
+    /// Edit the pronunciation dictionaries in pronunciation.toml.
+    #[clap(subcommand, alias = "dict")]
+    Dictionary(DictionaryCommand),
+}
+
+/// Arguments of the `speak` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct SpeakArgs {
+    /// Text to speak for testing.
+    #[clap(default_value = "Hello, this is a test of the Aria TTS system.")]
+    pub text: String,
+    /// Voice to use, e.g. `Zira`, `id:<voice id>` or `lang:de-AT, gender:female`.
+    #[clap(short, long)]
+    pub voice: Option<String>,
+    /// Speech rate multiplier, overriding aria.toml (0.5 to 6.0).
+    #[clap(long)]
+    pub rate: Option<f64>,
+    /// Pitch, overriding aria.toml (0.0 to 2.0, 1.0 is the voice's natural pitch).
+    #[clap(long)]
+    pub pitch: Option<f64>,
+    /// Volume, overriding aria.toml (0.0 to 1.0).
+    #[clap(long)]
+    pub volume: Option<f64>,
+    /// Save the speech to this WAV file instead of playing it.
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Subcommands of `dictionary`.
+#[derive(clap::Subcommand, Debug)]
+pub enum DictionaryCommand {
+    /// Add an entry to the end of a dictionary.
+    Add {
+        /// Word, text or regular expression to replace.
+        pattern: String,
+        /// Text or phoneme string to speak instead.
+        replacement: String,
+        /// How the pattern matches: `literal`, `whole-word` or `regex`.
+        #[clap(short, long, default_value_t = MatchKind::WholeWord)]
+        kind: MatchKind,
+        /// Only match the pattern with the same capitalization.
         #[clap(short, long)]
+        case_sensitive: bool,
+        #[clap(flatten)]
+        scope: ScopeArgs,
+    },
+    /// List the entries of a dictionary, or of all dictionaries.
+    List {
+        #[clap(flatten)]
+        scope: ScopeArgs,
+    },
+    /// Show how text is pronounced with the global dictionary and, if given,
+    /// those of a voice and an application.
+    Test {
+        text: String,
+        /// Voice whose dictionary applies, by display name or id.
+        #[clap(long)]
         voice: Option<String>,
+        /// Application whose dictionary applies, e.g. `notepad.exe`.
+        #[clap(long)]
+        app: Option<String>,
+    },
+    /// Remove an entry by the number `list` shows for it.
+    Remove {
+        number: usize,
+        #[clap(flatten)]
+        scope: ScopeArgs,
     },
 }
 
+/// Which dictionary a `dictionary` subcommand works on; the global one by default.
+#[derive(clap::Args, Debug)]
+pub struct ScopeArgs {
+    /// The dictionary of a voice, by display name or id.
+    #[clap(long, conflicts_with = "app")]
+    pub voice: Option<String>,
+    /// The dictionary of an application, e.g. `notepad.exe`.
+    #[clap(long)]
+    pub app: Option<String>,
+}
+
+impl ScopeArgs {
+    fn scope(&self) -> Option<DictionaryScope> {
+        match (&self.voice, &self.app) {
+            (Some(voice), _) => Some(DictionaryScope::Voice(voice.clone())),
+            (None, Some(app)) => Some(DictionaryScope::App(app.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+impl SpeakArgs {
+    fn prosody(&self) -> Prosody {
+        Prosody {
+            rate: self.rate,
+            pitch: self.pitch,
+            volume: self.volume,
+        }
+    }
+}
+
+#[cfg(all(windows, feature = "windows"))]
 pub async fn start_aria_cli() -> Result<(), Box<dyn std::error::Error>> {
+    use aria_core::driver::WindowsDriver;
+    use tokio::sync::mpsc;
+
     let (tx, mut rx) = mpsc::channel(1);
     ctrlc::set_handler(move || {
         let _ = tx.blocking_send(());
     })?;
 
-    WindowsDriver::start().await?;
+    WindowsDriver::start(configured_engine()?).await?;
     rx.recv().await.ok_or("Failed to receive Ctrl-C signal")?;
     WindowsDriver::stop().await?;
     Ok(())
 }
 
+#[cfg(not(all(windows, feature = "windows")))]
+pub async fn start_aria_cli() -> Result<(), Box<dyn std::error::Error>> {
+    Err(
+        "The Aria screen reader is only available on Windows builds with the `windows` feature."
+            .into(),
+    )
+}
+
 pub async fn list_voices() -> Result<(), Box<dyn std::error::Error>> {
-    let voices = TTS::get_available_voices().await?;
+    let tts = TTS::new(configured_engine()?);
+    let voices = tts.get_available_voices().await?;
 
     println!("Available TTS Voices:");
 
     for voice in voices.iter() {
         println!(
-            "- {} ({}, {})",
-            voice.display_name, voice.language, voice.gender
+            "- {} ({}, {}) id:{}",
+            voice.display_name, voice.language, voice.gender, voice.id
         );
     }
 
@@ -63,20 +176,128 @@ pub async fn list_voices() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-pub async fn speak_text(text: &str, voice: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
-    // Set voice if specified
-    if let Some(voice_name) = voice {
-        let success = TTS::set_voice(voice_name).await?;
-        if !success {
+pub async fn speak_text(args: &SpeakArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let config = get_config()?;
+    let tts = TTS::new(configured_engine()?);
+    tts.set_pronunciation(load_pronunciation()?.compile()?);
+    tts.set_punctuation(config.punctuation);
+    tts.set_language(config.language).await?;
+
+    // Set the voice asked for, or else the one of aria.toml, which the engine may
+    // already use but the queue has to know to return to after other languages.
+    if let Some(selector) = args.voice.as_ref().or(config.voice.as_ref()) {
+        match tts.set_voice(selector).await {
+            Ok(voice) => println!("Using voice {}", voice.display_name),
+            Err(e @ TTSError::VoiceNotFound { .. }) => {
+                println!("Warning: {}. Using default voice", e);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    tts.set_language_voices(&config.language_voices).await?;
+
+    let utterance = Utterance::from(args.text.as_str()).with_prosody(args.prosody());
+
+    if let Some(output) = &args.output {
+        let audio = tts.synthesize(utterance).await?;
+        audio.write_wav(output)?;
+        println!(
+            "Saved {:.1}s of speech to {}",
+            audio.duration().as_secs_f64(),
+            output.display()
+        );
+        return Ok(());
+    }
+
+    // Speak the text a sentence at a time, until it is done or Ctrl+C stops it.
+    tts.set_state(SpeechState::Running).await?;
+    let mut events = tts.subscribe();
+    tokio::select! {
+        result = tts.speak_and_wait(utterance) => result?,
+        _ = tokio::signal::ctrl_c() => {
+            tts.stop().await?;
+            println!("Stopped");
+        }
+    }
+
+    // The queue only logs playback failures, so report them here.
+    while let Ok(event) = events.try_recv() {
+        if let SpeechEvent::Failed(reason) = event {
+            return Err(format!("playback failed: {}", reason).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn print_entries(number_from: usize, entries: &[PronunciationEntry]) {
+    for (number, entry) in entries.iter().enumerate() {
+        println!(
+            "{:>3}. {} -> {} ({}{})",
+            number + number_from,
+            entry.pattern,
+            entry.replacement,
+            entry.kind,
+            if entry.case_sensitive {
+                ", case-sensitive"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+pub fn edit_dictionary(command: &DictionaryCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dictionaries = load_pronunciation()?;
+
+    match command {
+        DictionaryCommand::Add {
+            pattern,
+            replacement,
+            kind,
+            case_sensitive,
+            scope,
+        } => {
+            let scope = scope.scope().unwrap_or(DictionaryScope::Global);
+            let entry = PronunciationEntry::new(pattern, replacement)
+                .with_kind(*kind)
+                .case_sensitive(*case_sensitive);
+            dictionaries.add(&scope, entry)?;
+            save_pronunciation(&dictionaries)?;
             println!(
-                "Warning: Voice '{}' not found, using default voice",
-                voice_name
+                "Added entry {} to the {} dictionary",
+                dictionaries.entries(&scope).len(),
+                scope
             );
         }
+        DictionaryCommand::List { scope } => {
+            let scopes = match scope.scope() {
+                Some(scope) => vec![scope],
+                None => dictionaries.scopes(),
+            };
+            if scopes.is_empty() {
+                println!("No pronunciation entries.");
+            }
+            for scope in scopes {
+                println!("{}:", scope);
+                print_entries(1, dictionaries.entries(&scope));
+            }
+        }
+        DictionaryCommand::Test { text, voice, app } => {
+            let voice: Vec<&str> = voice.iter().map(String::as_str).collect();
+            let pronunciation = dictionaries.compile()?;
+            println!("{}", pronunciation.apply(text, &voice, app.as_deref()));
+        }
+        DictionaryCommand::Remove { number, scope } => {
+            let scope = scope.scope().unwrap_or(DictionaryScope::Global);
+            let removed = number
+                .checked_sub(1)
+                .and_then(|index| dictionaries.remove(&scope, index))
+                .ok_or_else(|| format!("The {} dictionary has no entry {}", scope, number))?;
+            save_pronunciation(&dictionaries)?;
+            println!("Removed {} -> {}", removed.pattern, removed.replacement);
+        }
     }
 
-    // Speak the text and wait for completion
-    TTS::speak_and_wait(text, false).await?;
-
     Ok(())
 }
@@ -1,11 +1,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use aria::cli::{Args, Command};
-use aria::start_highlight_overlay;
-use aria_core::driver::WindowsDriver;
 use clap::Parser;
 use log::Level;
-use tokio::sync::mpsc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -19,9 +16,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match args.command {
         Some(Command::Start) => aria::cli::start_aria_cli().await?,
         Some(Command::Voices) => aria::cli::list_voices().await?,
-        Some(Command::Speak { text, voice }) => {
-            aria::cli::speak_text(&text, voice.as_deref()).await?
-        }
+        Some(Command::Speak(speak_args)) => aria::cli::speak_text(&speak_args).await?,
+        Some(Command::Dictionary(command)) => aria::cli::edit_dictionary(&command)?,
         None => {
             // No CLI command provided, start GUI mode
             start_aria_gui().await?;
@@ -31,7 +27,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[cfg(all(windows, feature = "windows"))]
 async fn start_aria_gui() -> Result<(), Box<dyn std::error::Error>> {
+    use aria::start_highlight_overlay;
+    use aria_core::driver::WindowsDriver;
+    use aria_tts::engine::configured_engine;
+    use tokio::sync::mpsc;
+
     // Set up shutdown signal handling for both Ctrl+C and window close
     let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
 
@@ -45,7 +47,7 @@ async fn start_aria_gui() -> Result<(), Box<dyn std::error::Error>> {
     let (highlight_sender, window_close_rx) = start_highlight_overlay();
 
     // Start the Windows driver with highlight functionality
-    WindowsDriver::start_with_highlight(Some(highlight_sender)).await?;
+    WindowsDriver::start_with_highlight(configured_engine()?, Some(highlight_sender)).await?;
 
     // Wait for either shutdown signal or window close
     tokio::select! {
@@ -65,3 +67,11 @@ async fn start_aria_gui() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(not(all(windows, feature = "windows")))]
+async fn start_aria_gui() -> Result<(), Box<dyn std::error::Error>> {
+    Err(
+        "The Aria screen reader is only available on Windows builds with the `windows` feature."
+            .into(),
+    )
+}